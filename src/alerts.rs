@@ -0,0 +1,154 @@
+use crate::models::{Subsystem, TelemetryPacket};
+use crate::monitor::{Alert, AlertLevel};
+use serde::Serialize;
+
+/// Cleared alerts kept as history; older ones are dropped first, so a
+/// flapping check cannot grow the list without bound.
+pub const MAX_CLEARED: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum AlertState {
+    /// Breach seen and not yet acknowledged.
+    Raised,
+    /// Acknowledged by an operator while the breach persists.
+    Acknowledged,
+    /// Telemetry is nominal again but the alert was never acknowledged.
+    ReturnedToNominal,
+    /// Acknowledged and nominal; kept only as history.
+    Cleared,
+}
+
+/// One deduplicated alert: every breach of the same rule while the alert is
+/// still open is folded into it.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct AlertRecord {
+    pub id: u64,
+    pub rule: String,
+    pub subsystem: Subsystem,
+    pub level: AlertLevel,
+    pub message: String, // Message of the latest occurrence
+    pub state: AlertState,
+    pub occurrences: u32,
+    pub first_seen: u64,
+    pub last_seen: u64,
+}
+
+impl AlertRecord {
+    pub fn is_open(&self) -> bool {
+        self.state != AlertState::Cleared
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct AlertManager {
+    records: Vec<AlertRecord>,
+    next_id: u64,
+}
+
+impl AlertManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Folds the alerts produced for `packet` into the alert list.
+    ///
+    /// Open alerts from the packet's subsystem whose rule did not fire this
+    /// time are treated as nominal: unacknowledged ones move to
+    /// `ReturnedToNominal`, acknowledged ones are cleared.
+    pub fn update(&mut self, packet: &TelemetryPacket, alerts: &[Alert]) {
+        self.raise(&packet.subsystem, alerts);
+
+        for record in &mut self.records {
+            if record.subsystem != packet.subsystem || alerts.iter().any(|a| a.rule == record.rule)
+            {
+                continue;
+            }
+            record.state = match record.state {
+                AlertState::Raised => AlertState::ReturnedToNominal,
+                AlertState::Acknowledged => AlertState::Cleared,
+                state => state,
+            };
+        }
+        self.prune();
+    }
+
+    /// Folds alerts that are not tied to a packet, such as stale-telemetry
+    /// alerts, into the alert list without treating anything as nominal.
+    pub fn raise(&mut self, subsystem: &Subsystem, alerts: &[Alert]) {
+        for alert in alerts {
+            match self
+                .records
+                .iter_mut()
+                .find(|r| r.is_open() && r.rule == alert.rule)
+            {
+                Some(record) => {
+                    record.occurrences += 1;
+                    record.last_seen = alert.timestamp;
+                    record.level = alert.level;
+                    record.message = alert.message.clone();
+                    if record.state == AlertState::ReturnedToNominal {
+                        record.state = AlertState::Raised;
+                    }
+                }
+                None => {
+                    self.next_id += 1;
+                    self.records.push(AlertRecord {
+                        id: self.next_id,
+                        rule: alert.rule.clone(),
+                        subsystem: subsystem.clone(),
+                        level: alert.level,
+                        message: alert.message.clone(),
+                        state: AlertState::Raised,
+                        occurrences: 1,
+                        first_seen: alert.timestamp,
+                        last_seen: alert.timestamp,
+                    });
+                }
+            }
+        }
+    }
+
+    /// Acknowledges an alert. Returns `false` if no open alert has this id.
+    pub fn acknowledge(&mut self, id: u64) -> bool {
+        let Some(record) = self.records.iter_mut().find(|r| r.id == id && r.is_open()) else {
+            return false;
+        };
+        record.state = match record.state {
+            AlertState::ReturnedToNominal => AlertState::Cleared,
+            _ => AlertState::Acknowledged,
+        };
+        self.prune();
+        true
+    }
+
+    /// Drops the oldest cleared alerts beyond `MAX_CLEARED`.
+    fn prune(&mut self) {
+        let cleared = self.records.iter().filter(|r| !r.is_open()).count();
+        let mut excess = cleared.saturating_sub(MAX_CLEARED);
+        if excess > 0 {
+            self.records.retain(|r| {
+                let drop = excess > 0 && !r.is_open();
+                excess -= usize::from(drop);
+                !drop
+            });
+        }
+    }
+
+    /// Alerts that are not yet cleared, oldest first.
+    pub fn open(&self) -> impl Iterator<Item = &AlertRecord> {
+        self.records.iter().filter(|r| r.is_open())
+    }
+
+    /// Every alert, including the last `MAX_CLEARED` cleared ones, oldest first.
+    pub fn all(&self) -> &[AlertRecord] {
+        &self.records
+    }
+
+    pub fn get(&self, id: u64) -> Option<&AlertRecord> {
+        self.records.iter().find(|r| r.id == id)
+    }
+
+    pub fn clear(&mut self) {
+        self.records.clear();
+    }
+}
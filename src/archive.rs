@@ -0,0 +1,494 @@
+use crate::models::{Subsystem, TelemetryPacket};
+use crate::monitor::Alert;
+use crate::pipeline::PacketSink;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Records written to one segment file before a new one is started.
+pub const DEFAULT_SEGMENT_RECORDS: usize = 10_000;
+
+/// Gap between packet timestamps, in seconds, that starts a new pass.
+pub const DEFAULT_PASS_GAP_SECONDS: u64 = 600;
+
+const INDEX_FILE: &str = "index.json";
+
+#[derive(Error, Debug)]
+pub enum ArchiveError {
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// One line of a segment file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ArchiveRecord {
+    Packet(TelemetryPacket),
+    Alert(ArchivedAlert),
+}
+
+impl ArchiveRecord {
+    fn timestamp(&self) -> u64 {
+        match self {
+            ArchiveRecord::Packet(packet) => packet.timestamp,
+            ArchiveRecord::Alert(alert) => alert.alert.timestamp,
+        }
+    }
+
+    fn subsystem(&self) -> &Subsystem {
+        match self {
+            ArchiveRecord::Packet(packet) => &packet.subsystem,
+            ArchiveRecord::Alert(alert) => &alert.subsystem,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArchivedAlert {
+    pub subsystem: Subsystem,
+    pub alert: Alert,
+}
+
+/// Index entry for one segment file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Segment {
+    pub file: String,
+    pub records: usize,
+    pub first_timestamp: Option<u64>, // Earliest record timestamp
+    pub last_timestamp: Option<u64>,  // Latest record timestamp
+    #[serde(default)]
+    pub pass_gap_seconds: Option<u64>, // Gap `passes` was split with
+    #[serde(default)]
+    pub passes: Vec<Pass>, // Packet runs within this segment
+}
+
+impl Segment {
+    fn new(file: String, pass_gap_seconds: u64) -> Self {
+        Self {
+            file,
+            records: 0,
+            first_timestamp: None,
+            last_timestamp: None,
+            pass_gap_seconds: Some(pass_gap_seconds),
+            passes: Vec::new(),
+        }
+    }
+
+    fn add(&mut self, record: &ArchiveRecord) {
+        let timestamp = record.timestamp();
+        self.records += 1;
+        self.first_timestamp = Some(self.first_timestamp.map_or(timestamp, |t| t.min(timestamp)));
+        self.last_timestamp = Some(self.last_timestamp.map_or(timestamp, |t| t.max(timestamp)));
+        if let (ArchiveRecord::Packet(_), Some(gap)) = (record, self.pass_gap_seconds) {
+            let pass = Pass {
+                start: timestamp,
+                end: timestamp,
+                packets: 1,
+            };
+            add_pass(&mut self.passes, pass, gap);
+        }
+    }
+
+    /// Segment number from a `segment-NNNNNN.jsonl` file name.
+    fn number(&self) -> Option<usize> {
+        self.file
+            .strip_prefix("segment-")?
+            .strip_suffix(".jsonl")?
+            .parse()
+            .ok()
+    }
+
+    fn overlaps(&self, query: &ArchiveQuery) -> bool {
+        let (Some(first), Some(last)) = (self.first_timestamp, self.last_timestamp) else {
+            return false;
+        };
+        query.start.is_none_or(|start| last >= start) && query.end.is_none_or(|end| first <= end)
+    }
+}
+
+/// Filter for archive queries. Time bounds are inclusive.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ArchiveQuery {
+    pub subsystem: Option<Subsystem>,
+    pub start: Option<u64>,
+    pub end: Option<u64>,
+}
+
+impl ArchiveQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn subsystem(mut self, subsystem: Subsystem) -> Self {
+        self.subsystem = Some(subsystem);
+        self
+    }
+
+    pub fn range(mut self, start: u64, end: u64) -> Self {
+        self.start = Some(start);
+        self.end = Some(end);
+        self
+    }
+
+    pub fn matches(&self, timestamp: u64, subsystem: &Subsystem) -> bool {
+        self.subsystem.as_ref().is_none_or(|s| s == subsystem)
+            && self.start.is_none_or(|start| timestamp >= start)
+            && self.end.is_none_or(|end| timestamp <= end)
+    }
+}
+
+/// A run of packets with no gap longer than the gap used to split passes.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Pass {
+    pub start: u64,
+    pub end: u64,
+    pub packets: usize,
+}
+
+/// Append-only store of packets and alerts.
+///
+/// Records are written as JSON lines to numbered segment files in one
+/// directory. `index.json` keeps the record count, time range and passes of
+/// each segment so queries can skip segments outside the requested range and
+/// passes need no reads; the active segment is rescanned on open in case it
+/// was not closed cleanly. Each record is flushed as it is appended. NaN and
+/// infinite values are written as `null` and read back as NaN.
+pub struct Archive {
+    dir: PathBuf,
+    segment_records: usize,
+    pass_gap_seconds: u64,
+    segments: Vec<Segment>,
+    writer: Option<BufWriter<File>>, // Appends to the last segment
+}
+
+impl Archive {
+    /// Opens the archive in `dir`, creating the directory if needed.
+    pub fn open(dir: impl AsRef<Path>) -> Result<Self, ArchiveError> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+
+        let mut segments: Vec<Segment> = match fs::read_to_string(dir.join(INDEX_FILE)) {
+            Ok(index) => serde_json::from_str(&index)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut files: Vec<String> = fs::read_dir(&dir)?
+            .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+            .filter(|name| name.starts_with("segment-") && name.ends_with(".jsonl"))
+            .collect();
+        files.sort();
+        segments.retain(|s| files.contains(&s.file));
+        if let Some(last) = files.last() {
+            terminate_last_line(&dir.join(last))?;
+        }
+        for file in &files {
+            let last = files.last() == Some(file);
+            if last || !segments.iter().any(|s| &s.file == file) {
+                let segment = scan_segment(&dir, file, DEFAULT_PASS_GAP_SECONDS)?;
+                match segments.iter_mut().find(|s| &s.file == file) {
+                    Some(existing) => *existing = segment,
+                    None => segments.push(segment),
+                }
+            }
+        }
+        segments.sort_by(|a, b| a.file.cmp(&b.file));
+
+        Ok(Self {
+            dir,
+            segment_records: DEFAULT_SEGMENT_RECORDS,
+            pass_gap_seconds: DEFAULT_PASS_GAP_SECONDS,
+            segments,
+            writer: None,
+        })
+    }
+
+    /// Sets how many records go into a segment before a new one is started.
+    pub fn segment_records(mut self, records: usize) -> Self {
+        self.segment_records = records.max(1);
+        self
+    }
+
+    /// Sets the gap between packet timestamps that starts a new pass.
+    pub fn pass_gap_seconds(mut self, seconds: u64) -> Self {
+        self.pass_gap_seconds = seconds;
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    pub fn append_packet(&mut self, packet: &TelemetryPacket) -> Result<(), ArchiveError> {
+        self.append(&ArchiveRecord::Packet(packet.clone()))
+    }
+
+    pub fn append_alert(
+        &mut self,
+        subsystem: &Subsystem,
+        alert: &Alert,
+    ) -> Result<(), ArchiveError> {
+        self.append(&ArchiveRecord::Alert(ArchivedAlert {
+            subsystem: subsystem.clone(),
+            alert: alert.clone(),
+        }))
+    }
+
+    /// Appends `record` to the last segment and flushes it to the file.
+    pub fn append(&mut self, record: &ArchiveRecord) -> Result<(), ArchiveError> {
+        let full = self
+            .segments
+            .last()
+            .is_none_or(|s| s.records >= self.segment_records);
+        if full {
+            self.start_segment()?;
+        }
+        if self.writer.is_none() {
+            let file = self.segments.last().expect("segment exists").file.clone();
+            let file = OpenOptions::new().append(true).open(self.dir.join(file))?;
+            self.writer = Some(BufWriter::new(file));
+        }
+
+        let writer = self.writer.as_mut().expect("writer is open");
+        serde_json::to_writer(&mut *writer, record)?;
+        writer.write_all(b"\n")?;
+        writer.flush()?;
+        self.segments
+            .last_mut()
+            .expect("segment exists")
+            .add(record);
+        Ok(())
+    }
+
+    /// Writes buffered records and the index to disk.
+    pub fn flush(&mut self) -> Result<(), ArchiveError> {
+        if let Some(writer) = &mut self.writer {
+            writer.flush()?;
+        }
+        self.write_index()
+    }
+
+    fn start_segment(&mut self) -> Result<(), ArchiveError> {
+        self.flush()?;
+        self.writer = None;
+
+        let number = self.segments.iter().filter_map(Segment::number).max();
+        let file = format!("segment-{:06}.jsonl", number.unwrap_or(0) + 1);
+        OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(self.dir.join(&file))?;
+        self.segments
+            .push(Segment::new(file, self.pass_gap_seconds));
+        self.write_index()
+    }
+
+    fn write_index(&self) -> Result<(), ArchiveError> {
+        let tmp = self.dir.join(format!("{}.tmp", INDEX_FILE));
+        fs::write(&tmp, serde_json::to_string_pretty(&self.segments)?)?;
+        fs::rename(tmp, self.dir.join(INDEX_FILE))?;
+        Ok(())
+    }
+
+    /// A read-only view of the records appended so far, which can be
+    /// queried without holding on to the archive.
+    pub fn reader(&self) -> ArchiveReader {
+        ArchiveReader {
+            dir: self.dir.clone(),
+            pass_gap_seconds: self.pass_gap_seconds,
+            segments: self.segments.clone(),
+        }
+    }
+
+    pub fn query(&self, query: &ArchiveQuery) -> Result<Vec<ArchiveRecord>, ArchiveError> {
+        self.reader().query(query)
+    }
+
+    pub fn packets(&self, query: &ArchiveQuery) -> Result<Vec<TelemetryPacket>, ArchiveError> {
+        self.reader().packets(query)
+    }
+
+    pub fn alerts(&self, query: &ArchiveQuery) -> Result<Vec<ArchivedAlert>, ArchiveError> {
+        self.reader().alerts(query)
+    }
+
+    /// Splits the archived packets into passes, see `ArchiveReader::passes`.
+    /// Segments indexed with a different gap are rescanned once and the
+    /// index is updated.
+    pub fn passes(&mut self) -> Result<Vec<Pass>, ArchiveError> {
+        let gap = self.pass_gap_seconds;
+        if self
+            .segments
+            .iter()
+            .any(|s| s.pass_gap_seconds != Some(gap))
+        {
+            self.flush()?;
+            for segment in &mut self.segments {
+                if segment.pass_gap_seconds != Some(gap) {
+                    *segment = scan_segment(&self.dir, &segment.file, gap)?;
+                }
+            }
+            self.write_index()?;
+        }
+        self.reader().passes()
+    }
+}
+
+/// Segments of an `Archive` as they were when `Archive::reader` was called.
+///
+/// Records appended later to the last of those segments may also be
+/// returned.
+#[derive(Debug, Clone)]
+pub struct ArchiveReader {
+    dir: PathBuf,
+    pass_gap_seconds: u64,
+    segments: Vec<Segment>,
+}
+
+impl ArchiveReader {
+    /// Every record matching `query`, ordered by timestamp and then by
+    /// write order.
+    pub fn query(&self, query: &ArchiveQuery) -> Result<Vec<ArchiveRecord>, ArchiveError> {
+        let mut records = Vec::new();
+        for segment in self.segments.iter().filter(|s| s.overlaps(query)) {
+            read_segment(&self.dir, &segment.file, |record| {
+                if query.matches(record.timestamp(), record.subsystem()) {
+                    records.push(record);
+                }
+            })?;
+        }
+        records.sort_by_key(|r| r.timestamp());
+        Ok(records)
+    }
+
+    pub fn packets(&self, query: &ArchiveQuery) -> Result<Vec<TelemetryPacket>, ArchiveError> {
+        Ok(self
+            .query(query)?
+            .into_iter()
+            .filter_map(|record| match record {
+                ArchiveRecord::Packet(packet) => Some(packet),
+                ArchiveRecord::Alert(_) => None,
+            })
+            .collect())
+    }
+
+    pub fn alerts(&self, query: &ArchiveQuery) -> Result<Vec<ArchivedAlert>, ArchiveError> {
+        Ok(self
+            .query(query)?
+            .into_iter()
+            .filter_map(|record| match record {
+                ArchiveRecord::Alert(alert) => Some(alert),
+                ArchiveRecord::Packet(_) => None,
+            })
+            .collect())
+    }
+
+    /// Splits the archived packets into passes wherever consecutive packet
+    /// timestamps are more than the pass gap apart.
+    ///
+    /// Passes come from the index; only segments indexed with a different
+    /// gap are read.
+    pub fn passes(&self) -> Result<Vec<Pass>, ArchiveError> {
+        let gap = self.pass_gap_seconds;
+        let mut passes = Vec::new();
+        for segment in &self.segments {
+            let rescanned;
+            let segment = if segment.pass_gap_seconds == Some(gap) {
+                segment
+            } else {
+                rescanned = scan_segment(&self.dir, &segment.file, gap)?;
+                &rescanned
+            };
+            for pass in &segment.passes {
+                add_pass(&mut passes, *pass, gap);
+            }
+        }
+        Ok(passes)
+    }
+}
+
+impl Drop for Archive {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+impl PacketSink for Archive {
+    fn process(&mut self, packet: &TelemetryPacket, alerts: &[Alert]) {
+        let result = self.append_packet(packet).and_then(|()| {
+            alerts
+                .iter()
+                .try_for_each(|alert| self.append_alert(&packet.subsystem, alert))
+        });
+        if let Err(e) = result {
+            log::warn!("Failed to archive packet: {}", e);
+        }
+    }
+
+    fn alert(&mut self, subsystem: &Subsystem, alert: &Alert) {
+        if let Err(e) = self.append_alert(subsystem, alert) {
+            log::warn!("Failed to archive alert: {}", e);
+        }
+    }
+}
+
+fn read_segment(
+    dir: &Path,
+    file: &str,
+    mut f: impl FnMut(ArchiveRecord),
+) -> Result<(), ArchiveError> {
+    let reader = BufReader::new(File::open(dir.join(file))?);
+    for line in reader.lines() {
+        let line = line?;
+        if line.is_empty() {
+            continue;
+        }
+        match serde_json::from_str(&line) {
+            Ok(record) => f(record),
+            // A line torn by a crash; the records around it are intact.
+            Err(e) if e.is_eof() => continue,
+            Err(e) => log::warn!("Skipping undecodable record in {}: {}", file, e),
+        }
+    }
+    Ok(())
+}
+
+fn scan_segment(dir: &Path, file: &str, pass_gap_seconds: u64) -> Result<Segment, ArchiveError> {
+    let mut segment = Segment::new(file.to_string(), pass_gap_seconds);
+    read_segment(dir, file, |record| segment.add(&record))?;
+    Ok(segment)
+}
+
+/// Inserts `pass` into `passes`, which are sorted and more than `gap`
+/// apart, merging it with every pass it comes within `gap` of.
+fn add_pass(passes: &mut Vec<Pass>, mut pass: Pass, gap: u64) {
+    let first = passes.partition_point(|p| p.end.saturating_add(gap) < pass.start);
+    let mut last = first;
+    while last < passes.len() && passes[last].start <= pass.end.saturating_add(gap) {
+        let other = passes[last];
+        pass.start = pass.start.min(other.start);
+        pass.end = pass.end.max(other.end);
+        pass.packets += other.packets;
+        last += 1;
+    }
+    passes.splice(first..last, [pass]);
+}
+
+/// Ends a torn last line so new records start on a line of their own.
+fn terminate_last_line(path: &Path) -> Result<(), ArchiveError> {
+    let contents = fs::read(path)?;
+    if contents.last().is_some_and(|b| *b != b'\n') {
+        OpenOptions::new()
+            .append(true)
+            .open(path)?
+            .write_all(b"\n")?;
+    }
+    Ok(())
+}
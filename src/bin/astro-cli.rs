@@ -0,0 +1,333 @@
+use astro_monitor::{
+    AlertLevel, CalibrationSet, Config, CrcMode, Monitor, PacketDefinitions, PacketFramer, Parser,
+    PowerAnalytics, RuleSet, TelemetryStats,
+};
+use std::net::UdpSocket;
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+const USAGE: &str = "\
+Usage: astro-cli <COMMAND> <DUMP_FILE> [OPTIONS]
+
+Commands:
+  decode     Print every packet in a raw dump file
+  analyze    Run the monitor over a dump file and print alerts
+  stats      Print per-subsystem packet counts and field statistics
+  send       Send each packet in a dump file as one UDP datagram
+
+Options:
+  --json                Print JSON lines instead of text (decode, analyze, stats)
+  --rules <PATH>        Load monitor rules from a TOML or JSON file (analyze)
+  --config <PATH>       Take calibrations, the battery model and packet definitions from a
+                        dashboard config file
+  --definitions <PATH>  Load packet definitions for new instruments from a TOML file
+  --crc <MODE>          CRC trailer: required, optional (default) or absent
+  --to <ADDR>           Destination address, default 127.0.0.1:5005 (send)
+  --interval <MS>       Delay between packets in milliseconds, default 1000 (send)
+
+Exit status: 0 on success, 1 if analyze raised a Critical alert, 2 on error.";
+
+struct Args {
+    command: String,
+    path: String,
+    json: bool,
+    rules: Option<String>,
+    config: Option<String>,
+    definitions: Option<String>,
+    crc: CrcMode,
+    to: String,
+    interval: Duration,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = std::env::args().skip(1);
+    let mut positional = Vec::new();
+    let mut json = false;
+    let mut rules = None;
+    let mut config = None;
+    let mut definitions = None;
+    let mut crc = CrcMode::default();
+    let mut to = "127.0.0.1:5005".to_string();
+    let mut interval = Duration::from_millis(1000);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--json" => json = true,
+            "--rules" => rules = Some(args.next().ok_or("--rules needs a path")?),
+            "--config" => config = Some(args.next().ok_or("--config needs a path")?),
+            "--definitions" => definitions = Some(args.next().ok_or("--definitions needs a path")?),
+            "--crc" => {
+                let mode = args.next().ok_or("--crc needs a mode")?;
+                crc = match mode.as_str() {
+                    "required" => CrcMode::Required,
+                    "optional" => CrcMode::Optional,
+                    "absent" => CrcMode::Absent,
+                    _ => return Err(format!("Invalid CRC mode: {}", mode)),
+                };
+            }
+            "--to" => to = args.next().ok_or("--to needs an address")?,
+            "--interval" => {
+                let ms = args.next().ok_or("--interval needs milliseconds")?;
+                let ms = ms
+                    .parse()
+                    .map_err(|_| format!("Invalid interval: {}", ms))?;
+                interval = Duration::from_millis(ms);
+            }
+            "-h" | "--help" => return Err(String::new()),
+            _ if arg.starts_with("--") => return Err(format!("Unknown option: {}", arg)),
+            _ => positional.push(arg),
+        }
+    }
+
+    let [command, path]: [String; 2] = positional
+        .try_into()
+        .map_err(|_| "Expected a command and a dump file".to_string())?;
+    Ok(Args {
+        command,
+        path,
+        json,
+        rules,
+        config,
+        definitions,
+        crc,
+        to,
+        interval,
+    })
+}
+
+fn main() -> ExitCode {
+    env_logger::init();
+
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(e) => {
+            if !e.is_empty() {
+                eprintln!("Error: {}\n", e);
+            }
+            eprintln!("{}", USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    let data = match std::fs::read(&args.path) {
+        Ok(data) => data,
+        Err(e) => {
+            eprintln!("Error reading {}: {}", args.path, e);
+            return ExitCode::from(2);
+        }
+    };
+    let config = match &args.config {
+        Some(path) => match Config::load(path) {
+            Ok(config) => config,
+            Err(e) => {
+                eprintln!("Error loading config from {}: {}", path, e);
+                return ExitCode::from(2);
+            }
+        },
+        None => Config::default(),
+    };
+    let definitions = match &args.definitions {
+        Some(path) => PacketDefinitions::load(path),
+        None => config.packet_definitions(),
+    };
+    let definitions = match definitions {
+        Ok(definitions) => Arc::new(definitions),
+        Err(e) => {
+            eprintln!("Error loading packet definitions: {}", e);
+            return ExitCode::from(2);
+        }
+    };
+    let mut framer = PacketFramer::with_crc(args.crc).definitions(Arc::clone(&definitions));
+    framer.push(&data);
+    let calibration = config.calibration(&definitions);
+    let power = PowerAnalytics::new(config.battery.clone());
+
+    match args.command.as_str() {
+        "decode" => decode(framer, &calibration, power, args.json),
+        "analyze" => analyze(
+            framer,
+            definitions,
+            &calibration,
+            power,
+            args.json,
+            args.rules.as_deref(),
+        ),
+        "stats" => stats(framer, &calibration, power, args.json),
+        "send" => send(&data, &args.to, args.interval, args.crc),
+        other => {
+            eprintln!("Error: Unknown command: {}\n\n{}", other, USAGE);
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn print_json<T: serde::Serialize>(value: &T) {
+    match serde_json::to_string(value) {
+        Ok(line) => println!("{}", line),
+        Err(e) => eprintln!("Error serializing output: {}", e),
+    }
+}
+
+fn decode(
+    mut framer: PacketFramer,
+    calibration: &CalibrationSet,
+    mut power: PowerAnalytics,
+    json: bool,
+) -> ExitCode {
+    let total = framer.pending();
+    loop {
+        let offset = total - framer.pending();
+        match framer.next_packet() {
+            Some(Ok((mut packet, _))) => {
+                calibration.apply(&mut packet);
+                power.apply(&mut packet);
+                if json {
+                    print_json(&packet);
+                } else {
+                    println!(
+                        "@{:<8} [{}] {:?} - {:?}",
+                        offset, packet.timestamp, packet.subsystem, packet.payload
+                    );
+                }
+            }
+            Some(Err(e)) => eprintln!("Error parsing packet at offset {}: {}", offset, e),
+            None => break,
+        }
+    }
+    if framer.pending() > 0 {
+        eprintln!("Ignoring {} trailing bytes", framer.pending());
+    }
+    ExitCode::SUCCESS
+}
+
+fn analyze(
+    framer: PacketFramer,
+    definitions: Arc<PacketDefinitions>,
+    calibration: &CalibrationSet,
+    mut power: PowerAnalytics,
+    json: bool,
+    rules: Option<&str>,
+) -> ExitCode {
+    let mut monitor = match rules {
+        Some(path) => match RuleSet::load_using(path, &definitions) {
+            Ok(rules) => Monitor::from(rules),
+            Err(e) => {
+                eprintln!("Error loading rules from {}: {}", path, e);
+                return ExitCode::from(2);
+            }
+        },
+        None => Monitor::default(),
+    };
+    monitor.watchdog.set_definitions(definitions);
+
+    // Silences are measured in packet time from the first packet, not in
+    // the time the dump takes to read
+    let start = Instant::now();
+    let mut now = start;
+    let mut first = None;
+    let mut critical = false;
+    for result in framer {
+        let mut packet = match result {
+            Ok((packet, _)) => packet,
+            Err(e) => {
+                eprintln!("Error parsing packet: {}", e);
+                continue;
+            }
+        };
+        calibration.apply(&mut packet);
+        power.apply(&mut packet);
+        let first = *first.get_or_insert(packet.timestamp);
+        let elapsed = Duration::from_secs(packet.timestamp.saturating_sub(first));
+        now = start.checked_add(elapsed).unwrap_or(now);
+        let mut alerts = monitor.analyze_at(&packet, now);
+        alerts.extend(monitor.check_stale(now).into_iter().map(|(_, alert)| alert));
+        for alert in alerts {
+            critical |= alert.level == AlertLevel::Critical;
+            if json {
+                print_json(&alert);
+            } else {
+                println!(
+                    "[{}] [{:?}] {} ({})",
+                    alert.timestamp, alert.level, alert.message, alert.rule
+                );
+            }
+        }
+    }
+
+    if critical {
+        ExitCode::from(1)
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+fn stats(
+    framer: PacketFramer,
+    calibration: &CalibrationSet,
+    mut power: PowerAnalytics,
+    json: bool,
+) -> ExitCode {
+    let mut stats = TelemetryStats::new();
+    for result in framer {
+        match result {
+            Ok((mut packet, _)) => {
+                calibration.apply(&mut packet);
+                power.apply(&mut packet);
+                stats.add(&packet);
+            }
+            Err(e) if e.is_corruption() => stats.add_corrupted(),
+            Err(_) => stats.add_error(),
+        }
+    }
+
+    if json {
+        print_json(&stats);
+        return ExitCode::SUCCESS;
+    }
+
+    for (subsystem, s) in &stats.subsystems {
+        println!("{:?}: {} packets", subsystem, s.packets);
+        for (name, f) in &s.fields {
+            println!(
+                "  {:<30} min {:>12.4}  max {:>12.4}  mean {:>12.4}",
+                name,
+                f.min,
+                f.max,
+                f.mean()
+            );
+        }
+    }
+    println!("Parse errors: {}", stats.errors);
+    println!("Corrupted: {}", stats.corrupted);
+    ExitCode::SUCCESS
+}
+
+fn send(data: &[u8], to: &str, interval: Duration, crc: CrcMode) -> ExitCode {
+    let socket = match UdpSocket::bind("0.0.0.0:0") {
+        Ok(socket) => socket,
+        Err(e) => {
+            eprintln!("Error opening UDP socket: {}", e);
+            return ExitCode::from(2);
+        }
+    };
+
+    // Forward the original frames byte for byte, split by the header length
+    let mut offset = 0;
+    let mut sent = 0;
+    while let Ok(len) = Parser::frame_len(&data[offset..], crc) {
+        let end = (offset + len).min(data.len());
+        if sent > 0 {
+            std::thread::sleep(interval);
+        }
+        if let Err(e) = socket.send_to(&data[offset..end], to) {
+            eprintln!("Error sending to {}: {}", to, e);
+            return ExitCode::from(2);
+        }
+        sent += 1;
+        offset = end;
+    }
+
+    println!("Sent {} packets to {}", sent, to);
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,239 @@
+use crate::definition::PacketDefinitions;
+use crate::models::{TelemetryPacket, TelemetryPayload, Value};
+use serde::{Deserialize, Serialize};
+
+/// Zero degrees Celsius in kelvin.
+const KELVIN_OFFSET: f64 = 273.15;
+
+/// Conversion from raw sensor counts to engineering units.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum Calibration {
+    /// `c0 + c1 * x + c2 * x^2 + ...`
+    Polynomial { coefficients: Vec<f64> },
+    /// Linear interpolation between `(raw, value)` points sorted by raw
+    /// count, held at the end values outside the table.
+    Table { points: Vec<(f64, f64)> },
+    /// NTC thermistor on the low side of a divider with `series_ohms` to the
+    /// reference, read by an ADC whose reference voltage reads `full_scale`.
+    /// `1/T = a + b ln(R) + c ln(R)^3` with `T` in kelvin; the result is in
+    /// degrees Celsius.
+    SteinhartHart {
+        a: f64,
+        b: f64,
+        c: f64,
+        series_ohms: f64,
+        full_scale: f64,
+    },
+}
+
+impl Calibration {
+    pub fn apply(&self, raw: f64) -> f64 {
+        match self {
+            Calibration::Polynomial { coefficients } => {
+                coefficients.iter().rev().fold(0.0, |acc, c| acc * raw + c)
+            }
+            Calibration::Table { points } => {
+                let Some(&(first_raw, first)) = points.first() else {
+                    return raw;
+                };
+                if raw <= first_raw {
+                    return first;
+                }
+                for pair in points.windows(2) {
+                    let ((x0, y0), (x1, y1)) = (pair[0], pair[1]);
+                    if raw <= x1 {
+                        return y0 + (raw - x0) * (y1 - y0) / (x1 - x0);
+                    }
+                }
+                points[points.len() - 1].1
+            }
+            Calibration::SteinhartHart {
+                a,
+                b,
+                c,
+                series_ohms,
+                full_scale,
+            } => {
+                // An open or shorted thermistor reads as the coldest or
+                // hottest representable count rather than infinity.
+                let counts = raw.clamp(1.0, full_scale - 1.0);
+                let ohms = series_ohms * counts / (full_scale - counts);
+                let ln_r = ohms.ln();
+                1.0 / (a + b * ln_r + c * ln_r.powi(3)) - KELVIN_OFFSET
+            }
+        }
+    }
+
+    /// Describes the first problem that would make `apply` meaningless.
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            Calibration::Polynomial { coefficients } if coefficients.is_empty() => {
+                Err("polynomial has no coefficients".to_string())
+            }
+            Calibration::Table { points } if points.len() < 2 => {
+                Err("table needs at least two points".to_string())
+            }
+            Calibration::Table { points } if points.windows(2).any(|p| p[0].0 >= p[1].0) => {
+                Err("table raw counts must be strictly increasing".to_string())
+            }
+            Calibration::SteinhartHart {
+                series_ohms,
+                full_scale,
+                ..
+            } if *series_ohms <= 0.0 || *full_scale <= 2.0 => {
+                Err("series_ohms must be positive and full_scale above 2".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Calibration of one telemetry field, e.g. `thermal.temp_celsius`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ParameterCalibration {
+    pub field: String,
+    #[serde(flatten)]
+    pub calibration: Calibration,
+}
+
+impl ParameterCalibration {
+    pub fn new(field: &str, calibration: Calibration) -> Self {
+        Self {
+            field: field.to_string(),
+            calibration,
+        }
+    }
+}
+
+/// Per-parameter calibrations applied to packets sent in the raw format and
+/// to the count fields of instruments from packet definitions.
+///
+/// Raw counts without a calibration are passed through unchanged.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CalibrationSet {
+    pub parameters: Vec<ParameterCalibration>,
+}
+
+impl Default for CalibrationSet {
+    /// Calibrations for the simulated spacecraft's sensors.
+    fn default() -> Self {
+        Self {
+            parameters: vec![
+                // 10 mV per count
+                ParameterCalibration::new(
+                    "power.voltage",
+                    Calibration::Polynomial {
+                        coefficients: vec![0.0, 0.01],
+                    },
+                ),
+                // 1 mA per count, offset binary around 32768, positive while discharging
+                ParameterCalibration::new(
+                    "power.current",
+                    Calibration::Polynomial {
+                        coefficients: vec![-32.768, 0.001],
+                    },
+                ),
+                // Battery gauge, flat in the middle of the discharge curve
+                ParameterCalibration::new(
+                    "power.battery_level",
+                    Calibration::Table {
+                        points: vec![
+                            (0.0, 0.0),
+                            (8000.0, 10.0),
+                            (48000.0, 90.0),
+                            (65535.0, 100.0),
+                        ],
+                    },
+                ),
+                // 10k NTC thermistor with a 10k series resistor, 16-bit ADC
+                ParameterCalibration::new(
+                    "thermal.temp_celsius",
+                    Calibration::SteinhartHart {
+                        a: 1.129148e-3,
+                        b: 2.34125e-4,
+                        c: 8.76741e-8,
+                        series_ohms: 10_000.0,
+                        full_scale: 65_535.0,
+                    },
+                ),
+            ],
+        }
+    }
+}
+
+impl CalibrationSet {
+    pub fn get(&self, field: &str) -> Option<&Calibration> {
+        self.parameters
+            .iter()
+            .find(|p| p.field == field)
+            .map(|p| &p.calibration)
+    }
+
+    /// Replaces calibrations for the same field and appends the rest.
+    pub fn merge(&mut self, parameters: Vec<ParameterCalibration>) {
+        for parameter in parameters {
+            match self
+                .parameters
+                .iter_mut()
+                .find(|p| p.field == parameter.field)
+            {
+                Some(existing) => *existing = parameter,
+                None => self.parameters.push(parameter),
+            }
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        self.validate_using(&PacketDefinitions::built_in())
+    }
+
+    /// Like `validate`, also accepting the u8, u16 and u32 fields of the
+    /// instruments in `definitions`.
+    pub fn validate_using(&self, definitions: &PacketDefinitions) -> Result<(), String> {
+        for (i, parameter) in self.parameters.iter().enumerate() {
+            if !definitions.is_field_path(&parameter.field) {
+                return Err(format!("unknown field '{}'", parameter.field));
+            }
+            if let Some(field) = definitions.instrument_field(&parameter.field) {
+                if !field.kind.is_count() {
+                    return Err(format!("'{}' is not a count field", parameter.field));
+                }
+            }
+            if self.parameters[..i]
+                .iter()
+                .any(|p| p.field == parameter.field)
+            {
+                return Err(format!("duplicate calibration for '{}'", parameter.field));
+            }
+            parameter
+                .calibration
+                .validate()
+                .map_err(|e| format!("'{}': {}", parameter.field, e))?;
+        }
+        Ok(())
+    }
+
+    /// Converts the packet's raw counts into its payload fields. Calibrated
+    /// parameters of a generic payload are decoded as counts, which are
+    /// moved to `raw_counts` first, so applying the set twice is harmless.
+    pub fn apply(&self, packet: &mut TelemetryPacket) {
+        if let TelemetryPayload::Generic(parameters) = &packet.payload {
+            for parameter in &self.parameters {
+                if let Some(Value::Number(count)) = parameters.get(&parameter.field) {
+                    packet
+                        .raw_counts
+                        .entry(parameter.field.clone())
+                        .or_insert(*count as u32);
+                }
+            }
+        }
+        for (field, &count) in &packet.raw_counts {
+            let value = match self.get(field) {
+                Some(calibration) => calibration.apply(count as f64),
+                None => count as f64,
+            };
+            packet.payload.set_field(field, value);
+        }
+    }
+}
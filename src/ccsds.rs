@@ -0,0 +1,203 @@
+use crate::definition::PacketDefinitions;
+use crate::models::{Subsystem, TelemetryPacket};
+use crate::parser::{Parser, ParserError};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Size of the CCSDS Space Packet primary header.
+pub const PRIMARY_HEADER_LEN: usize = 6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum SequenceFlags {
+    Continuation,
+    First,
+    Last,
+    Unsegmented,
+}
+
+impl SequenceFlags {
+    fn from_bits(bits: u8) -> Self {
+        match bits & 0b11 {
+            0b00 => SequenceFlags::Continuation,
+            0b01 => SequenceFlags::First,
+            0b10 => SequenceFlags::Last,
+            _ => SequenceFlags::Unsegmented,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PrimaryHeader {
+    pub version: u8,
+    pub is_telecommand: bool,
+    pub has_secondary_header: bool,
+    pub apid: u16,
+    pub sequence_flags: SequenceFlags,
+    pub sequence_count: u16,
+    /// Length of the packet data field in bytes (the wire value plus one).
+    pub data_length: usize,
+}
+
+/// Layout of the CCSDS Unsegmented Time Code carried in the secondary header.
+/// The P-field is implicit, so only the coarse and fine octet counts are needed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CucFormat {
+    pub coarse_bytes: usize,
+    pub fine_bytes: usize,
+    /// Seconds added to the coarse time to convert it to a Unix timestamp.
+    pub epoch_offset: u64,
+}
+
+impl Default for CucFormat {
+    fn default() -> Self {
+        Self {
+            coarse_bytes: 4,
+            fine_bytes: 2,
+            epoch_offset: 0,
+        }
+    }
+}
+
+impl CucFormat {
+    pub fn len(&self) -> usize {
+        self.coarse_bytes + self.fine_bytes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Decodes CCSDS Space Packets into `TelemetryPacket`s.
+///
+/// The APID selects the subsystem through a configurable table, and the user
+/// data field uses the same payload layout as the custom header format,
+/// decoded with `definitions`. Packets without a secondary header get a
+/// timestamp of 0.
+pub struct CcsdsParser {
+    pub apid_map: HashMap<u16, Subsystem>,
+    pub time_format: CucFormat,
+    pub definitions: Arc<PacketDefinitions>,
+}
+
+impl Default for CcsdsParser {
+    fn default() -> Self {
+        let apid_map = HashMap::from([
+            (100, Subsystem::Power),
+            (101, Subsystem::Thermal),
+            (102, Subsystem::Aocs),
+            (103, Subsystem::StarTracker),
+        ]);
+        Self::new(apid_map, CucFormat::default())
+    }
+}
+
+impl CcsdsParser {
+    pub fn new(apid_map: HashMap<u16, Subsystem>, time_format: CucFormat) -> Self {
+        Self {
+            apid_map,
+            time_format,
+            definitions: PacketDefinitions::built_in(),
+        }
+    }
+
+    pub fn map_apid(&mut self, apid: u16, subsystem: Subsystem) {
+        self.apid_map.insert(apid, subsystem);
+    }
+
+    pub fn parse_primary_header(data: &[u8]) -> Result<PrimaryHeader, ParserError> {
+        if data.len() < PRIMARY_HEADER_LEN {
+            return Err(ParserError::BufferTooShort);
+        }
+
+        let word0 = u16::from_be_bytes([data[0], data[1]]);
+        let word1 = u16::from_be_bytes([data[2], data[3]]);
+        let word2 = u16::from_be_bytes([data[4], data[5]]);
+
+        Ok(PrimaryHeader {
+            version: (word0 >> 13) as u8,
+            is_telecommand: (word0 >> 12) & 1 == 1,
+            has_secondary_header: (word0 >> 11) & 1 == 1,
+            apid: word0 & 0x07FF,
+            sequence_flags: SequenceFlags::from_bits((word1 >> 14) as u8),
+            sequence_count: word1 & 0x3FFF,
+            data_length: word2 as usize + 1,
+        })
+    }
+
+    /// Parses exactly one Space Packet.
+    pub fn parse(&self, data: &[u8]) -> Result<TelemetryPacket, ParserError> {
+        let (_, packet, used) = self.parse_prefix(data)?;
+        if used != data.len() {
+            return Err(ParserError::LengthMismatch {
+                declared: used - PRIMARY_HEADER_LEN,
+                actual: data.len() - PRIMARY_HEADER_LEN,
+            });
+        }
+        Ok(packet)
+    }
+
+    /// Parses the Space Packet at the start of `data` and returns its primary
+    /// header, the decoded packet and the number of bytes it occupied.
+    pub fn parse_prefix(
+        &self,
+        data: &[u8],
+    ) -> Result<(PrimaryHeader, TelemetryPacket, usize), ParserError> {
+        let header = Self::parse_primary_header(data)?;
+        let total = PRIMARY_HEADER_LEN + header.data_length;
+        if data.len() < total {
+            return Err(ParserError::BufferTooShort);
+        }
+        let mut body = &data[PRIMARY_HEADER_LEN..total];
+
+        let subsystem = self
+            .apid_map
+            .get(&header.apid)
+            .cloned()
+            .ok_or(ParserError::UnknownApid(header.apid))?;
+
+        let timestamp = if header.has_secondary_header {
+            let time_len = self.time_format.len();
+            if body.len() < time_len {
+                return Err(ParserError::LengthMismatch {
+                    declared: header.data_length,
+                    actual: time_len,
+                });
+            }
+            let coarse = body[..self.time_format.coarse_bytes]
+                .iter()
+                .fold(0u64, |acc, b| (acc << 8) | *b as u64);
+            body = &body[time_len..];
+            let epoch_offset = self.time_format.epoch_offset;
+            coarse
+                .checked_add(epoch_offset)
+                .ok_or(ParserError::TimestampOverflow {
+                    coarse,
+                    epoch_offset,
+                })?
+        } else {
+            0
+        };
+
+        let (_, payload, used) = Parser::parse_payload(subsystem.id(), body, &self.definitions)?;
+        if used != body.len() {
+            return Err(ParserError::LengthMismatch {
+                declared: header.data_length,
+                actual: header.data_length - body.len() + used,
+            });
+        }
+
+        Ok((
+            header,
+            TelemetryPacket {
+                timestamp,
+                subsystem,
+                payload,
+                raw_counts: Default::default(),
+                derived: Default::default(),
+            },
+            total,
+        ))
+    }
+}
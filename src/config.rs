@@ -0,0 +1,352 @@
+use crate::calibration::{CalibrationSet, ParameterCalibration};
+use crate::crc::CrcMode;
+use crate::definition::{DefinitionError, PacketDefinitions};
+use crate::monitor::{LimitSet, Monitor};
+use crate::power::BatteryConfig;
+use crate::rules::{Rule, RuleError, RuleSet};
+use crate::skymap::Projection;
+use crate::trend::TrendCheck;
+use crate::watchdog::ReportingPeriod;
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("TOML error: {0}")]
+    Toml(#[from] toml::de::Error),
+    #[error("Invalid configuration: {0}")]
+    Invalid(String),
+}
+
+/// Dashboard configuration, as stored in a TOML file.
+///
+/// Every section is optional and falls back to the built-in defaults.
+/// `profile` names the monitor profile that is active at startup,
+/// `calibrations` replace the built-in or definition file calibration of
+/// the same field,
+/// `definitions` names a packet definition file for new instruments, and
+/// `battery` describes the battery for the power analytics.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub profile: String,
+    pub simulation: SimulationConfig,
+    pub source: SourceConfig,
+    pub archive: ArchiveConfig,
+    pub ui: UiConfig,
+    pub profiles: Vec<Profile>,
+    pub calibrations: Vec<ParameterCalibration>,
+    pub definitions: Option<PathBuf>,
+    pub battery: BatteryConfig,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            profile: "default".to_string(),
+            simulation: SimulationConfig::default(),
+            source: SourceConfig::default(),
+            archive: ArchiveConfig::default(),
+            ui: UiConfig::default(),
+            profiles: vec![Profile::new("default")],
+            calibrations: Vec::new(),
+            definitions: None,
+            battery: BatteryConfig::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Parses and validates a configuration.
+    pub fn from_toml_str(s: &str) -> Result<Self, ConfigError> {
+        let config: Self = toml::from_str(s)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        Self::from_toml_str(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn profile(&self, name: &str) -> Option<&Profile> {
+        self.profiles.iter().find(|p| p.name == name)
+    }
+
+    /// Monitor for the startup profile.
+    pub fn monitor(&self) -> Option<Monitor> {
+        self.profile(&self.profile).map(Profile::monitor)
+    }
+
+    /// Packet layouts from the `definitions` file, or the built-in ones.
+    pub fn packet_definitions(&self) -> Result<PacketDefinitions, DefinitionError> {
+        match &self.definitions {
+            Some(path) => PacketDefinitions::load(path),
+            None => Ok(PacketDefinitions::default()),
+        }
+    }
+
+    /// The built-in calibrations, those given in `definitions` and the
+    /// `[[calibrations]]` of this file, later ones replacing earlier ones.
+    pub fn calibration(&self, definitions: &PacketDefinitions) -> CalibrationSet {
+        let mut set = CalibrationSet::default();
+        set.merge(definitions.calibrations());
+        set.merge(self.calibrations.clone());
+        set
+    }
+
+    /// Checks the configuration, reading the `definitions` file so rules
+    /// and calibrations can refer to the instruments it defines.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let invalid = |msg: String| Err(ConfigError::Invalid(msg));
+        let definitions = match self.packet_definitions() {
+            Ok(definitions) => definitions,
+            Err(e) => return invalid(format!("definitions: {}", e)),
+        };
+
+        for (i, profile) in self.profiles.iter().enumerate() {
+            if profile.name.trim().is_empty() {
+                return invalid("profile name is empty".to_string());
+            }
+            if self.profiles[..i].iter().any(|p| p.name == profile.name) {
+                return invalid(format!("duplicate profile '{}'", profile.name));
+            }
+            if let Err(e) = profile.rule_set().validate_using(&definitions) {
+                let e = match e {
+                    RuleError::Invalid(msg) => msg,
+                    e => e.to_string(),
+                };
+                return invalid(format!("profile '{}': {}", profile.name, e));
+            }
+        }
+        if self.profile(&self.profile).is_none() {
+            let names: Vec<&str> = self.profiles.iter().map(|p| p.name.as_str()).collect();
+            return invalid(format!(
+                "profile '{}' is not defined (available: {})",
+                self.profile,
+                names.join(", ")
+            ));
+        }
+
+        let mut fields: Vec<&str> = self.calibrations.iter().map(|c| c.field.as_str()).collect();
+        fields.sort_unstable();
+        if let Some(pair) = fields.windows(2).find(|pair| pair[0] == pair[1]) {
+            return invalid(format!("duplicate calibration for '{}'", pair[0]));
+        }
+        if let Err(e) = self.calibration(&definitions).validate_using(&definitions) {
+            return invalid(format!("calibrations: {}", e));
+        }
+        if let Err(e) = self.battery.validate() {
+            return invalid(format!("battery.{}", e));
+        }
+
+        if self.source.kind == SourceKind::TcpClient
+            && self.source.address.parse::<SocketAddr>().is_err()
+        {
+            return invalid(format!(
+                "source.address '{}' is not a socket address such as 127.0.0.1:5005",
+                self.source.address
+            ));
+        }
+        if self.archive.segment_records == 0 {
+            return invalid("archive.segment_records is 0".to_string());
+        }
+
+        let injection = &self.ui.injection;
+        if !(0.0..=100.0).contains(&injection.battery) {
+            return invalid(format!(
+                "ui.injection.battery {} is outside 0 to 100",
+                injection.battery
+            ));
+        }
+        if !(0.0..=1.0).contains(&injection.confidence) {
+            return invalid(format!(
+                "ui.injection.confidence {} is outside 0 to 1",
+                injection.confidence
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Named set of monitor checks an operator can switch to, e.g. "safe-mode".
+///
+/// With `inherit_defaults` the profile starts from `RuleSet::default()`, and
+/// its entries replace built-in ones of the same name (see `RuleSet::merge`).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default = "default_true")]
+    pub inherit_defaults: bool,
+    #[serde(default)]
+    pub rules: Vec<Rule>,
+    #[serde(default)]
+    pub limits: Vec<LimitSet>,
+    #[serde(default)]
+    pub trends: Vec<TrendCheck>,
+    #[serde(default)]
+    pub watchdog: Vec<ReportingPeriod>,
+}
+
+impl Profile {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            description: String::new(),
+            inherit_defaults: true,
+            rules: Vec::new(),
+            limits: Vec::new(),
+            trends: Vec::new(),
+            watchdog: Vec::new(),
+        }
+    }
+
+    /// The profile's checks, merged over the defaults if inherited.
+    pub fn rule_set(&self) -> RuleSet {
+        let own = RuleSet {
+            rules: self.rules.clone(),
+            limits: self.limits.clone(),
+            trends: self.trends.clone(),
+            watchdog: self.watchdog.clone(),
+        };
+        if !self.inherit_defaults {
+            return own;
+        }
+        let mut set = RuleSet::default();
+        set.merge(own);
+        set
+    }
+
+    pub fn monitor(&self) -> Monitor {
+        self.rule_set().into()
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SimulationConfig {
+    pub interval_ms: u64, // Delay between replayed packets
+    pub autostart: bool,
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        Self {
+            interval_ms: 1000,
+            autostart: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SourceKind {
+    Udp,
+    TcpClient,
+    TcpServer,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SourceConfig {
+    pub kind: SourceKind,
+    pub port: u16,       // Listening port for UDP and TCP server
+    pub address: String, // Remote address for TCP client
+    pub crc: CrcMode,
+    pub autostart: bool,
+}
+
+impl Default for SourceConfig {
+    fn default() -> Self {
+        Self {
+            kind: SourceKind::Udp,
+            port: 5005,
+            address: "127.0.0.1:5005".to_string(),
+            crc: CrcMode::default(),
+            autostart: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ArchiveConfig {
+    pub enabled: bool,
+    pub dir: PathBuf,
+    pub segment_records: usize,
+    pub pass_gap_seconds: u64, // Gap between packets that starts a new pass
+}
+
+impl Default for ArchiveConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            dir: PathBuf::from("archive"),
+            segment_records: 10_000,
+            pass_gap_seconds: 600,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UiConfig {
+    pub show_charts: bool,
+    pub show_sky_map: bool,
+    pub show_thermal: bool,
+    pub show_power: bool,
+    pub sky_projection: Projection,
+    pub injection: InjectionDefaults,
+}
+
+impl Default for UiConfig {
+    fn default() -> Self {
+        Self {
+            show_charts: true,
+            show_sky_map: false,
+            show_thermal: false,
+            show_power: false,
+            sky_projection: Projection::Aitoff,
+            injection: InjectionDefaults::default(),
+        }
+    }
+}
+
+/// Initial values of the manual packet injection form.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct InjectionDefaults {
+    pub voltage: f64,
+    pub current: f64,
+    pub battery: f64,
+    pub temp_celsius: f64,
+    pub right_ascension: f64,
+    pub declination: f64,
+    pub confidence: f64,
+    pub target: String,
+}
+
+impl Default for InjectionDefaults {
+    fn default() -> Self {
+        Self {
+            voltage: 28.0,
+            current: 2.5,
+            battery: 95.0,
+            temp_celsius: 25.0,
+            right_ascension: 0.0,
+            declination: 0.0,
+            confidence: 1.0,
+            target: "Unknown".to_string(),
+        }
+    }
+}
@@ -0,0 +1,29 @@
+use serde::{Deserialize, Serialize};
+
+/// CRC-16/CCITT-FALSE as used for the CCSDS packet error control field:
+/// polynomial 0x1021, initial value 0xFFFF, no reflection, no final XOR.
+pub fn crc16_ccitt(data: &[u8]) -> u16 {
+    data.iter().fold(0xFFFF, |crc, &byte| {
+        (0..8).fold(crc ^ (u16::from(byte) << 8), |crc, _| {
+            if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            }
+        })
+    })
+}
+
+/// Whether a packet source carries the CRC trailer.
+///
+/// A packet with a trailer sets `CRC_FLAG` in its subsystem ID byte. With
+/// `Optional`, such packets are verified and packets without one are
+/// accepted; `Absent` reads the legacy format and never looks for a trailer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CrcMode {
+    Required,
+    #[default]
+    Optional,
+    Absent,
+}
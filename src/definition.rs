@@ -0,0 +1,625 @@
+use crate::calibration::{Calibration, ParameterCalibration};
+use crate::encoder::EncoderError;
+use crate::models::{self, ParameterMap, Subsystem, Value};
+use crate::parser::{ParserError, CRC_FLAG, RAW_FLAG};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum DefinitionError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("TOML error: {0}")]
+    Toml(#[from] toml::de::Error),
+    #[error("Invalid packet definitions: {0}")]
+    Invalid(String),
+}
+
+/// Wire type of a field. Strings are prefixed with their length as a u8.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FieldType {
+    U8,
+    U16,
+    U32,
+    U64,
+    I8,
+    I16,
+    I32,
+    I64,
+    F32,
+    F64,
+    String,
+}
+
+impl FieldType {
+    /// Size on the wire; for a string, the size of its length prefix.
+    pub fn size(&self) -> usize {
+        match self {
+            FieldType::U8 | FieldType::I8 | FieldType::String => 1,
+            FieldType::U16 | FieldType::I16 => 2,
+            FieldType::U32 | FieldType::I32 | FieldType::F32 => 4,
+            FieldType::U64 | FieldType::I64 | FieldType::F64 => 8,
+        }
+    }
+
+    pub fn is_integer(&self) -> bool {
+        !matches!(self, FieldType::F32 | FieldType::F64 | FieldType::String)
+    }
+
+    /// Whether values fit the u32 raw counts calibrations convert.
+    pub fn is_count(&self) -> bool {
+        matches!(self, FieldType::U8 | FieldType::U16 | FieldType::U32)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Endian {
+    #[default]
+    Big,
+    Little,
+}
+
+/// One field of a packet, in wire order.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FieldDefinition {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: FieldType,
+    #[serde(default)]
+    pub endian: Endian,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unit: Option<String>,
+    /// Name of an earlier integer field holding the number of repetitions.
+    /// A repeated field decodes into a `Value::List` or `Value::TextList`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub count: Option<String>,
+    /// May be left out at the end of the payload, e.g. by older senders.
+    /// Only optional fields can follow an optional field.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub optional: bool,
+    /// Converts the decoded count into engineering units. The field is
+    /// calibrated with the packet's other raw counts, see
+    /// `PacketDefinitions::calibrations`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub calibration: Option<Calibration>,
+}
+
+impl FieldDefinition {
+    pub fn new(name: &str, kind: FieldType) -> Self {
+        Self {
+            name: name.to_string(),
+            kind,
+            endian: Endian::Big,
+            unit: None,
+            count: None,
+            optional: false,
+            calibration: None,
+        }
+    }
+
+    pub fn unit(mut self, unit: &str) -> Self {
+        self.unit = Some(unit.to_string());
+        self
+    }
+
+    pub fn count(mut self, field: &str) -> Self {
+        self.count = Some(field.to_string());
+        self
+    }
+
+    pub fn optional(mut self) -> Self {
+        self.optional = true;
+        self
+    }
+
+    fn read(&self, data: &[u8], offset: &mut usize) -> Result<f64, ParserError> {
+        let size = self.kind.size();
+        let bytes = data
+            .get(*offset..*offset + size)
+            .ok_or(ParserError::BufferTooShort)?;
+        *offset += size;
+
+        macro_rules! num {
+            ($t:ty) => {{
+                let bytes = bytes.try_into().map_err(|_| ParserError::BufferTooShort)?;
+                match self.endian {
+                    Endian::Big => <$t>::from_be_bytes(bytes),
+                    Endian::Little => <$t>::from_le_bytes(bytes),
+                }
+            }};
+        }
+        Ok(match self.kind {
+            FieldType::U8 => num!(u8) as f64,
+            FieldType::U16 => num!(u16) as f64,
+            FieldType::U32 => num!(u32) as f64,
+            FieldType::U64 => num!(u64) as f64,
+            FieldType::I8 => num!(i8) as f64,
+            FieldType::I16 => num!(i16) as f64,
+            FieldType::I32 => num!(i32) as f64,
+            FieldType::I64 => num!(i64) as f64,
+            FieldType::F32 => num!(f32) as f64,
+            FieldType::F64 => num!(f64),
+            FieldType::String => unreachable!("strings are read by PacketDefinition::decode"),
+        })
+    }
+
+    fn write(&self, value: f64, data: &mut Vec<u8>) {
+        macro_rules! num {
+            ($t:ty) => {{
+                let value = value as $t;
+                match self.endian {
+                    Endian::Big => data.extend_from_slice(&value.to_be_bytes()),
+                    Endian::Little => data.extend_from_slice(&value.to_le_bytes()),
+                }
+            }};
+        }
+        match self.kind {
+            FieldType::U8 => num!(u8),
+            FieldType::U16 => num!(u16),
+            FieldType::U32 => num!(u32),
+            FieldType::U64 => num!(u64),
+            FieldType::I8 => num!(i8),
+            FieldType::I16 => num!(i16),
+            FieldType::I32 => num!(i32),
+            FieldType::I64 => num!(i64),
+            FieldType::F32 => num!(f32),
+            FieldType::F64 => num!(f64),
+            FieldType::String => unreachable!("strings are written by PacketDefinition::encode"),
+        }
+    }
+}
+
+/// Layout of the payload sent with one subsystem ID. Parameters are named
+/// `<name>.<field>`, e.g. `power.voltage`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PacketDefinition {
+    pub id: u8,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+    pub fields: Vec<FieldDefinition>,
+}
+
+impl PacketDefinition {
+    pub fn new(id: u8, name: &str, fields: Vec<FieldDefinition>) -> Self {
+        Self {
+            id,
+            name: name.to_string(),
+            description: String::new(),
+            fields,
+        }
+    }
+
+    pub fn path(&self, field: &str) -> String {
+        format!("{}.{}", self.name, field)
+    }
+
+    pub fn field(&self, name: &str) -> Option<&FieldDefinition> {
+        self.fields.iter().find(|f| f.name == name)
+    }
+
+    /// Payload size without optional or repeated fields and string contents.
+    pub fn min_len(&self) -> usize {
+        self.fields
+            .iter()
+            .filter(|f| f.count.is_none() && !f.optional)
+            .map(|f| f.kind.size())
+            .sum()
+    }
+
+    /// Largest payload the layout can describe, with every repeat count
+    /// and string length at its maximum.
+    pub fn max_len(&self) -> usize {
+        self.fields
+            .iter()
+            .map(|f| {
+                let size = match f.kind {
+                    FieldType::String => 1 + u8::MAX as usize,
+                    kind => kind.size(),
+                };
+                let repeats = match f.count.as_deref().and_then(|c| self.field(c)) {
+                    Some(counter) if counter.kind.size() < 8 => {
+                        (1usize << (8 * counter.kind.size())) - 1
+                    }
+                    Some(_) => usize::MAX,
+                    None => 1,
+                };
+                size.saturating_mul(repeats)
+            })
+            .fold(0, usize::saturating_add)
+    }
+
+    /// Decodes a payload and returns its parameters and the number of bytes
+    /// the layout requires. A payload cut short by the declared length is
+    /// reported as a mismatch.
+    pub fn decode(&self, data: &[u8]) -> Result<(ParameterMap, usize), ParserError> {
+        let mismatch = |actual: usize| ParserError::LengthMismatch {
+            declared: data.len(),
+            actual,
+        };
+        if data.len() < self.min_len() {
+            return Err(mismatch(self.min_len()));
+        }
+
+        let mut parameters = ParameterMap::new();
+        let mut offset = 0;
+        for field in &self.fields {
+            if field.optional && offset == data.len() {
+                break;
+            }
+            let value = match (&field.count, field.kind) {
+                (Some(count), FieldType::String) => {
+                    // Each string takes at least its length prefix
+                    let count = self.count(&parameters, count);
+                    let remaining = data.len() - offset;
+                    if count > remaining {
+                        return Err(mismatch(offset.saturating_add(count)));
+                    }
+                    let mut list = Vec::with_capacity(count);
+                    for _ in 0..count {
+                        list.push(read_text(data, &mut offset)?);
+                    }
+                    Value::TextList(list)
+                }
+                (Some(count), kind) => {
+                    let count = self.count(&parameters, count);
+                    let end = count
+                        .checked_mul(kind.size())
+                        .and_then(|len| len.checked_add(offset));
+                    match end {
+                        Some(end) if end <= data.len() => {}
+                        _ => return Err(mismatch(end.unwrap_or(usize::MAX))),
+                    }
+                    let mut list = Vec::with_capacity(count);
+                    for _ in 0..count {
+                        list.push(field.read(data, &mut offset)?);
+                    }
+                    Value::List(list)
+                }
+                (None, FieldType::String) => Value::Text(read_text(data, &mut offset)?),
+                (None, kind) => {
+                    if data.len() < offset + kind.size() {
+                        return Err(mismatch(offset + kind.size()));
+                    }
+                    Value::Number(field.read(data, &mut offset)?)
+                }
+            };
+            parameters.insert(self.path(&field.name), value);
+        }
+        Ok((parameters, offset))
+    }
+
+    /// Value of the repeat count field `name`, already decoded.
+    fn count(&self, parameters: &ParameterMap, name: &str) -> usize {
+        parameters
+            .get(&self.path(name))
+            .and_then(Value::as_f64)
+            .unwrap_or(0.0) as usize
+    }
+
+    /// Writes `parameters` in this layout, the inverse of `decode`. Writing
+    /// stops at the first missing optional field.
+    pub fn encode(&self, parameters: &ParameterMap) -> Result<Vec<u8>, EncoderError> {
+        let mut data = Vec::with_capacity(self.min_len());
+        for field in &self.fields {
+            let path = self.path(&field.name);
+            if field.optional && !parameters.contains_key(&path) {
+                break;
+            }
+            let missing = || EncoderError::MissingParameter(path.clone());
+            let check_count = |len: usize| {
+                let count = field.count.as_deref().unwrap_or_default();
+                if len == self.count(parameters, count) {
+                    Ok(())
+                } else {
+                    Err(EncoderError::CountMismatch(path.clone()))
+                }
+            };
+            match (field.kind, parameters.get(&path).ok_or_else(missing)?) {
+                (FieldType::String, Value::Text(text)) if field.count.is_none() => {
+                    write_text(&path, text, &mut data)?;
+                }
+                (FieldType::String, Value::TextList(list)) if field.count.is_some() => {
+                    check_count(list.len())?;
+                    for text in list {
+                        write_text(&path, text, &mut data)?;
+                    }
+                }
+                (_, Value::List(list)) if field.count.is_some() => {
+                    check_count(list.len())?;
+                    for value in list {
+                        field.write(*value, &mut data);
+                    }
+                }
+                (_, Value::Number(value)) if field.count.is_none() => {
+                    field.write(*value, &mut data);
+                }
+                _ => return Err(missing()),
+            }
+        }
+        Ok(data)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.id & (CRC_FLAG | RAW_FLAG) != 0 {
+            return Err(format!(
+                "id {} collides with the CRC and raw flags",
+                self.id
+            ));
+        }
+        if self.name.is_empty() || self.name.contains('.') {
+            return Err(format!("invalid name '{}'", self.name));
+        }
+        for (i, field) in self.fields.iter().enumerate() {
+            let earlier = &self.fields[..i];
+            if earlier.iter().any(|f| f.name == field.name) {
+                return Err(format!("duplicate field '{}'", field.name));
+            }
+            if field.calibration.is_some() && (!field.kind.is_count() || field.count.is_some()) {
+                return Err(format!(
+                    "field '{}': only single u8, u16 or u32 counts can be calibrated",
+                    field.name
+                ));
+            }
+            if !field.optional && earlier.iter().any(|f| f.optional) {
+                return Err(format!(
+                    "required field '{}' follows an optional field",
+                    field.name
+                ));
+            }
+            if let Some(count) = &field.count {
+                let counter = earlier.iter().find(|f| {
+                    &f.name == count
+                        && f.kind.is_integer()
+                        && f.count.is_none()
+                        && f.calibration.is_none()
+                });
+                if counter.is_none() {
+                    return Err(format!(
+                        "field '{}': count '{}' is not an earlier uncalibrated integer field",
+                        field.name, count
+                    ));
+                }
+            }
+            if let Some(calibration) = &field.calibration {
+                calibration
+                    .validate()
+                    .map_err(|e| format!("field '{}': {}", field.name, e))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reads a string with a u8 length prefix.
+fn read_text(data: &[u8], offset: &mut usize) -> Result<String, ParserError> {
+    let mismatch = |actual: usize| ParserError::LengthMismatch {
+        declared: data.len(),
+        actual,
+    };
+    let len = *data.get(*offset).ok_or(mismatch(*offset + 1))? as usize;
+    *offset += 1;
+    if data.len() < *offset + len {
+        return Err(mismatch(*offset + len));
+    }
+    let text = String::from_utf8(data[*offset..*offset + len].to_vec())?;
+    *offset += len;
+    Ok(text)
+}
+
+fn write_text(path: &str, text: &str, data: &mut Vec<u8>) -> Result<(), EncoderError> {
+    let len = u8::try_from(text.len())
+        .map_err(|_| EncoderError::StringTooLong(path.to_string(), text.len()))?;
+    data.push(len);
+    data.extend_from_slice(text.as_bytes());
+    Ok(())
+}
+
+/// Packet layouts by subsystem ID, as stored in a TOML file.
+///
+/// The defaults describe the built-in Power, Thermal, AOCS and Star Tracker
+/// payloads. A file is merged over them, so it only needs to list new
+/// instruments or the layouts it changes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PacketDefinitions {
+    #[serde(default)]
+    pub packets: Vec<PacketDefinition>,
+}
+
+impl Default for PacketDefinitions {
+    fn default() -> Self {
+        use FieldType::{F64, U8};
+        let f64s = |names: &[&str], unit: &str| -> Vec<FieldDefinition> {
+            names
+                .iter()
+                .map(|name| FieldDefinition::new(name, F64).unit(unit))
+                .collect()
+        };
+
+        let power = vec![
+            FieldDefinition::new("voltage", F64).unit("V"),
+            FieldDefinition::new("current", F64).unit("A"),
+            FieldDefinition::new("battery_level", F64).unit("%"),
+        ];
+        // Named zones follow the primary sensor; older senders leave them out
+        let thermal = vec![
+            FieldDefinition::new("temp_celsius", F64).unit("C"),
+            FieldDefinition::new("zone_count", U8).optional(),
+            FieldDefinition::new("zone_names", FieldType::String)
+                .count("zone_count")
+                .optional(),
+            FieldDefinition::new("zone_temps", F64)
+                .unit("C")
+                .count("zone_count")
+                .optional(),
+        ];
+        let mut aocs = f64s(
+            &["attitude_w", "attitude_x", "attitude_y", "attitude_z"],
+            "",
+        );
+        aocs.extend(f64s(&["rate_x", "rate_y", "rate_z"], "deg/s"));
+        aocs.extend([
+            FieldDefinition::new("control_mode", U8),
+            FieldDefinition::new("wheel_count", U8),
+            FieldDefinition::new("wheel_speeds", F64)
+                .unit("RPM")
+                .count("wheel_count"),
+        ]);
+        let mut star_tracker = f64s(&["right_ascension", "declination"], "deg");
+        star_tracker.extend([
+            FieldDefinition::new("confidence", F64),
+            FieldDefinition::new("target_id", FieldType::String),
+        ]);
+
+        Self {
+            packets: vec![
+                PacketDefinition::new(0, "power", power),
+                PacketDefinition::new(1, "thermal", thermal),
+                PacketDefinition::new(2, "aocs", aocs),
+                PacketDefinition::new(3, "star_tracker", star_tracker),
+            ],
+        }
+    }
+}
+
+impl PacketDefinitions {
+    /// Parses a definition file and merges it over the built-in layouts.
+    pub fn from_toml_str(s: &str) -> Result<Self, DefinitionError> {
+        let file: Self = toml::from_str(s)?;
+        let mut definitions = Self::default();
+        definitions.merge(file);
+        definitions.validate()?;
+        Ok(definitions)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, DefinitionError> {
+        Self::from_toml_str(&std::fs::read_to_string(path)?)
+    }
+
+    /// The built-in layouts, shared by every parser not given a file.
+    pub fn built_in() -> Arc<PacketDefinitions> {
+        static BUILT_IN: OnceLock<Arc<PacketDefinitions>> = OnceLock::new();
+        BUILT_IN
+            .get_or_init(|| Arc::new(PacketDefinitions::default()))
+            .clone()
+    }
+
+    pub fn get(&self, id: u8) -> Option<&PacketDefinition> {
+        self.packets.iter().find(|p| p.id == id)
+    }
+
+    /// Prefix of the subsystem's field paths and alert rules: the name of
+    /// its packet definition, which for the built-in IDs is `Subsystem::key`.
+    pub fn key(&self, subsystem: &Subsystem) -> String {
+        match self.get(subsystem.id()) {
+            Some(packet) => packet.name.clone(),
+            None => subsystem.key(),
+        }
+    }
+
+    /// Whether `path` is a field of a built-in payload (see
+    /// `models::is_field_path`) or a scalar numeric parameter of an
+    /// instrument known only from these definitions.
+    pub fn is_field_path(&self, path: &str) -> bool {
+        models::is_field_path(path)
+            || self
+                .instrument_field(path)
+                .is_some_and(|f| f.kind != FieldType::String && f.count.is_none())
+    }
+
+    /// Definition of the field at `path` in an instrument that is not one
+    /// of the built-in payloads.
+    pub fn instrument_field(&self, path: &str) -> Option<&FieldDefinition> {
+        let built_in = Self::built_in();
+        self.packets
+            .iter()
+            .filter(|p| built_in.get(p.id).is_none())
+            .find_map(|p| {
+                path.strip_prefix(&p.name)
+                    .and_then(|rest| rest.strip_prefix('.'))
+                    .and_then(|field| p.field(field))
+            })
+    }
+
+    /// The calibrations given in the definitions, by field path. Merged into
+    /// a `CalibrationSet`, which converts them like any other raw count.
+    pub fn calibrations(&self) -> Vec<ParameterCalibration> {
+        self.packets
+            .iter()
+            .flat_map(|p| {
+                p.fields.iter().filter_map(|f| {
+                    let calibration = f.calibration.clone()?;
+                    Some(ParameterCalibration::new(&p.path(&f.name), calibration))
+                })
+            })
+            .collect()
+    }
+
+    /// Replaces definitions with the same ID and appends the rest.
+    pub fn merge(&mut self, other: PacketDefinitions) {
+        for packet in other.packets {
+            match self.packets.iter_mut().find(|p| p.id == packet.id) {
+                Some(existing) => *existing = packet,
+                None => self.packets.push(packet),
+            }
+        }
+    }
+
+    /// Checks IDs and names are unique, repeat counts refer to earlier
+    /// integer fields, and the built-in IDs keep the layout their typed
+    /// payloads are written in, adding only optional fields at the end.
+    pub fn validate(&self) -> Result<(), DefinitionError> {
+        let built_in = Self::default();
+        for (i, packet) in self.packets.iter().enumerate() {
+            let invalid = |msg: String| {
+                Err(DefinitionError::Invalid(format!(
+                    "packet '{}' (id {}): {}",
+                    packet.name, packet.id, msg
+                )))
+            };
+            if self.packets[..i].iter().any(|p| p.id == packet.id) {
+                return invalid("duplicate id".to_string());
+            }
+            if self.packets[..i].iter().any(|p| p.name == packet.name) {
+                return invalid("duplicate name".to_string());
+            }
+            if let Err(e) = packet.validate() {
+                return invalid(e);
+            }
+            if let Some(typed) = built_in.get(packet.id) {
+                if packet.name != typed.name {
+                    return invalid(format!("must be named '{}'", typed.name));
+                }
+                for (i, field) in typed.fields.iter().enumerate() {
+                    let same_layout = packet.fields.get(i).is_some_and(|f| {
+                        f.name == field.name
+                            && f.kind == field.kind
+                            && f.endian == field.endian
+                            && f.count == field.count
+                            && f.optional == field.optional
+                            && f.calibration.is_none()
+                    });
+                    if !same_layout {
+                        return invalid(format!(
+                            "field {} must be '{}' as built in",
+                            i + 1,
+                            field.name
+                        ));
+                    }
+                }
+                let added = &packet.fields[typed.fields.len()..];
+                if let Some(field) = added.iter().find(|f| !f.optional) {
+                    return invalid(format!("added field '{}' must be optional", field.name));
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,190 @@
+use crate::crc::{crc16_ccitt, CrcMode};
+use crate::definition::PacketDefinitions;
+use crate::models::{TelemetryPacket, TelemetryPayload, Value};
+use crate::parser::{self, CRC_FLAG, CRC_LEN, HEADER_LEN, RAW_FLAG};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum EncoderError {
+    #[error("Payload cannot be encoded: {0}")]
+    UnsupportedPayload(String),
+    #[error("Target ID too long: {0} bytes (max 255)")]
+    TargetIdTooLong(usize),
+    #[error("Too many reaction wheels: {0} (max 255)")]
+    TooManyWheels(usize),
+    #[error("Too many thermal zones: {0} (max 255)")]
+    TooManyZones(usize),
+    #[error("Payload too long: {0} bytes (max 65535)")]
+    PayloadTooLong(usize),
+    #[error("Missing raw count for {0}")]
+    MissingRawCount(String),
+    #[error("Raw count for {0} does not fit in 16 bits: {1}")]
+    RawCountTooLarge(String, u32),
+    #[error("Missing or mistyped parameter {0}")]
+    MissingParameter(String),
+    #[error("String too long for {0}: {1} bytes (max 255)")]
+    StringTooLong(String, usize),
+    #[error("Length of {0} does not match its count field")]
+    CountMismatch(String),
+}
+
+/// Writes `TelemetryPacket`s in the wire format read by `Parser::parse`.
+pub struct PacketEncoder;
+
+impl PacketEncoder {
+    /// Packets with `raw_counts` are written in the raw format, others with
+    /// their payload values. Generic payloads need a definition, so they
+    /// are only written by `encode_using`.
+    pub fn encode(packet: &TelemetryPacket) -> Result<Vec<u8>, EncoderError> {
+        Self::encode_using(packet, &PacketDefinitions::built_in())
+    }
+
+    /// Like `encode`, writing generic payloads with the definition in
+    /// `definitions` for their subsystem ID and the raw counts of their
+    /// calibrated parameters in place of the values. Built-in payloads
+    /// always use the built-in layout, which `PacketDefinitions::validate`
+    /// keeps redefinitions compatible with.
+    pub fn encode_using(
+        packet: &TelemetryPacket,
+        definitions: &PacketDefinitions,
+    ) -> Result<Vec<u8>, EncoderError> {
+        let (subsystem_id, payload) = if let TelemetryPayload::Generic(parameters) = &packet.payload
+        {
+            let id = packet.subsystem.id();
+            let definition = definitions.get(id).ok_or_else(|| {
+                EncoderError::UnsupportedPayload(format!(
+                    "no definition for {:?}",
+                    packet.subsystem
+                ))
+            })?;
+            // Calibrated parameters go back on the wire as their counts
+            let mut parameters = parameters.clone();
+            for (field, &count) in &packet.raw_counts {
+                parameters.insert(field.clone(), Value::Number(count as f64));
+            }
+            (id, definition.encode(&parameters)?)
+        } else if !packet.raw_counts.is_empty() {
+            Self::encode_raw_payload(packet)?
+        } else {
+            Self::encode_payload(&packet.payload)?
+        };
+        let len = u16::try_from(payload.len())
+            .map_err(|_| EncoderError::PayloadTooLong(payload.len()))?;
+
+        let mut data = Vec::with_capacity(HEADER_LEN + payload.len());
+        data.extend_from_slice(&packet.timestamp.to_be_bytes()); // Timestamp
+        data.push(subsystem_id); // Subsystem ID
+        data.extend_from_slice(&len.to_be_bytes()); // Len
+        data.extend_from_slice(&payload);
+        Ok(data)
+    }
+
+    /// Encodes `packet` with `CRC_FLAG` set and a CRC-16 trailer.
+    pub fn encode_with_crc(packet: &TelemetryPacket) -> Result<Vec<u8>, EncoderError> {
+        let mut data = Self::encode(packet)?;
+        data.reserve(CRC_LEN);
+        data[8] |= CRC_FLAG;
+        let crc = crc16_ccitt(&data);
+        data.extend_from_slice(&crc.to_be_bytes());
+        Ok(data)
+    }
+
+    /// Encodes `packet` with a CRC-16 trailer unless `crc` is `Absent`.
+    pub fn encode_for(packet: &TelemetryPacket, crc: CrcMode) -> Result<Vec<u8>, EncoderError> {
+        match crc {
+            CrcMode::Absent => Self::encode(packet),
+            _ => Self::encode_with_crc(packet),
+        }
+    }
+
+    fn encode_raw_payload(packet: &TelemetryPacket) -> Result<(u8, Vec<u8>), EncoderError> {
+        let subsystem_id = packet.subsystem.id();
+        let fields = parser::raw_fields(subsystem_id).ok_or_else(|| {
+            EncoderError::UnsupportedPayload(format!("raw {:?}", packet.subsystem))
+        })?;
+
+        let mut data = Vec::with_capacity(fields.len() * 2);
+        for field in fields {
+            let count = *packet
+                .raw_counts
+                .get(*field)
+                .ok_or_else(|| EncoderError::MissingRawCount(field.to_string()))?;
+            let count = u16::try_from(count)
+                .map_err(|_| EncoderError::RawCountTooLarge(field.to_string(), count))?;
+            data.extend_from_slice(&count.to_be_bytes());
+        }
+        Ok((subsystem_id | RAW_FLAG, data))
+    }
+
+    /// Returns the subsystem ID and the payload bytes without the header.
+    pub(crate) fn encode_payload(
+        payload: &TelemetryPayload,
+    ) -> Result<(u8, Vec<u8>), EncoderError> {
+        let mut data = Vec::new();
+        let subsystem_id = match payload {
+            TelemetryPayload::Power(p) => {
+                data.extend_from_slice(&p.voltage.to_be_bytes());
+                data.extend_from_slice(&p.current.to_be_bytes());
+                data.extend_from_slice(&p.battery_level.to_be_bytes());
+                0
+            }
+            TelemetryPayload::Thermal(t) => {
+                data.extend_from_slice(&t.temp_celsius.to_be_bytes());
+                // Without zones the frame keeps the single-sensor layout
+                if !t.zones.is_empty() {
+                    let zone_count = u8::try_from(t.zones.len())
+                        .map_err(|_| EncoderError::TooManyZones(t.zones.len()))?;
+                    data.push(zone_count);
+                    for zone in &t.zones {
+                        let name_len = u8::try_from(zone.name.len()).map_err(|_| {
+                            EncoderError::StringTooLong(zone.name.clone(), zone.name.len())
+                        })?;
+                        data.push(name_len);
+                        data.extend_from_slice(zone.name.as_bytes());
+                    }
+                    for zone in &t.zones {
+                        data.extend_from_slice(&zone.temp_celsius.to_be_bytes());
+                    }
+                }
+                1
+            }
+            TelemetryPayload::Aocs(a) => {
+                let wheel_count = u8::try_from(a.wheel_speeds.len())
+                    .map_err(|_| EncoderError::TooManyWheels(a.wheel_speeds.len()))?;
+
+                let q = &a.attitude;
+                for value in [q.w, q.x, q.y, q.z] {
+                    data.extend_from_slice(&value.to_be_bytes());
+                }
+                for rate in a.angular_rate {
+                    data.extend_from_slice(&rate.to_be_bytes());
+                }
+                data.push(a.control_mode.code());
+                data.push(wheel_count);
+                for speed in &a.wheel_speeds {
+                    data.extend_from_slice(&speed.to_be_bytes());
+                }
+                2
+            }
+            TelemetryPayload::StarTracker(s) => {
+                let target = s.target_id.as_deref().unwrap_or("");
+                let id_len = u8::try_from(target.len())
+                    .map_err(|_| EncoderError::TargetIdTooLong(target.len()))?;
+
+                data.extend_from_slice(&s.coordinates.right_ascension.to_be_bytes());
+                data.extend_from_slice(&s.coordinates.declination.to_be_bytes());
+                data.extend_from_slice(&s.confidence.to_be_bytes());
+                data.push(id_len);
+                data.extend_from_slice(target.as_bytes());
+                3
+            }
+            TelemetryPayload::Generic(_) => {
+                return Err(EncoderError::UnsupportedPayload("Generic".to_string()))
+            }
+            TelemetryPayload::Unknown => {
+                return Err(EncoderError::UnsupportedPayload("Unknown".to_string()))
+            }
+        };
+        Ok((subsystem_id, data))
+    }
+}
@@ -1,23 +1,86 @@
-use crate::{Monitor, Parser, Alert, AlertLevel, simulation};
+use crate::models::{self, ZONE_PREFIX};
+use crate::plot::{self, PlotView, TIME_WINDOWS};
+use crate::skymap::{self, Projection};
+use crate::source::POLL_INTERVAL;
+use crate::status::Health;
+use crate::{
+    simulation, AlertLevel, AlertState, AocsData, Archive, ArchiveError, ArchiveQuery,
+    ArchiveReader, ArchiveRecord, Backoff, CelestialCoordinates, Config, ControlMode, CrcMode,
+    EncoderError, LimitSet, Monitor, PacketDefinitions, PacketEncoder, PacketSink, Pass, Pipeline,
+    PipelineCommand, PipelineSnapshot, PowerData, Quaternion, SourceKind, StarTrackerReading,
+    Subsystem, TcpClientSource, TcpServerSource, TelemetryPacket, TelemetryPayload,
+    TelemetrySource, ThermalData, Threshold, UdpSource,
+};
 use eframe::egui;
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
+/// Charted fields with their titles.
+const CHARTS: [(&str, &str); 6] = [
+    ("Bus Voltage (V)", "power.voltage"),
+    ("Current (A)", "power.current"),
+    ("Battery (%)", "power.battery_level"),
+    ("Depth of Discharge (%)", "power.depth_of_discharge"),
+    ("Temperature (C)", "thermal.temp_celsius"),
+    ("Star Tracker Confidence", "star_tracker.confidence"),
+];
+
+const SUBSYSTEMS: [Subsystem; 4] = [
+    Subsystem::Power,
+    Subsystem::Thermal,
+    Subsystem::Aocs,
+    Subsystem::StarTracker,
+];
+
 #[derive(PartialEq)]
 enum InputSubsystem {
     Power,
     Thermal,
+    Aocs,
     StarTracker,
 }
 
 pub struct AstroMonitorApp {
-    monitor: Monitor,
-    packets: Vec<Vec<u8>>,
-    packet_index: usize,
-    logs: Vec<String>,
-    alerts: Vec<Alert>,
-    last_update: Instant,
-    simulation_speed: Duration,
-    paused: bool,
+    config: Config,
+    profile: String, // Active monitor profile
+    pipeline: Pipeline,
+    snapshot: PipelineSnapshot,
+    simulation_speed_ms: u64,
+
+    // Charts
+    show_charts: bool,
+    chart_views: [PlotView; CHARTS.len()],
+    chart_thresholds: Vec<Vec<Threshold>>,
+
+    // Sky map
+    show_sky_map: bool,
+    sky_projection: Projection,
+
+    // Thermal table
+    show_thermal: bool,
+    thermal_limits: Vec<LimitSet>,
+
+    // Power budget
+    show_power: bool,
+
+    // Archive browser
+    archive: Option<Arc<Mutex<Archive>>>,
+    show_archive: bool,
+    archive_subsystem: Option<Subsystem>,
+    archive_start: u64,
+    archive_end: u64,
+    archive_passes: Vec<Pass>,
+    archive_results: Vec<ArchiveRecord>,
+    archive_pass_scan: Option<Receiver<Result<Vec<Pass>, String>>>, // Scan in progress
+    archive_search: Option<Receiver<Result<Vec<ArchiveRecord>, String>>>, // Query in progress
+
+    // Live source
+    source_kind: SourceKind,
+    listen_port: u16,
+    tcp_address: String,
+    source_crc: CrcMode,
+    definitions: Arc<PacketDefinitions>, // Layouts the live sources decode with
 
     // Input fields
     input_subsystem: InputSubsystem,
@@ -25,6 +88,10 @@ pub struct AstroMonitorApp {
     input_current: f64,
     input_battery: f64,
     input_temp: f64,
+    input_quaternion: [f64; 4],
+    input_rates: [f64; 3],
+    input_wheels: [f64; 4],
+    input_mode: ControlMode,
     input_ra: f64,
     input_dec: f64,
     input_confidence: f64,
@@ -33,42 +100,148 @@ pub struct AstroMonitorApp {
 
 impl Default for AstroMonitorApp {
     fn default() -> Self {
-        Self {
-            monitor: Monitor::default(),
-            packets: simulation::generate_simulated_packets(),
-            packet_index: 0,
-            logs: Vec::new(),
-            alerts: Vec::new(),
-            last_update: Instant::now(),
-            simulation_speed: Duration::from_millis(1000),
-            paused: false,
+        Self::new(Config::default(), PacketDefinitions::default())
+    }
+}
+
+impl AstroMonitorApp {
+    /// Builds the dashboard from a validated configuration and the packet
+    /// definitions it names.
+    pub fn new(config: Config, definitions: PacketDefinitions) -> Self {
+        let definitions = Arc::new(definitions);
+        let simulation_speed_ms = config.simulation.interval_ms;
+        let mut snapshot = PipelineSnapshot::default();
+        let archive_dir = &config.archive.dir;
+        let archive = if !config.archive.enabled {
+            None
+        } else {
+            match Archive::open(archive_dir) {
+                Ok(archive) => {
+                    let archive = archive
+                        .segment_records(config.archive.segment_records)
+                        .pass_gap_seconds(config.archive.pass_gap_seconds);
+                    Some(Arc::new(Mutex::new(archive)))
+                }
+                Err(e) => {
+                    snapshot.logs.push_back(format!(
+                        "Archive disabled, cannot open '{}': {}",
+                        archive_dir.display(),
+                        e
+                    ));
+                    None
+                }
+            }
+        };
+        let sinks: Vec<Box<dyn PacketSink>> = archive
+            .iter()
+            .map(|a| Box::new(Arc::clone(a)) as Box<dyn PacketSink>)
+            .collect();
+        let profile = config.profile.clone();
+        let monitor = config.monitor().unwrap_or_default();
+        let chart_thresholds = CHARTS
+            .iter()
+            .map(|(_, field)| monitor.thresholds(field))
+            .collect();
+        let thermal_limits = thermal_limits(&monitor);
+        let pipeline = Pipeline::spawn(monitor, sinks);
+        pipeline.send(PipelineCommand::SetCalibration(
+            config.calibration(&definitions),
+        ));
+        pipeline.send(PipelineCommand::SetBattery(config.battery.clone()));
+        pipeline.send(PipelineCommand::SetCrc(config.source.crc));
+        pipeline.send(PipelineCommand::SetDefinitions(Arc::clone(&definitions)));
+        if config.simulation.autostart {
+            pipeline.send(PipelineCommand::Replay {
+                frames: simulation::generate_simulated_packets(config.source.crc),
+                interval: Duration::from_millis(simulation_speed_ms),
+            });
+        }
+
+        let source = &config.source;
+        let ui = &config.ui;
+        let injection = &ui.injection;
+        let mut app = Self {
+            profile,
+            pipeline,
+            snapshot,
+            simulation_speed_ms,
+
+            show_charts: ui.show_charts,
+            chart_views: Default::default(),
+            chart_thresholds,
+
+            show_sky_map: ui.show_sky_map,
+            sky_projection: ui.sky_projection,
+
+            show_thermal: ui.show_thermal,
+            thermal_limits,
+
+            show_power: ui.show_power,
+
+            archive,
+            show_archive: false,
+            archive_subsystem: None,
+            archive_start: 0,
+            archive_end: u64::MAX,
+            archive_passes: Vec::new(),
+            archive_results: Vec::new(),
+            archive_pass_scan: None,
+            archive_search: None,
+
+            source_kind: source.kind,
+            listen_port: source.port,
+            tcp_address: source.address.clone(),
+            source_crc: source.crc,
+            definitions,
 
             // Default input values
             input_subsystem: InputSubsystem::Power,
-            input_voltage: 28.0,
-            input_current: 2.5,
-            input_battery: 95.0,
-            input_temp: 25.0,
-            input_ra: 0.0,
-            input_dec: 0.0,
-            input_confidence: 1.0,
-            input_target: "Unknown".to_string(),
+            input_voltage: injection.voltage,
+            input_current: injection.current,
+            input_battery: injection.battery,
+            input_temp: injection.temp_celsius,
+            input_quaternion: [1.0, 0.0, 0.0, 0.0],
+            input_rates: [0.0; 3],
+            input_wheels: [1000.0; 4],
+            input_mode: ControlMode::FinePointing,
+            input_ra: injection.right_ascension,
+            input_dec: injection.declination,
+            input_confidence: injection.confidence,
+            input_target: injection.target.clone(),
+
+            config,
+        };
+        if app.config.source.autostart {
+            app.start_selected_source();
         }
+        app
+    }
+
+    fn switch_profile(&mut self, name: String) {
+        let Some(profile) = self.config.profile(&name) else {
+            return;
+        };
+        let monitor = profile.monitor();
+        self.chart_thresholds = CHARTS
+            .iter()
+            .map(|(_, field)| monitor.thresholds(field))
+            .collect();
+        self.thermal_limits = thermal_limits(&monitor);
+        self.pipeline.send(PipelineCommand::SetMonitor {
+            profile: name.clone(),
+            monitor: Box::new(monitor),
+        });
+        self.profile = name;
     }
 }
 
 impl eframe::App for AstroMonitorApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        // Simulation Logic
-        if !self.paused && self.packet_index < self.packets.len() {
-            if self.last_update.elapsed() >= self.simulation_speed {
-                let packet_data = self.packets[self.packet_index].clone();
-                self.process_packet(&packet_data, Some(self.packet_index + 1));
-                self.packet_index += 1;
-                self.last_update = Instant::now();
-            }
-            ctx.request_repaint();
+        // Pipeline Events
+        for event in self.pipeline.try_events() {
+            self.snapshot.apply(event);
         }
+        ctx.request_repaint_after(POLL_INTERVAL);
 
         // GUI Layout
         egui::CentralPanel::default().show(ctx, |ui| {
@@ -76,17 +249,184 @@ impl eframe::App for AstroMonitorApp {
 
             // Control Bar
             ui.horizontal(|ui| {
-                if ui.button(if self.paused { "Resume" } else { "Pause" }).clicked() {
-                    self.paused = !self.paused;
+                let paused = self.snapshot.paused;
+                if ui.button(if paused { "Resume" } else { "Pause" }).clicked() {
+                    self.pipeline.send(if paused {
+                        PipelineCommand::Resume
+                    } else {
+                        PipelineCommand::Pause
+                    });
                 }
                 if ui.button("Restart Simulation").clicked() {
-                    self.packet_index = 0;
-                    self.logs.clear();
-                    self.alerts.clear();
-                    self.last_update = Instant::now();
-                    self.paused = false;
+                    self.snapshot.logs.clear();
+                    self.snapshot.history.clear();
+                    self.snapshot.status.clear();
+                    self.pipeline.send(PipelineCommand::Restart);
+                }
+                ui.label("Interval (ms):");
+                let speed = ui.add(
+                    egui::DragValue::new(&mut self.simulation_speed_ms)
+                        .speed(10.0)
+                        .range(0..=10_000),
+                );
+                if speed.changed() {
+                    let interval = Duration::from_millis(self.simulation_speed_ms);
+                    self.pipeline.send(PipelineCommand::SetInterval(interval));
+                }
+                ui.label(format!(
+                    "Progress: {}/{}",
+                    self.snapshot.replay_sent, self.snapshot.replay_total
+                ));
+                let corrupted = format!("Corrupted: {}", self.snapshot.corrupted);
+                if self.snapshot.corrupted > 0 {
+                    ui.colored_label(egui::Color32::RED, corrupted);
+                } else {
+                    ui.label(corrupted);
+                }
+                ui.label("Profile:");
+                let mut selected = None;
+                egui::ComboBox::from_id_salt("monitor_profile")
+                    .selected_text(&self.profile)
+                    .show_ui(ui, |ui| {
+                        for profile in &self.config.profiles {
+                            let option =
+                                ui.selectable_label(profile.name == self.profile, &profile.name);
+                            let option = if profile.description.is_empty() {
+                                option
+                            } else {
+                                option.on_hover_text(&profile.description)
+                            };
+                            if option.clicked() && profile.name != self.profile {
+                                selected = Some(profile.name.clone());
+                            }
+                        }
+                    });
+                if let Some(name) = selected {
+                    self.switch_profile(name);
+                }
+                if ui.button("Charts").clicked() {
+                    self.show_charts = true;
+                }
+                if ui.button("Sky Map").clicked() {
+                    self.show_sky_map = true;
+                }
+                if ui.button("Thermal").clicked() {
+                    self.show_thermal = true;
+                }
+                if ui.button("Power").clicked() {
+                    self.show_power = true;
+                }
+                if ui
+                    .add_enabled(self.archive.is_some(), egui::Button::new("Archive"))
+                    .clicked()
+                {
+                    self.show_archive = true;
+                    self.refresh_passes();
+                }
+            });
+
+            // Live Source Bar
+            ui.horizontal(|ui| {
+                let running = self.snapshot.source.is_some();
+                ui.add_enabled_ui(!running, |ui| {
+                    ui.radio_value(&mut self.source_kind, SourceKind::Udp, "UDP");
+                    ui.radio_value(&mut self.source_kind, SourceKind::TcpClient, "TCP Client");
+                    ui.radio_value(&mut self.source_kind, SourceKind::TcpServer, "TCP Server");
+                    if self.source_kind == SourceKind::TcpClient {
+                        ui.label("Address:");
+                        ui.text_edit_singleline(&mut self.tcp_address);
+                    } else {
+                        ui.label("Port:");
+                        ui.add(egui::DragValue::new(&mut self.listen_port));
+                    }
+                    ui.label("CRC:");
+                    egui::ComboBox::from_id_salt("source_crc")
+                        .selected_text(format!("{:?}", self.source_crc))
+                        .show_ui(ui, |ui| {
+                            for mode in [CrcMode::Required, CrcMode::Optional, CrcMode::Absent] {
+                                ui.selectable_value(
+                                    &mut self.source_crc,
+                                    mode,
+                                    format!("{:?}", mode),
+                                );
+                            }
+                        });
+                });
+                if running {
+                    if ui.button("Stop Live").clicked() {
+                        self.pipeline.send(PipelineCommand::StopSource);
+                    }
+                } else if ui.button("Start Live").clicked() {
+                    self.start_selected_source();
+                }
+            });
+            if let Some(source) = &self.snapshot.source {
+                let status = match &source.status {
+                    Some(status) => {
+                        format!(
+                            "{} - {} bytes received",
+                            status.state, status.bytes_received
+                        )
+                    }
+                    None => "Starting".to_string(),
+                };
+                ui.label(format!(
+                    "{}: {} ({} packets, {} corrupted)",
+                    source.name, status, source.packets, source.corrupted
+                ));
+            }
+
+            ui.separator();
+
+            // Status Panel
+            let now = Instant::now();
+            ui.columns(self.snapshot.status.tiles.len(), |columns| {
+                for (ui, tile) in columns.iter_mut().zip(&self.snapshot.status.tiles) {
+                    let status = &self.snapshot.status;
+                    let health = status.health(&tile.subsystem, &self.snapshot.alerts, now);
+                    let color = match health {
+                        Health::NoData | Health::Stale => egui::Color32::GRAY,
+                        Health::Nominal => egui::Color32::GREEN,
+                        Health::Warning => egui::Color32::YELLOW,
+                        Health::Critical => egui::Color32::RED,
+                    };
+                    egui::Frame::group(ui.style())
+                        .stroke(egui::Stroke::new(2.0, color))
+                        .show(ui, |ui| {
+                            ui.set_min_width(ui.available_width());
+                            ui.colored_label(color, format!("{:?}", tile.subsystem));
+                            let Some(packet) = &tile.latest else {
+                                ui.label("No data");
+                                return;
+                            };
+                            for (field, value) in packet.fields() {
+                                let name = field.split_once('.').map_or(field.as_str(), |(_, n)| n);
+                                match packet.raw_counts.get(&field) {
+                                    Some(raw) => {
+                                        ui.small(format!("{}: {:.2} (raw {})", name, value, raw))
+                                    }
+                                    None => ui.small(format!("{}: {:.2}", name, value)),
+                                };
+                            }
+                            match &packet.payload {
+                                TelemetryPayload::Aocs(aocs) => {
+                                    ui.small(format!("mode: {:?}", aocs.control_mode));
+                                }
+                                TelemetryPayload::StarTracker(reading) => {
+                                    let target = reading.target_id.as_deref().unwrap_or("-");
+                                    ui.small(format!("target: {}", target));
+                                }
+                                _ => {}
+                            }
+                            let age = tile.age(now).unwrap_or_default().as_secs();
+                            let stale = if health == Health::Stale {
+                                " (stale)"
+                            } else {
+                                ""
+                            };
+                            ui.small(format!("Last packet: {}s ago{}", age, stale));
+                        });
                 }
-                ui.label(format!("Progress: {}/{}", self.packet_index, self.packets.len()));
             });
 
             ui.separator();
@@ -101,7 +441,7 @@ impl eframe::App for AstroMonitorApp {
                         .max_height(300.0)
                         .stick_to_bottom(true)
                         .show(ui, |ui| {
-                            for log in &self.logs {
+                            for log in &self.snapshot.logs {
                                 ui.label(log);
                             }
                         });
@@ -110,21 +450,41 @@ impl eframe::App for AstroMonitorApp {
                 // Alerts Column
                 columns[1].vertical(|ui| {
                     ui.heading("Active Alerts");
+                    let mut acknowledged = None;
                     egui::ScrollArea::vertical()
                         .id_salt("alerts_scroll")
                         .max_height(300.0)
                         .stick_to_bottom(true)
                         .show(ui, |ui| {
-                            for alert in &self.alerts {
-                                let text = format!("[{:?}] {} (Time: {})", alert.level, alert.message, alert.timestamp);
-                                let color = match alert.level {
-                                    AlertLevel::Critical => egui::Color32::RED,
-                                    AlertLevel::Warning => egui::Color32::YELLOW,
-                                    AlertLevel::Info => egui::Color32::LIGHT_BLUE,
+                            for alert in &self.snapshot.alerts {
+                                let text = format!(
+                                    "[{:?}] {} (Rule: {}, x{}, First: {}, Last: {}, {:?})",
+                                    alert.level,
+                                    alert.message,
+                                    alert.rule,
+                                    alert.occurrences,
+                                    alert.first_seen,
+                                    alert.last_seen,
+                                    alert.state
+                                );
+                                let color = match (alert.state, alert.level) {
+                                    (AlertState::ReturnedToNominal, _) => egui::Color32::GRAY,
+                                    (_, AlertLevel::Critical) => egui::Color32::RED,
+                                    (_, AlertLevel::Warning) => egui::Color32::YELLOW,
+                                    (_, AlertLevel::Info) => egui::Color32::LIGHT_BLUE,
                                 };
-                                ui.colored_label(color, text);
+                                ui.horizontal(|ui| {
+                                    let can_ack = alert.state != AlertState::Acknowledged;
+                                    if ui.add_enabled(can_ack, egui::Button::new("Ack")).clicked() {
+                                        acknowledged = Some(alert.id);
+                                    }
+                                    ui.colored_label(color, text);
+                                });
                             }
                         });
+                    if let Some(id) = acknowledged {
+                        self.pipeline.send(PipelineCommand::Acknowledge(id));
+                    }
                 });
             });
 
@@ -134,8 +494,17 @@ impl eframe::App for AstroMonitorApp {
             ui.heading("Manual Packet Injection");
             ui.horizontal(|ui| {
                 ui.radio_value(&mut self.input_subsystem, InputSubsystem::Power, "Power");
-                ui.radio_value(&mut self.input_subsystem, InputSubsystem::Thermal, "Thermal");
-                ui.radio_value(&mut self.input_subsystem, InputSubsystem::StarTracker, "Star Tracker");
+                ui.radio_value(
+                    &mut self.input_subsystem,
+                    InputSubsystem::Thermal,
+                    "Thermal",
+                );
+                ui.radio_value(&mut self.input_subsystem, InputSubsystem::Aocs, "AOCS");
+                ui.radio_value(
+                    &mut self.input_subsystem,
+                    InputSubsystem::StarTracker,
+                    "Star Tracker",
+                );
             });
 
             match self.input_subsystem {
@@ -146,7 +515,11 @@ impl eframe::App for AstroMonitorApp {
                         ui.label("Current (A):");
                         ui.add(egui::DragValue::new(&mut self.input_current).speed(0.1));
                         ui.label("Battery (%):");
-                        ui.add(egui::DragValue::new(&mut self.input_battery).speed(0.1).range(0.0..=100.0));
+                        ui.add(
+                            egui::DragValue::new(&mut self.input_battery)
+                                .speed(0.1)
+                                .range(0.0..=100.0),
+                        );
                     });
                 }
                 InputSubsystem::Thermal => {
@@ -155,16 +528,58 @@ impl eframe::App for AstroMonitorApp {
                         ui.add(egui::DragValue::new(&mut self.input_temp).speed(0.5));
                     });
                 }
+                InputSubsystem::Aocs => {
+                    ui.horizontal(|ui| {
+                        ui.label("Quaternion (w, x, y, z):");
+                        for q in &mut self.input_quaternion {
+                            ui.add(egui::DragValue::new(q).speed(0.01).range(-1.0..=1.0));
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Rates (deg/s):");
+                        for rate in &mut self.input_rates {
+                            ui.add(egui::DragValue::new(rate).speed(0.01));
+                        }
+                        ui.label("Mode:");
+                        egui::ComboBox::from_id_salt("aocs_mode")
+                            .selected_text(format!("{:?}", self.input_mode))
+                            .show_ui(ui, |ui| {
+                                for mode in [
+                                    ControlMode::Standby,
+                                    ControlMode::Detumble,
+                                    ControlMode::SunPointing,
+                                    ControlMode::NadirPointing,
+                                    ControlMode::FinePointing,
+                                ] {
+                                    ui.selectable_value(
+                                        &mut self.input_mode,
+                                        mode,
+                                        format!("{:?}", mode),
+                                    );
+                                }
+                            });
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Wheels (RPM):");
+                        for wheel in &mut self.input_wheels {
+                            ui.add(egui::DragValue::new(wheel).speed(10.0));
+                        }
+                    });
+                }
                 InputSubsystem::StarTracker => {
-                     ui.horizontal(|ui| {
+                    ui.horizontal(|ui| {
                         ui.label("RA:");
                         ui.add(egui::DragValue::new(&mut self.input_ra).speed(0.1));
                         ui.label("Dec:");
                         ui.add(egui::DragValue::new(&mut self.input_dec).speed(0.1));
                     });
-                     ui.horizontal(|ui| {
+                    ui.horizontal(|ui| {
                         ui.label("Confidence:");
-                        ui.add(egui::DragValue::new(&mut self.input_confidence).speed(0.01).range(0.0..=1.0));
+                        ui.add(
+                            egui::DragValue::new(&mut self.input_confidence)
+                                .speed(0.01)
+                                .range(0.0..=1.0),
+                        );
                         ui.label("Target:");
                         ui.text_edit_singleline(&mut self.input_target);
                     });
@@ -172,71 +587,482 @@ impl eframe::App for AstroMonitorApp {
             }
 
             if ui.button("Inject Packet").clicked() {
-                let packet = self.create_manual_packet();
-                self.process_packet(&packet, None);
+                match self.create_manual_packet() {
+                    Ok(packet) => self.pipeline.send(PipelineCommand::Inject(packet)),
+                    Err(e) => self
+                        .snapshot
+                        .logs
+                        .push_back(format!("Error encoding packet: {}", e)),
+                }
             }
         });
+
+        self.show_charts_window(ctx);
+        self.show_sky_map_window(ctx);
+        self.show_thermal_window(ctx);
+        self.show_power_window(ctx);
+        self.show_archive_window(ctx);
     }
 }
 
 impl AstroMonitorApp {
-    fn process_packet(&mut self, packet_data: &[u8], index: Option<usize>) {
-         let prefix = if let Some(idx) = index {
-            format!("Processing packet {}...", idx)
-        } else {
-            "Processing manual packet...".to_string()
+    fn start_selected_source(&mut self) {
+        let (crc, definitions) = (self.source_crc, Arc::clone(&self.definitions));
+        let source: Box<dyn TelemetrySource> = match self.source_kind {
+            SourceKind::Udp => Box::new(
+                UdpSource::on_port(self.listen_port)
+                    .crc(crc)
+                    .definitions(definitions),
+            ),
+            SourceKind::TcpServer => Box::new(
+                TcpServerSource::on_port(self.listen_port)
+                    .crc(crc)
+                    .definitions(definitions),
+            ),
+            SourceKind::TcpClient => match self.tcp_address.trim().parse() {
+                Ok(addr) => Box::new(
+                    TcpClientSource::new(addr, Backoff::default())
+                        .crc(crc)
+                        .definitions(definitions),
+                ),
+                Err(e) => {
+                    self.snapshot
+                        .logs
+                        .push_back(format!("Invalid address '{}': {}", self.tcp_address, e));
+                    return;
+                }
+            },
         };
-        self.logs.push(prefix);
+        self.pipeline.send(PipelineCommand::StartSource(source));
+    }
 
-        match Parser::parse(packet_data) {
-            Ok(packet) => {
-                self.logs.push(format!("Parsed: {:?} - {:?}", packet.subsystem, packet.payload));
+    fn show_charts_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_charts;
+        egui::Window::new("Telemetry Charts")
+            .open(&mut open)
+            .default_width(500.0)
+            .default_pos([290.0, 40.0])
+            .show(ctx, |ui| {
+                ui.label("Drag to pan, scroll to zoom, double-click to reset.");
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for (i, (title, field)) in CHARTS.iter().enumerate() {
+                        let view = &mut self.chart_views[i];
+                        ui.horizontal(|ui| {
+                            ui.strong(*title);
+                            let selected = TIME_WINDOWS
+                                .iter()
+                                .find(|(_, w)| *w == view.window)
+                                .map_or("Custom", |(label, _)| label);
+                            egui::ComboBox::from_id_salt(("chart_window", i))
+                                .selected_text(selected)
+                                .show_ui(ui, |ui| {
+                                    for (label, window) in TIME_WINDOWS {
+                                        let option =
+                                            ui.selectable_value(&mut view.window, window, label);
+                                        if option.clicked() {
+                                            view.reset();
+                                        }
+                                    }
+                                });
+                        });
+                        plot::show(
+                            ui,
+                            view,
+                            &self.snapshot.history,
+                            field,
+                            &self.chart_thresholds[i],
+                        );
+                    }
+                });
+            });
+        self.show_charts = open;
+    }
 
-                if let Some(alert) = self.monitor.analyze(&packet) {
-                    self.logs.push(format!(
-                        "*** ALERT: [{:?}] {} ***",
-                        alert.level, alert.message
-                    ));
-                    self.alerts.push(alert);
+    fn show_sky_map_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_sky_map;
+        egui::Window::new("Star Tracker Sky Map")
+            .open(&mut open)
+            .default_width(600.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.radio_value(&mut self.sky_projection, Projection::Aitoff, "Aitoff");
+                    ui.radio_value(
+                        &mut self.sky_projection,
+                        Projection::Equirectangular,
+                        "Equirectangular",
+                    );
+                    ui.label("Track coloured by confidence: red 0, yellow 0.5, green 1.");
+                });
+                skymap::show(ui, self.sky_projection, &self.snapshot.history);
+            });
+        self.show_sky_map = open;
+    }
+
+    fn show_thermal_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_thermal;
+        egui::Window::new("Thermal Zones")
+            .open(&mut open)
+            .default_width(420.0)
+            .show(ctx, |ui| {
+                let latest = self
+                    .snapshot
+                    .status
+                    .get(&Subsystem::Thermal)
+                    .and_then(|tile| tile.latest.as_ref());
+                let Some(TelemetryPayload::Thermal(thermal)) = latest.map(|p| &p.payload) else {
+                    ui.label("No thermal data");
+                    return;
+                };
+                let rows = std::iter::once(("primary", "thermal.temp_celsius".to_string(), thermal.temp_celsius))
+                    .chain(
+                        thermal
+                            .zones
+                            .iter()
+                            .map(|z| (z.name.as_str(), models::zone_path(&z.name), z.temp_celsius)),
+                    );
+
+                egui::Grid::new("thermal_zones")
+                    .striped(true)
+                    .num_columns(4)
+                    .show(ui, |ui| {
+                        ui.strong("Zone");
+                        ui.strong("Temp (C)");
+                        ui.strong("Min");
+                        ui.strong("Max");
+                        ui.end_row();
+
+                        for (name, field, temp) in rows {
+                            let limits = self.thermal_limits.iter().find(|l| l.field == field);
+                            let color = match limits.and_then(|l| l.classify(temp)) {
+                                Some((band, _)) => match band.level() {
+                                    AlertLevel::Critical => egui::Color32::RED,
+                                    _ => egui::Color32::YELLOW,
+                                },
+                                None => egui::Color32::GREEN,
+                            };
+                            let limit = |value: Option<f64>| {
+                                value.map_or("-".to_string(), |v| format!("{:.1}", v))
+                            };
+                            ui.colored_label(color, name);
+                            ui.colored_label(color, format!("{:.1}", temp));
+                            ui.label(limit(limits.and_then(|l| l.yellow_low.or(l.red_low))));
+                            ui.label(limit(limits.and_then(|l| l.yellow_high.or(l.red_high))));
+                            ui.end_row();
+                        }
+                    });
+                ui.small("Min/max are the operating limits; yellow is outside them, red outside the survival limits.");
+            });
+        self.show_thermal = open;
+    }
+
+    fn show_power_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_power;
+        egui::Window::new("Power Budget")
+            .open(&mut open)
+            .default_width(420.0)
+            .show(ctx, |ui| {
+                let latest = self
+                    .snapshot
+                    .status
+                    .get(&Subsystem::Power)
+                    .and_then(|tile| tile.latest.as_ref());
+                let Some(packet) = latest else {
+                    ui.label("No power data");
+                    return;
+                };
+                let value = |path: &str, precision: usize| {
+                    packet
+                        .field(path)
+                        .map_or("-".to_string(), |v| format!("{:.*}", precision, v))
+                };
+                let state = match packet.field("power.eclipse") {
+                    Some(e) if e > 0.0 => "Eclipse (discharging)",
+                    Some(_) => "Sunlit (charging)",
+                    None => "-",
+                };
+                let time_to_min = packet
+                    .field("power.time_to_min_battery")
+                    .map_or("-".to_string(), |s| {
+                        format!("{}h {:02}m", s as u64 / 3600, s as u64 % 3600 / 60)
+                    });
+
+                egui::Grid::new("power_budget")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        let rows = [
+                            ("State", state.to_string()),
+                            ("Battery, reported (%)", value("power.battery_level", 1)),
+                            ("Battery, estimated (%)", value("power.soc_estimate", 1)),
+                            ("Estimate error (%)", value("power.soc_error", 1)),
+                            ("Charge used (Ah)", value("power.charge_used_ah", 3)),
+                            (
+                                "Depth of discharge (%)",
+                                value("power.depth_of_discharge", 1),
+                            ),
+                            ("Time to minimum", time_to_min),
+                        ];
+                        for (label, text) in rows {
+                            ui.label(label);
+                            ui.label(text);
+                            ui.end_row();
+                        }
+                    });
+
+                ui.separator();
+                ui.strong("Orbits");
+                egui::Grid::new("power_orbits")
+                    .striped(true)
+                    .num_columns(4)
+                    .show(ui, |ui| {
+                        ui.strong("#");
+                        ui.strong("Eclipse Start");
+                        ui.strong("Eclipse (s)");
+                        ui.strong("DoD (%)");
+                        ui.end_row();
+
+                        for orbit in self.snapshot.orbits.iter().rev().take(10) {
+                            ui.label(orbit.number.to_string());
+                            ui.label(orbit.eclipse_start.to_string());
+                            ui.label(
+                                orbit
+                                    .eclipse_seconds()
+                                    .map_or("in eclipse".to_string(), |s| s.to_string()),
+                            );
+                            ui.label(format!("{:.1}", orbit.depth_of_discharge));
+                            ui.end_row();
+                        }
+                    });
+            });
+        self.show_power = open;
+    }
+
+    fn show_archive_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_archive;
+        egui::Window::new("Archive Browser")
+            .open(&mut open)
+            .default_width(600.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Passes:");
+                    if ui.button("Refresh").clicked() {
+                        self.refresh_passes();
+                    }
+                    if let Some(passes) =
+                        poll_archive(&mut self.archive_pass_scan, &mut self.snapshot)
+                    {
+                        self.archive_passes = passes;
+                    }
+                    if self.archive_pass_scan.is_some() {
+                        ui.label("Scanning...");
+                    }
+                });
+                let mut selected = None;
+                egui::ScrollArea::vertical()
+                    .id_salt("passes_scroll")
+                    .max_height(100.0)
+                    .show(ui, |ui| {
+                        for pass in &self.archive_passes {
+                            let label =
+                                format!("{} - {} ({} packets)", pass.start, pass.end, pass.packets);
+                            if ui.selectable_label(false, label).clicked() {
+                                selected = Some(*pass);
+                            }
+                        }
+                    });
+                if let Some(pass) = selected {
+                    self.archive_start = pass.start;
+                    self.archive_end = pass.end;
+                    self.search_archive();
                 }
-            }
-            Err(e) => {
-                self.logs.push(format!("Error parsing packet: {}", e));
-            }
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.label("Subsystem:");
+                    egui::ComboBox::from_id_salt("archive_subsystem")
+                        .selected_text(match &self.archive_subsystem {
+                            Some(subsystem) => format!("{:?}", subsystem),
+                            None => "All".to_string(),
+                        })
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut self.archive_subsystem, None, "All");
+                            for subsystem in SUBSYSTEMS {
+                                let label = format!("{:?}", subsystem);
+                                ui.selectable_value(
+                                    &mut self.archive_subsystem,
+                                    Some(subsystem),
+                                    label,
+                                );
+                            }
+                        });
+                    ui.label("From:");
+                    ui.add(egui::DragValue::new(&mut self.archive_start));
+                    ui.label("To:");
+                    ui.add(egui::DragValue::new(&mut self.archive_end));
+                    if ui.button("Search").clicked() {
+                        self.search_archive();
+                    }
+                });
+
+                if let Some(records) = poll_archive(&mut self.archive_search, &mut self.snapshot) {
+                    self.archive_results = records;
+                }
+                if self.archive_search.is_some() {
+                    ui.label("Searching...");
+                } else {
+                    ui.label(format!("{} records", self.archive_results.len()));
+                }
+                egui::ScrollArea::vertical()
+                    .id_salt("archive_scroll")
+                    .max_height(300.0)
+                    .show(ui, |ui| {
+                        for record in &self.archive_results {
+                            match record {
+                                ArchiveRecord::Packet(packet) => {
+                                    ui.label(format!(
+                                        "{} {:?} - {:?}",
+                                        packet.timestamp, packet.subsystem, packet.payload
+                                    ));
+                                }
+                                ArchiveRecord::Alert(archived) => {
+                                    let alert = &archived.alert;
+                                    let color = match alert.level {
+                                        AlertLevel::Critical => egui::Color32::RED,
+                                        AlertLevel::Warning => egui::Color32::YELLOW,
+                                        AlertLevel::Info => egui::Color32::LIGHT_BLUE,
+                                    };
+                                    ui.colored_label(
+                                        color,
+                                        format!(
+                                            "{} [{:?}] {} ({})",
+                                            alert.timestamp, alert.level, alert.message, alert.rule
+                                        ),
+                                    );
+                                }
+                            }
+                        }
+                    });
+            });
+        self.show_archive = open;
+    }
+
+    fn refresh_passes(&mut self) {
+        if let Some(archive) = &self.archive {
+            self.archive_pass_scan = Some(read_archive(archive, |reader| reader.passes()));
         }
     }
 
-    fn create_manual_packet(&self) -> Vec<u8> {
-        let mut packet = Vec::new();
-        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
-        packet.extend_from_slice(&timestamp.to_be_bytes());
-
-        match self.input_subsystem {
-            InputSubsystem::Power => {
-                packet.push(0); // Subsystem ID
-                packet.extend_from_slice(&(24u16).to_be_bytes()); // Len
-                packet.extend_from_slice(&self.input_voltage.to_be_bytes());
-                packet.extend_from_slice(&self.input_current.to_be_bytes());
-                packet.extend_from_slice(&self.input_battery.to_be_bytes());
-            }
-            InputSubsystem::Thermal => {
-                packet.push(1); // Subsystem ID
-                packet.extend_from_slice(&(8u16).to_be_bytes()); // Len
-                packet.extend_from_slice(&self.input_temp.to_be_bytes());
-            }
-            InputSubsystem::StarTracker => {
-                packet.push(3); // Subsystem ID
-                // Calculate len: 3*8 (f64) + 1 (u8) + target.len()
-                let len = 24 + 1 + self.input_target.len() as u16;
-                packet.extend_from_slice(&len.to_be_bytes()); // Len
-
-                packet.extend_from_slice(&self.input_ra.to_be_bytes());
-                packet.extend_from_slice(&self.input_dec.to_be_bytes());
-                packet.extend_from_slice(&self.input_confidence.to_be_bytes());
-                packet.push(self.input_target.len() as u8);
-                packet.extend_from_slice(self.input_target.as_bytes());
+    fn search_archive(&mut self) {
+        let Some(archive) = &self.archive else {
+            return;
+        };
+        let mut query = ArchiveQuery::new().range(self.archive_start, self.archive_end);
+        query.subsystem = self.archive_subsystem.clone();
+        self.archive_search = Some(read_archive(archive, move |reader| reader.query(&query)));
+    }
+
+    fn create_manual_packet(&self) -> Result<Vec<u8>, EncoderError> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+
+        let (subsystem, payload) = match self.input_subsystem {
+            InputSubsystem::Power => (
+                Subsystem::Power,
+                TelemetryPayload::Power(PowerData {
+                    voltage: self.input_voltage,
+                    current: self.input_current,
+                    battery_level: self.input_battery,
+                }),
+            ),
+            InputSubsystem::Thermal => (
+                Subsystem::Thermal,
+                TelemetryPayload::Thermal(ThermalData {
+                    temp_celsius: self.input_temp,
+                    zones: Vec::new(),
+                }),
+            ),
+            InputSubsystem::Aocs => {
+                let [w, x, y, z] = self.input_quaternion;
+                (
+                    Subsystem::Aocs,
+                    TelemetryPayload::Aocs(AocsData {
+                        attitude: Quaternion { w, x, y, z },
+                        angular_rate: self.input_rates,
+                        wheel_speeds: self.input_wheels.to_vec(),
+                        control_mode: self.input_mode,
+                    }),
+                )
             }
-        }
-        packet
+            InputSubsystem::StarTracker => (
+                Subsystem::StarTracker,
+                TelemetryPayload::StarTracker(StarTrackerReading {
+                    target_id: Some(self.input_target.clone()).filter(|t| !t.is_empty()),
+                    coordinates: CelestialCoordinates {
+                        right_ascension: self.input_ra,
+                        declination: self.input_dec,
+                    },
+                    confidence: self.input_confidence,
+                }),
+            ),
+        };
+
+        let packet = TelemetryPacket {
+            timestamp,
+            subsystem,
+            payload,
+            raw_counts: Default::default(),
+            derived: Default::default(),
+        };
+        PacketEncoder::encode_for(&packet, self.config.source.crc)
     }
 }
+
+/// Runs `read` on a reader of `archive` on its own thread. The archive is
+/// only locked while the reader is taken, so slow segment reads hold up
+/// neither the UI nor the pipeline's appends.
+fn read_archive<T: Send + 'static>(
+    archive: &Mutex<Archive>,
+    read: impl FnOnce(&ArchiveReader) -> Result<T, ArchiveError> + Send + 'static,
+) -> Receiver<Result<T, String>> {
+    let reader = archive
+        .lock()
+        .map(|archive| archive.reader())
+        .map_err(|_| "archive is unavailable after a writer panicked".to_string());
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        let result = reader.and_then(|reader| read(&reader).map_err(|e| e.to_string()));
+        let _ = sender.send(result);
+    });
+    receiver
+}
+
+/// The result of a `read_archive` call once it is done; errors are logged.
+fn poll_archive<T>(
+    pending: &mut Option<Receiver<Result<T, String>>>,
+    snapshot: &mut PipelineSnapshot,
+) -> Option<T> {
+    let result = match pending.as_ref()?.try_recv() {
+        Ok(Ok(value)) => Some(value),
+        Ok(Err(e)) => {
+            snapshot
+                .logs
+                .push_back(format!("Error reading archive: {}", e));
+            None
+        }
+        Err(mpsc::TryRecvError::Empty) => return None,
+        Err(mpsc::TryRecvError::Disconnected) => None,
+    };
+    *pending = None;
+    result
+}
+
+/// Limits on the primary thermal sensor and on every zone, for the thermal table.
+fn thermal_limits(monitor: &Monitor) -> Vec<LimitSet> {
+    monitor
+        .limits
+        .iter()
+        .filter(|l| l.field == "thermal.temp_celsius" || l.field.starts_with(ZONE_PREFIX))
+        .cloned()
+        .collect()
+}
@@ -0,0 +1,87 @@
+use crate::models::{StarTrackerReading, TelemetryPacket, TelemetryPayload};
+use crate::monitor::{Alert, AlertLevel};
+use std::collections::{HashMap, VecDeque};
+
+/// Samples kept per field; older ones are dropped first.
+pub const MAX_SAMPLES: usize = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AlertMarker {
+    pub timestamp: u64,
+    pub level: AlertLevel,
+}
+
+/// Recent values of every numeric field, with the alerts raised on them,
+/// and recent star tracker readings, for plotting.
+#[derive(Debug, Clone, Default)]
+pub struct TelemetryHistory {
+    samples: HashMap<String, VecDeque<(u64, f64)>>,
+    markers: HashMap<String, VecDeque<AlertMarker>>,
+    pointing: VecDeque<(u64, StarTrackerReading)>,
+}
+
+impl TelemetryHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, packet: &TelemetryPacket, alerts: &[Alert]) {
+        for (field, value) in packet.fields() {
+            push_capped(
+                self.samples.entry(field).or_default(),
+                (packet.timestamp, value),
+            );
+        }
+        if let TelemetryPayload::StarTracker(reading) = &packet.payload {
+            push_capped(&mut self.pointing, (packet.timestamp, reading.clone()));
+        }
+        for alert in alerts {
+            push_capped(
+                self.markers.entry(alert.field.clone()).or_default(),
+                AlertMarker {
+                    timestamp: alert.timestamp,
+                    level: alert.level,
+                },
+            );
+        }
+    }
+
+    /// Samples of `field` in arrival order.
+    pub fn samples(&self, field: &str) -> impl Iterator<Item = (u64, f64)> + '_ {
+        self.samples.get(field).into_iter().flatten().copied()
+    }
+
+    /// Alerts raised on `field` in arrival order.
+    pub fn markers(&self, field: &str) -> impl Iterator<Item = AlertMarker> + '_ {
+        self.markers.get(field).into_iter().flatten().copied()
+    }
+
+    /// Star tracker readings in arrival order.
+    pub fn pointing(&self) -> impl DoubleEndedIterator<Item = &(u64, StarTrackerReading)> {
+        self.pointing.iter()
+    }
+
+    /// Latest timestamp among the samples of `field`, which need not be
+    /// the last to arrive.
+    pub fn latest(&self, field: &str) -> Option<u64> {
+        self.samples.get(field)?.iter().map(|&(t, _)| t).max()
+    }
+
+    /// Earliest timestamp among the samples of `field`.
+    pub fn earliest(&self, field: &str) -> Option<u64> {
+        self.samples.get(field)?.iter().map(|&(t, _)| t).min()
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+        self.markers.clear();
+        self.pointing.clear();
+    }
+}
+
+fn push_capped<T>(queue: &mut VecDeque<T>, item: T) {
+    if queue.len() == MAX_SAMPLES {
+        queue.pop_front();
+    }
+    queue.push_back(item);
+}
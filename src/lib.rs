@@ -1,18 +1,90 @@
 pub mod models;
 
 pub use models::{
-    CelestialCoordinates, PowerData, StarTrackerReading, Subsystem, TelemetryPacket,
-    TelemetryPayload, ThermalData,
+    AocsData, CelestialCoordinates, ControlMode, ParameterMap, PowerData, Quaternion,
+    StarTrackerReading, Subsystem, TelemetryPacket, TelemetryPayload, ThermalData, ThermalZone,
+    Value, FIELD_PATHS,
 };
 
+pub mod crc;
+pub use crc::{crc16_ccitt, CrcMode};
+
 pub mod parser;
-pub use parser::{Parser, ParserError};
+pub use parser::{PacketFramer, Parser, ParserError};
+
+pub mod definition;
+pub use definition::{
+    DefinitionError, Endian, FieldDefinition, FieldType, PacketDefinition, PacketDefinitions,
+};
+
+pub mod calibration;
+pub use calibration::{Calibration, CalibrationSet, ParameterCalibration};
+
+pub mod encoder;
+pub use encoder::{EncoderError, PacketEncoder};
+
+pub mod ccsds;
+pub use ccsds::{CcsdsParser, CucFormat, PrimaryHeader, SequenceFlags};
+
+pub mod power;
+pub use power::{BatteryConfig, Orbit, PowerAnalytics};
+
+pub mod rules;
+pub use rules::{Comparison, Rule, RuleError, RuleSet};
+
+pub mod trend;
+pub use trend::{Direction, TrendCheck};
+
+pub mod watchdog;
+pub use watchdog::{ReportingPeriod, Watchdog};
 
 pub mod monitor;
-pub use monitor::{Alert, AlertLevel, Monitor};
+pub use monitor::{Alert, AlertLevel, LimitBand, LimitSet, Monitor, Persistence, Threshold};
+
+pub mod alerts;
+pub use alerts::{AlertManager, AlertRecord, AlertState, MAX_CLEARED};
+
+pub mod source;
+pub use source::{ConnectionState, SourceEvent, SourceStatus, TelemetrySource};
+
+pub mod udp;
+pub use udp::UdpSource;
+
+pub mod tcp;
+pub use tcp::{Backoff, TcpClientSource, TcpServerSource};
+
+pub mod stats;
+pub use stats::{FieldStats, SubsystemStats, TelemetryStats};
+
+pub mod archive;
+pub use archive::{
+    Archive, ArchiveError, ArchiveQuery, ArchiveReader, ArchiveRecord, ArchivedAlert, Pass,
+    Segment, DEFAULT_PASS_GAP_SECONDS,
+};
+
+pub mod history;
+pub use history::{AlertMarker, TelemetryHistory};
+
+pub mod status;
+pub use status::{Health, StatusBoard, SubsystemStatus, DEFAULT_STALE_AFTER};
+
+pub mod pipeline;
+pub use pipeline::{
+    LiveSource, PacketSink, Pipeline, PipelineCommand, PipelineEvent, PipelineSnapshot,
+    EVENT_CAPACITY,
+};
 
 pub mod simulation;
 
+pub mod plot;
+pub use plot::PlotView;
+
+pub mod skymap;
+pub use skymap::{CatalogStar, Projection, BRIGHT_STARS};
+
+pub mod config;
+pub use config::{Config, ConfigError, Profile, SourceKind};
+
 pub mod gui;
 
 #[cfg(test)]
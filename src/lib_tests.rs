@@ -1,13 +1,12 @@
 use super::*;
 
-
 #[test]
 fn test_parse_power() {
     let mut data = Vec::new();
     let timestamp: u64 = 1627849200;
     data.extend_from_slice(&timestamp.to_be_bytes()); // Timestamp
     data.push(0); // Subsystem: Power
-    data.extend_from_slice(&(24u16).to_be_bytes()); // Len (ignored by logic but placeholder)
+    data.extend_from_slice(&(24u16).to_be_bytes()); // Len
 
     let voltage = 28.5f64;
     let current = 2.0f64;
@@ -37,12 +36,11 @@ fn test_parse_star_tracker() {
     let timestamp: u64 = 1627849200;
     data.extend_from_slice(&timestamp.to_be_bytes());
     data.push(3); // Subsystem: StarTracker
-    data.extend_from_slice(&(0u16).to_be_bytes()); // Placeholder Len
-
     let ra = 120.5f64;
     let dec = -30.2f64;
     let conf = 0.99f64;
     let target_id = "AlphaCentauri";
+    data.extend_from_slice(&(25 + target_id.len() as u16).to_be_bytes()); // Len
 
     data.extend_from_slice(&ra.to_be_bytes());
     data.extend_from_slice(&dec.to_be_bytes());
@@ -64,7 +62,7 @@ fn test_parse_star_tracker() {
 
 #[test]
 fn test_monitor_alerts() {
-    let monitor = Monitor::default();
+    let mut monitor = Monitor::default();
 
     // Critical Battery
     let packet = TelemetryPacket {
@@ -75,11 +73,13 @@ fn test_monitor_alerts() {
             current: 1.0,
             battery_level: 10.0, // Below 20.0 default
         }),
+        raw_counts: Default::default(),
+        derived: Default::default(),
     };
 
-    let alert = monitor.analyze(&packet);
-    assert!(alert.is_some());
-    assert_eq!(alert.unwrap().level, AlertLevel::Critical);
+    let alerts = monitor.analyze(&packet);
+    let battery = alerts.iter().find(|a| a.rule == "Battery.red_low").unwrap();
+    assert_eq!(battery.level, AlertLevel::Critical);
 
     // Good Battery
     let packet_good = TelemetryPacket {
@@ -90,6 +90,2519 @@ fn test_monitor_alerts() {
             current: 1.0,
             battery_level: 50.0,
         }),
+        raw_counts: Default::default(),
+        derived: Default::default(),
+    };
+    assert!(monitor.analyze(&packet_good).is_empty());
+}
+
+fn thermal_bytes(timestamp: u64, temp: f64) -> Vec<u8> {
+    PacketEncoder::encode(&thermal_packet(timestamp, temp)).unwrap()
+}
+
+#[test]
+fn test_parse_rejects_length_mismatch() {
+    // Trailing garbage after the declared payload
+    let mut data = thermal_bytes(1, 20.0);
+    data.push(0xFF);
+    assert!(matches!(
+        Parser::parse(&data),
+        Err(ParserError::LengthMismatch {
+            declared: 8,
+            actual: 9
+        })
+    ));
+
+    // Declared length larger than the thermal layout with no zones
+    let mut data = thermal_bytes(1, 20.0);
+    data[10] = 10;
+    data.extend_from_slice(&[0, 0]);
+    assert!(matches!(
+        Parser::parse(&data),
+        Err(ParserError::LengthMismatch {
+            declared: 10,
+            actual: 9
+        })
+    ));
+}
+
+#[test]
+fn test_framer_splits_stream() {
+    let mut stream = Vec::new();
+    for packet in simulation::generate_simulated_packets(CrcMode::Required) {
+        stream.extend_from_slice(&packet);
+    }
+
+    let mut framer = PacketFramer::new();
+    // Feed in uneven chunks to exercise partial frames
+    let mut parsed = Vec::new();
+    for chunk in stream.chunks(7) {
+        framer.push(chunk);
+        while let Some(result) = framer.next_packet() {
+            parsed.push(result.unwrap());
+        }
+    }
+
+    // Simulated frames carry the 2-byte CRC trailer
+    assert_eq!(parsed.len(), 5);
+    assert_eq!(parsed[0].1, 37);
+    assert_eq!(parsed[2].0.subsystem, Subsystem::StarTracker);
+    assert_eq!(parsed[2].1, 44);
+    assert_eq!(framer.pending(), 0);
+}
+
+#[test]
+fn test_framer_skips_bad_frame() {
+    let mut bad = thermal_bytes(1, 20.0);
+    bad[8] = 9; // Unknown subsystem
+    let mut framer = PacketFramer::new();
+    framer.push(&bad);
+    framer.push(&thermal_bytes(2, 21.0));
+
+    assert!(matches!(
+        framer.next_packet(),
+        Some(Err(ParserError::InvalidSubsystem(9)))
+    ));
+    let (packet, used) = framer.next_packet().unwrap().unwrap();
+    assert_eq!(packet.timestamp, 2);
+    assert_eq!(used, 19);
+    assert!(framer.next_packet().is_none());
+    assert_eq!(framer.pending(), 0);
+
+    // A corrupt length is reported once and the framer hunts for the next
+    // header instead of waiting for 60000 bytes
+    let mut bad = PacketEncoder::encode_with_crc(&power_packet(1627849200, 1.5, 80.0)).unwrap();
+    bad[9..11].copy_from_slice(&60000u16.to_be_bytes());
+    framer.push(&bad);
+    for timestamp in [1627849210, 1627849220] {
+        framer.push(&PacketEncoder::encode_with_crc(&thermal_packet(timestamp, 21.0)).unwrap());
+    }
+    assert!(matches!(
+        framer.next_packet(),
+        Some(Err(ParserError::InvalidLength {
+            id: 0,
+            declared: 60000
+        }))
+    ));
+    let timestamps: Vec<u64> = framer.map(|r| r.unwrap().0.timestamp).collect();
+    assert_eq!(timestamps, [1627849210, 1627849220]);
+}
+
+#[test]
+fn test_ccsds_parse_thermal() {
+    let mut data = Vec::new();
+    // Version 0, TM, secondary header present, APID 101
+    data.extend_from_slice(&(0x0800u16 | 101).to_be_bytes());
+    // Unsegmented, sequence count 42
+    data.extend_from_slice(&(0xC000u16 | 42).to_be_bytes());
+    // Data field: CUC (4 + 2) + thermal payload (8), minus one
+    data.extend_from_slice(&(13u16).to_be_bytes());
+    data.extend_from_slice(&(1627849200u32).to_be_bytes());
+    data.extend_from_slice(&[0x80, 0x00]);
+    data.extend_from_slice(&(42.5f64).to_be_bytes());
+
+    let header = CcsdsParser::parse_primary_header(&data).unwrap();
+    assert_eq!(header.apid, 101);
+    assert_eq!(header.sequence_flags, SequenceFlags::Unsegmented);
+    assert_eq!(header.sequence_count, 42);
+    assert_eq!(header.data_length, 14);
+
+    let packet = CcsdsParser::default().parse(&data).unwrap();
+    assert_eq!(packet.timestamp, 1627849200);
+    assert_eq!(packet.subsystem, Subsystem::Thermal);
+    assert_eq!(
+        packet.payload,
+        TelemetryPayload::Thermal(ThermalData {
+            temp_celsius: 42.5,
+            zones: Vec::new(),
+        })
+    );
+
+    let mut parser = CcsdsParser::default();
+    parser.time_format.epoch_offset = u64::MAX - 1000;
+    assert!(matches!(
+        parser.parse(&data),
+        Err(ParserError::TimestampOverflow {
+            coarse: 1627849200,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn test_ccsds_apid_table() {
+    let mut data = Vec::new();
+    data.extend_from_slice(&(7u16).to_be_bytes()); // APID 7, no secondary header
+    data.extend_from_slice(&(0xC000u16).to_be_bytes());
+    data.extend_from_slice(&(7u16).to_be_bytes());
+    data.extend_from_slice(&(-5.0f64).to_be_bytes());
+
+    let mut parser = CcsdsParser::default();
+    assert!(matches!(
+        parser.parse(&data),
+        Err(ParserError::UnknownApid(7))
+    ));
+
+    parser.map_apid(7, Subsystem::Thermal);
+    let packet = parser.parse(&data).unwrap();
+    assert_eq!(packet.timestamp, 0);
+    assert_eq!(packet.subsystem, Subsystem::Thermal);
+}
+
+#[test]
+fn test_encode_parse_round_trip() {
+    let payloads = vec![
+        TelemetryPayload::Power(PowerData {
+            voltage: 28.5,
+            current: 2.0,
+            battery_level: 95.0,
+        }),
+        TelemetryPayload::Thermal(ThermalData {
+            temp_celsius: -12.25,
+            zones: Vec::new(),
+        }),
+        TelemetryPayload::Aocs(AocsData {
+            attitude: Quaternion {
+                w: 0.5,
+                x: 0.5,
+                y: -0.5,
+                z: 0.5,
+            },
+            angular_rate: [0.1, -0.2, 0.05],
+            wheel_speeds: vec![1500.0, -2000.0, 0.0],
+            control_mode: ControlMode::NadirPointing,
+        }),
+        TelemetryPayload::StarTracker(StarTrackerReading {
+            target_id: Some("Vega".to_string()),
+            coordinates: CelestialCoordinates {
+                right_ascension: 279.23,
+                declination: 38.78,
+            },
+            confidence: 0.91,
+        }),
+        TelemetryPayload::StarTracker(StarTrackerReading {
+            target_id: None,
+            coordinates: CelestialCoordinates {
+                right_ascension: 0.0,
+                declination: -90.0,
+            },
+            confidence: 0.5,
+        }),
+    ];
+
+    for payload in payloads {
+        let subsystem = match payload {
+            TelemetryPayload::Power(_) => Subsystem::Power,
+            TelemetryPayload::Thermal(_) => Subsystem::Thermal,
+            TelemetryPayload::Aocs(_) => Subsystem::Aocs,
+            _ => Subsystem::StarTracker,
+        };
+        let packet = TelemetryPacket {
+            timestamp: 1627849200,
+            subsystem,
+            payload,
+            raw_counts: Default::default(),
+            derived: Default::default(),
+        };
+        let bytes = PacketEncoder::encode(&packet).unwrap();
+        assert_eq!(Parser::parse(&bytes).unwrap(), packet);
+    }
+}
+
+#[test]
+fn test_encode_rejects_unknown_payload() {
+    let packet = TelemetryPacket {
+        timestamp: 1,
+        subsystem: Subsystem::Power,
+        payload: TelemetryPayload::Unknown,
+        raw_counts: Default::default(),
+        derived: Default::default(),
+    };
+    assert!(matches!(
+        PacketEncoder::encode(&packet),
+        Err(EncoderError::UnsupportedPayload(_))
+    ));
+}
+
+fn aocs_packet(rate: f64, wheel: f64, attitude: Quaternion) -> TelemetryPacket {
+    TelemetryPacket {
+        timestamp: 200,
+        subsystem: Subsystem::Aocs,
+        payload: TelemetryPayload::Aocs(AocsData {
+            attitude,
+            angular_rate: [0.0, rate, 0.0],
+            wheel_speeds: vec![wheel, 0.0, 0.0, 0.0],
+            control_mode: ControlMode::FinePointing,
+        }),
+        raw_counts: Default::default(),
+        derived: Default::default(),
+    }
+}
+
+#[test]
+fn test_monitor_aocs_alerts() {
+    let mut monitor = Monitor::default();
+    let identity = Quaternion {
+        w: 1.0,
+        x: 0.0,
+        y: 0.0,
+        z: 0.0,
+    };
+
+    assert!(monitor
+        .analyze(&aocs_packet(0.1, 1000.0, identity))
+        .is_empty());
+
+    let alert = monitor
+        .analyze(&aocs_packet(-3.0, 1000.0, identity))
+        .remove(0);
+    assert_eq!(alert.level, AlertLevel::Critical);
+    assert!(alert.message.starts_with("High Angular Rate"));
+
+    let alert = monitor
+        .analyze(&aocs_packet(0.1, -6500.0, identity))
+        .remove(0);
+    assert!(alert.message.starts_with("Reaction Wheel Saturation"));
+
+    let drifted = Quaternion {
+        w: 0.98,
+        ..identity
+    };
+    let alert = monitor
+        .analyze(&aocs_packet(0.1, 1000.0, drifted))
+        .remove(0);
+    assert!(alert.message.starts_with("Quaternion Norm Drift"));
+}
+
+#[test]
+fn test_parse_rejects_invalid_control_mode() {
+    let mut data = PacketEncoder::encode(&aocs_packet(
+        0.0,
+        0.0,
+        Quaternion {
+            w: 1.0,
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+        },
+    ))
+    .unwrap();
+    data[11 + 56] = 9; // Control mode byte
+    assert!(matches!(
+        Parser::parse(&data),
+        Err(ParserError::InvalidControlMode(9))
+    ));
+}
+
+#[test]
+fn test_default_limits_keep_legacy_levels() {
+    let mut monitor = Monitor::default();
+    let packet = TelemetryPacket {
+        timestamp: 5,
+        subsystem: Subsystem::Thermal,
+        payload: TelemetryPayload::Thermal(ThermalData {
+            temp_celsius: 85.5,
+            zones: Vec::new(),
+        }),
+        raw_counts: Default::default(),
+        derived: Default::default(),
+    };
+    let alert = monitor.analyze(&packet).remove(0);
+    assert_eq!(alert.level, AlertLevel::Warning);
+    assert_eq!(alert.rule, "Temperature.yellow_high");
+    assert_eq!(
+        alert.message,
+        "High Temperature: 85.50C (Yellow High Limit: 80.00C)"
+    );
+    assert_eq!(alert.timestamp, 5);
+}
+
+#[test]
+fn test_rules_from_toml_and_json() {
+    let toml = r#"
+        [[rules]]
+        name = "bus_overvoltage"
+        field = "power.voltage"
+        comparison = ">="
+        threshold = 32.0
+        level = "Critical"
+        message = "{name}: {field} = {value:.1}V"
+    "#;
+    let rules = RuleSet::from_toml_str(toml).unwrap();
+    assert_eq!(rules.rules[0].comparison, Comparison::GreaterOrEqual);
+
+    let json = serde_json::to_string(&rules).unwrap();
+    assert_eq!(RuleSet::from_json_str(&json).unwrap(), rules);
+
+    let mut monitor = Monitor::from(rules);
+    let packet = TelemetryPacket {
+        timestamp: 7,
+        subsystem: Subsystem::Power,
+        payload: TelemetryPayload::Power(PowerData {
+            voltage: 33.04,
+            current: 1.0,
+            battery_level: 10.0, // Not covered by the custom rules
+        }),
+        raw_counts: Default::default(),
+        derived: Default::default(),
+    };
+    let alert = monitor.analyze(&packet).remove(0);
+    assert_eq!(alert.level, AlertLevel::Critical);
+    assert_eq!(alert.message, "bus_overvoltage: power.voltage = 33.0V");
+}
+
+#[test]
+fn test_monitor_reports_all_alerts() {
+    let mut rules = RuleSet::default().rules;
+    rules.push(Rule::new(
+        "bus_undervoltage",
+        "power.voltage",
+        Comparison::LessThan,
+        24.0,
+        AlertLevel::Warning,
+        "Bus Undervoltage: {value:.2}V",
+    ));
+    let mut monitor = Monitor::new(rules, RuleSet::default().limits);
+
+    let packet = TelemetryPacket {
+        timestamp: 9,
+        subsystem: Subsystem::Power,
+        payload: TelemetryPayload::Power(PowerData {
+            voltage: 21.0,
+            current: 1.0,
+            battery_level: 12.0,
+        }),
+        raw_counts: Default::default(),
+        derived: Default::default(),
+    };
+    let alerts = monitor.analyze(&packet);
+    let rules: Vec<&str> = alerts.iter().map(|a| a.rule.as_str()).collect();
+    assert_eq!(
+        rules,
+        ["bus_undervoltage", "Bus Voltage.red_low", "Battery.red_low"]
+    );
+    assert_eq!(alerts[0].level, AlertLevel::Warning);
+    assert_eq!(alerts[1].level, AlertLevel::Critical);
+}
+
+#[test]
+fn test_limit_set_bands() {
+    let limits = LimitSet::new("Temperature", "thermal.temp_celsius", "C")
+        .low(Some(-20.0), Some(-10.0))
+        .high(Some(80.0), Some(95.0));
+
+    assert_eq!(limits.classify(25.0), None);
+    assert_eq!(limits.classify(-15.0), Some((LimitBand::YellowLow, -10.0)));
+    assert_eq!(limits.classify(-25.0), Some((LimitBand::RedLow, -20.0)));
+    assert_eq!(limits.classify(85.0), Some((LimitBand::YellowHigh, 80.0)));
+    assert_eq!(limits.classify(100.0), Some((LimitBand::RedHigh, 95.0)));
+
+    // Cold readings now alert through the default monitor
+    let packet = TelemetryPacket {
+        timestamp: 11,
+        subsystem: Subsystem::Thermal,
+        payload: TelemetryPayload::Thermal(ThermalData {
+            temp_celsius: -30.0,
+            zones: Vec::new(),
+        }),
+        raw_counts: Default::default(),
+        derived: Default::default(),
+    };
+    let alert = Monitor::default().analyze(&packet).remove(0);
+    assert_eq!(alert.level, AlertLevel::Critical);
+    assert_eq!(
+        alert.message,
+        "Low Temperature: -30.00C (Red Low Limit: -20.00C)"
+    );
+}
+
+#[test]
+fn test_limits_from_toml() {
+    let toml = r#"
+        [[limits]]
+        name = "Current"
+        field = "power.current"
+        unit = "A"
+        yellow_high = 3.0
+        red_high = 3.5
+    "#;
+    let mut monitor = Monitor::from(RuleSet::from_toml_str(toml).unwrap());
+    assert!(monitor.rules.is_empty());
+    assert_eq!(monitor.limits[0].red_low, None);
+
+    let packet = TelemetryPacket {
+        timestamp: 12,
+        subsystem: Subsystem::Power,
+        payload: TelemetryPayload::Power(PowerData {
+            voltage: 28.0,
+            current: 3.2,
+            battery_level: 80.0,
+        }),
+        raw_counts: Default::default(),
+        derived: Default::default(),
+    };
+    let alerts = monitor.analyze(&packet);
+    assert_eq!(alerts.len(), 1);
+    assert_eq!(alerts[0].rule, "Current.yellow_high");
+    assert_eq!(alerts[0].level, AlertLevel::Warning);
+}
+
+fn thermal_packet(timestamp: u64, temp: f64) -> TelemetryPacket {
+    TelemetryPacket {
+        timestamp,
+        subsystem: Subsystem::Thermal,
+        payload: TelemetryPayload::Thermal(ThermalData {
+            temp_celsius: temp,
+            zones: Vec::new(),
+        }),
+        raw_counts: Default::default(),
+        derived: Default::default(),
+    }
+}
+
+#[test]
+fn test_alert_manager_deduplicates() {
+    let mut monitor = Monitor::default();
+    let mut manager = AlertManager::new();
+
+    for (t, temp) in [(10, 85.0), (20, 85.5), (30, 86.0)] {
+        let packet = thermal_packet(t, temp);
+        manager.update(&packet, &monitor.analyze(&packet));
+    }
+
+    let open: Vec<_> = manager.open().collect();
+    assert_eq!(open.len(), 1);
+    assert_eq!(open[0].occurrences, 3);
+    assert_eq!(open[0].first_seen, 10);
+    assert_eq!(open[0].last_seen, 30);
+    assert_eq!(open[0].state, AlertState::Raised);
+}
+
+#[test]
+fn test_alert_manager_lifecycle() {
+    let mut monitor = Monitor::default();
+    let mut manager = AlertManager::new();
+
+    let hot = thermal_packet(10, 85.0);
+    manager.update(&hot, &monitor.analyze(&hot));
+    let id = manager.open().next().unwrap().id;
+
+    // Acknowledged while still breaching
+    assert!(manager.acknowledge(id));
+    manager.update(&hot, &monitor.analyze(&hot));
+    assert_eq!(manager.get(id).unwrap().state, AlertState::Acknowledged);
+
+    // Other subsystems do not affect it
+    let power = TelemetryPacket {
+        timestamp: 15,
+        subsystem: Subsystem::Power,
+        payload: TelemetryPayload::Power(PowerData {
+            voltage: 28.0,
+            current: 1.0,
+            battery_level: 80.0,
+        }),
+        raw_counts: Default::default(),
+        derived: Default::default(),
+    };
+    manager.update(&power, &monitor.analyze(&power));
+    assert_eq!(manager.get(id).unwrap().state, AlertState::Acknowledged);
+
+    // Acknowledged and nominal clears it
+    let nominal = thermal_packet(20, 25.0);
+    manager.update(&nominal, &monitor.analyze(&nominal));
+    assert_eq!(manager.get(id).unwrap().state, AlertState::Cleared);
+    assert_eq!(manager.open().count(), 0);
+
+    // A new breach opens a new alert; returning to nominal unacknowledged
+    // keeps it open until acknowledged
+    manager.update(&hot, &monitor.analyze(&hot));
+    let second = manager.open().next().unwrap().id;
+    assert_ne!(second, id);
+    manager.update(&nominal, &monitor.analyze(&nominal));
+    assert_eq!(
+        manager.get(second).unwrap().state,
+        AlertState::ReturnedToNominal
+    );
+    assert!(manager.acknowledge(second));
+    assert_eq!(manager.get(second).unwrap().state, AlertState::Cleared);
+    assert!(!manager.acknowledge(second));
+}
+
+#[test]
+fn test_alert_manager_prunes_cleared_history() {
+    let mut monitor = Monitor::default();
+    let mut manager = AlertManager::new();
+
+    // A flapping alert that is acknowledged every time it trips
+    for i in 0..(MAX_CLEARED as u64 + 50) {
+        let hot = thermal_packet(2 * i, 85.0);
+        manager.update(&hot, &monitor.analyze(&hot));
+        let id = manager.open().next().unwrap().id;
+        assert!(manager.acknowledge(id));
+        let nominal = thermal_packet(2 * i + 1, 25.0);
+        manager.update(&nominal, &monitor.analyze(&nominal));
+    }
+    assert_eq!(manager.open().count(), 0);
+    assert_eq!(manager.all().len(), MAX_CLEARED);
+    // The oldest were dropped
+    assert_eq!(manager.all()[0].first_seen, 100);
+}
+
+fn alert_count(monitor: &mut Monitor, temps: &[(u64, f64)]) -> Vec<usize> {
+    temps
+        .iter()
+        .map(|&(t, temp)| monitor.analyze(&thermal_packet(t, temp)).len())
+        .collect()
+}
+
+#[test]
+fn test_rule_hysteresis() {
+    let rule = Rule::new(
+        "hot",
+        "thermal.temp_celsius",
+        Comparison::GreaterThan,
+        80.0,
+        AlertLevel::Warning,
+        "Hot: {value:.1}C",
+    )
+    .clear_threshold(75.0);
+    let mut monitor = Monitor::new(vec![rule], vec![]);
+
+    // Trips at 81, holds above 75, clears at 74, needs 80 again to re-trip
+    let temps = [
+        (1, 79.0),
+        (2, 81.0),
+        (3, 79.0),
+        (4, 76.0),
+        (5, 74.0),
+        (6, 79.0),
+    ];
+    assert_eq!(alert_count(&mut monitor, &temps), [0, 1, 1, 1, 0, 0]);
+}
+
+#[test]
+fn test_limit_hysteresis() {
+    let limits = LimitSet::new("Temperature", "thermal.temp_celsius", "C")
+        .high(Some(80.0), Some(95.0))
+        .hysteresis(2.0);
+    let mut monitor = Monitor::new(vec![], vec![limits]);
+
+    let bands: Vec<_> = [81.0, 79.0, 96.0, 94.0, 90.0, 77.0]
+        .iter()
+        .enumerate()
+        .map(|(t, &temp)| {
+            monitor
+                .analyze(&thermal_packet(t as u64, temp))
+                .first()
+                .map(|a| a.level)
+        })
+        .collect();
+    assert_eq!(
+        bands,
+        [
+            Some(AlertLevel::Warning),
+            Some(AlertLevel::Warning),
+            Some(AlertLevel::Critical),
+            Some(AlertLevel::Critical),
+            Some(AlertLevel::Warning),
+            None
+        ]
+    );
+
+    // Limit sets on the same field keep separate persistence state
+    let immediate = LimitSet::new("Hot", "thermal.temp_celsius", "C").high(Some(80.0), None);
+    let persistent = LimitSet::new("Hot for a While", "thermal.temp_celsius", "C")
+        .high(Some(80.0), None)
+        .persistence(Persistence::samples(2));
+    let mut monitor = Monitor::new(vec![], vec![immediate, persistent]);
+    assert_eq!(monitor.analyze(&thermal_packet(0, 85.0)).len(), 1);
+    let alerts = monitor.analyze(&thermal_packet(1, 85.0));
+    let rules: Vec<&str> = alerts.iter().map(|a| a.rule.as_str()).collect();
+    assert_eq!(rules, ["Hot.yellow_high", "Hot for a While.yellow_high"]);
+
+    // ... and stay separate alert records
+    let mut manager = AlertManager::new();
+    manager.update(&thermal_packet(1, 85.0), &alerts);
+    assert_eq!(manager.open().count(), 2);
+}
+
+#[test]
+fn test_persistence_samples_and_seconds() {
+    let rule = Rule::new(
+        "hot",
+        "thermal.temp_celsius",
+        Comparison::GreaterThan,
+        80.0,
+        AlertLevel::Warning,
+        "Hot",
+    )
+    .persistence(Persistence::samples(3));
+    let mut monitor = Monitor::new(vec![rule], vec![]);
+
+    // A nominal sample restarts the count
+    let temps = [
+        (1, 81.0),
+        (2, 81.0),
+        (3, 70.0),
+        (4, 81.0),
+        (5, 81.0),
+        (6, 81.0),
+    ];
+    assert_eq!(alert_count(&mut monitor, &temps), [0, 0, 0, 0, 0, 1]);
+
+    let limits = LimitSet::new("Temperature", "thermal.temp_celsius", "C")
+        .high(Some(80.0), None)
+        .persistence(Persistence::seconds(20));
+    let mut monitor = Monitor::new(vec![], vec![limits]);
+    let temps = [(100, 81.0), (110, 82.0), (120, 81.0), (130, 70.0)];
+    assert_eq!(alert_count(&mut monitor, &temps), [0, 0, 1, 0]);
+}
+
+fn battery_packet(timestamp: u64, battery_level: f64) -> TelemetryPacket {
+    TelemetryPacket {
+        timestamp,
+        subsystem: Subsystem::Power,
+        payload: TelemetryPayload::Power(PowerData {
+            voltage: 28.0,
+            current: 1.0,
+            battery_level,
+        }),
+        raw_counts: Default::default(),
+        derived: Default::default(),
+    }
+}
+
+#[test]
+fn test_trend_detects_battery_drain() {
+    let mut monitor = Monitor::default();
+
+    // 5%/min from 90%, far above the 20% hard limit
+    let mut rules = Vec::new();
+    for i in 0..4 {
+        let packet = battery_packet(1000 + i * 12, 90.0 - i as f64);
+        rules = monitor
+            .analyze(&packet)
+            .into_iter()
+            .map(|a| a.rule)
+            .collect();
+    }
+    assert_eq!(rules, ["Battery.fall"]);
+
+    // Steady discharge close to the limit triggers only the prediction
+    monitor.reset();
+    let mut alerts = Vec::new();
+    for i in 0..3 {
+        alerts = monitor.analyze(&battery_packet(2000 + i * 60, 36.0 - 1.9 * i as f64));
+    }
+    assert_eq!(alerts.len(), 1);
+    assert_eq!(alerts[0].rule, "Battery.prediction");
+    assert_eq!(alerts[0].level, AlertLevel::Warning);
+    assert_eq!(
+        alerts[0].message,
+        "Battery Predicted to Reach 20.00% in 385 s"
+    );
+
+    // Recovering from past the threshold is not predicted
+    monitor.reset();
+    let mut rules = Vec::new();
+    for i in 0..3 {
+        let packet = battery_packet(3000 + i * 60, 15.0 + i as f64);
+        rules = monitor
+            .analyze(&packet)
+            .into_iter()
+            .map(|a| a.rule)
+            .collect();
+    }
+    assert_eq!(rules, ["Battery.red_low"]);
+    for i in 0..3 {
+        let alerts = monitor.analyze(&thermal_packet(4000 + i * 60, 90.0 - i as f64));
+        assert!(alerts.iter().all(|a| !a.rule.ends_with(".prediction")));
+    }
+}
+
+#[test]
+fn test_trends_on_one_field_keep_separate_history() {
+    let fast = TrendCheck::new("Fast Heating", "thermal.temp_celsius", "C", 60).max_rise(5.0);
+    let slow = TrendCheck::new("Slow Heating", "thermal.temp_celsius", "C", 600).max_rise(2.0);
+    let mut monitor = Monitor::new(vec![], vec![]);
+    monitor.trends = vec![fast, slow];
+
+    monitor.analyze(&thermal_packet(0, 20.0));
+    let alerts = monitor.analyze(&thermal_packet(60, 26.0));
+    let rules: Vec<&str> = alerts.iter().map(|a| a.rule.as_str()).collect();
+    assert_eq!(rules, ["Fast Heating.rise", "Slow Heating.rise"]);
+
+    // The short window has levelled off; the long one still sees 3 C/min
+    let alerts = monitor.analyze(&thermal_packet(120, 26.0));
+    let messages: Vec<&str> = alerts.iter().map(|a| a.message.as_str()).collect();
+    assert_eq!(
+        messages,
+        ["Slow Heating Rising: 3.00 C/min (Limit: 2.00 C/min)"]
+    );
+}
+
+#[test]
+fn test_trend_window_uses_packet_timestamps() {
+    let trend = TrendCheck::new("Temperature", "thermal.temp_celsius", "C", 60).max_rise(5.0);
+    let mut monitor = Monitor::new(vec![], vec![]);
+    monitor.trends.push(trend);
+
+    // A jump that has aged out of the window no longer counts
+    assert!(monitor.analyze(&thermal_packet(0, 20.0)).is_empty());
+    assert_eq!(monitor.analyze(&thermal_packet(30, 30.0)).len(), 1);
+    assert!(monitor.analyze(&thermal_packet(100, 30.5)).is_empty());
+}
+
+#[test]
+fn test_stats_per_subsystem() {
+    let mut framer = PacketFramer::new();
+    framer.push(&simulation::generate_simulated_packets(CrcMode::Required).concat());
+
+    let calibration = CalibrationSet::default();
+    let mut stats = TelemetryStats::new();
+    for result in framer {
+        let mut packet = result.unwrap().0;
+        calibration.apply(&mut packet);
+        stats.add(&packet);
+    }
+    stats.add_error();
+
+    let power = stats.get(&Subsystem::Power).unwrap();
+    assert_eq!(power.packets, 2);
+    let (name, battery) = &power.fields[2];
+    assert_eq!(name, "power.battery_level");
+    assert_eq!(
+        (battery.min, battery.max, battery.mean()),
+        (15.0, 90.0, 52.5)
+    );
+
+    assert_eq!(stats.subsystems.len(), 4);
+    assert_eq!(stats.errors, 1);
+}
+
+/// Next non-status event from a live source, failing after two seconds.
+fn next_data_event(events: &std::sync::mpsc::Receiver<SourceEvent>) -> SourceEvent {
+    loop {
+        match events
+            .recv_timeout(std::time::Duration::from_secs(2))
+            .unwrap()
+        {
+            SourceEvent::Status(_) => continue,
+            event => return event,
+        }
+    }
+}
+
+/// Waits for a status event matching `pred`, failing after two seconds.
+fn wait_for_status(
+    events: &std::sync::mpsc::Receiver<SourceEvent>,
+    pred: impl Fn(&SourceStatus) -> bool,
+) -> SourceStatus {
+    loop {
+        if let SourceEvent::Status(status) = events
+            .recv_timeout(std::time::Duration::from_secs(2))
+            .unwrap()
+        {
+            if pred(&status) {
+                return status;
+            }
+        }
+    }
+}
+
+#[test]
+fn test_udp_source_receives_datagrams() {
+    use std::sync::mpsc;
+
+    let mut source = UdpSource::new("127.0.0.1:0".parse().unwrap());
+    let (sender, events) = mpsc::channel();
+    source.start(sender).unwrap();
+    assert!(source.is_running());
+
+    let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    let target = source.local_addr().unwrap();
+    socket.send_to(&thermal_bytes(42, 21.5), target).unwrap();
+    socket.send_to(&[0u8; 3], target).unwrap();
+
+    assert_eq!(
+        next_data_event(&events),
+        SourceEvent::Packet(thermal_packet(42, 21.5))
+    );
+    assert!(matches!(next_data_event(&events), SourceEvent::Error(_)));
+
+    // A burst is reported once it pauses, not datagram by datagram
+    let frame = thermal_bytes(43, 21.5);
+    for _ in 0..20 {
+        socket.send_to(&frame, target).unwrap();
+    }
+    let total = 20 * frame.len() as u64 + thermal_bytes(42, 21.5).len() as u64 + 3;
+    let mut statuses = 0;
+    loop {
+        match events
+            .recv_timeout(std::time::Duration::from_secs(2))
+            .unwrap()
+        {
+            SourceEvent::Status(status) if status.bytes_received == total => break,
+            SourceEvent::Status(_) => statuses += 1,
+            _ => {}
+        }
+    }
+    assert!(statuses <= 2, "{} status updates", statuses);
+
+    source.stop();
+    assert!(!source.is_running());
+}
+
+#[test]
+fn test_backoff_doubles_up_to_max() {
+    use std::time::Duration;
+
+    let backoff = Backoff {
+        initial: Duration::from_millis(500),
+        max: Duration::from_secs(3),
+    };
+    assert_eq!(backoff.delay(1), Duration::from_millis(500));
+    assert_eq!(backoff.delay(2), Duration::from_secs(1));
+    assert_eq!(backoff.delay(3), Duration::from_secs(2));
+    assert_eq!(backoff.delay(4), Duration::from_secs(3));
+    assert_eq!(backoff.delay(40), Duration::from_secs(3));
+}
+
+#[test]
+fn test_tcp_server_source_reassembles_stream() {
+    use std::io::Write;
+    use std::sync::mpsc;
+
+    let mut source = TcpServerSource::new("127.0.0.1:0".parse().unwrap());
+    let (sender, events) = mpsc::channel();
+    source.start(sender).unwrap();
+
+    let mut stream = std::net::TcpStream::connect(source.local_addr().unwrap()).unwrap();
+    let mut bytes = thermal_bytes(1, 20.0);
+    bytes.extend(thermal_bytes(2, 21.0));
+    let (first, second) = bytes.split_at(5);
+    stream.write_all(first).unwrap();
+    stream.flush().unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(50));
+    stream.write_all(second).unwrap();
+
+    assert_eq!(
+        next_data_event(&events),
+        SourceEvent::Packet(thermal_packet(1, 20.0))
+    );
+    assert_eq!(
+        next_data_event(&events),
+        SourceEvent::Packet(thermal_packet(2, 21.0))
+    );
+    let status = wait_for_status(&events, |s| s.bytes_received == bytes.len() as u64);
+    assert!(matches!(status.state, ConnectionState::Connected(_)));
+
+    drop(stream);
+    wait_for_status(&events, |s| s.state == ConnectionState::Listening);
+
+    source.stop();
+    assert!(!source.is_running());
+    wait_for_status(&events, |s| s.state == ConnectionState::Stopped);
+}
+
+#[test]
+fn test_tcp_client_source_reconnects() {
+    use std::io::Write;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let backoff = Backoff {
+        initial: Duration::from_millis(20),
+        max: Duration::from_millis(100),
     };
-    assert!(monitor.analyze(&packet_good).is_none());
+    let mut source = TcpClientSource::new(listener.local_addr().unwrap(), backoff);
+    let (sender, events) = mpsc::channel();
+    source.start(sender).unwrap();
+
+    let (mut stream, _) = listener.accept().unwrap();
+    stream.write_all(&thermal_bytes(1, 20.0)).unwrap();
+    assert_eq!(
+        next_data_event(&events),
+        SourceEvent::Packet(thermal_packet(1, 20.0))
+    );
+    drop(stream);
+
+    let status = wait_for_status(&events, |s| {
+        matches!(s.state, ConnectionState::Retrying { .. })
+    });
+    assert_eq!(
+        status.state,
+        ConnectionState::Retrying {
+            attempt: 1,
+            delay: Duration::from_millis(20)
+        }
+    );
+
+    let (mut stream, _) = listener.accept().unwrap();
+    stream.write_all(&thermal_bytes(2, 21.0)).unwrap();
+    assert_eq!(
+        next_data_event(&events),
+        SourceEvent::Packet(thermal_packet(2, 21.0))
+    );
+    let status = wait_for_status(&events, |s| {
+        matches!(s.state, ConnectionState::Connected(_))
+    });
+    assert_eq!(
+        status.bytes_received,
+        2 * thermal_bytes(0, 0.0).len() as u64
+    );
+
+    source.stop();
+    assert!(!source.is_running());
+}
+
+/// Applies pipeline events until `done` holds, failing after two seconds.
+fn run_pipeline_until(
+    pipeline: &Pipeline,
+    snapshot: &mut PipelineSnapshot,
+    done: impl Fn(&PipelineSnapshot) -> bool,
+) {
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(2);
+    while !done(snapshot) {
+        assert!(std::time::Instant::now() < deadline, "pipeline timed out");
+        if let Some(event) = pipeline.recv_timeout(std::time::Duration::from_millis(100)) {
+            snapshot.apply(event);
+        }
+    }
+}
+
+#[test]
+fn test_pipeline_replays_into_monitor_and_sinks() {
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    let stats = Arc::new(Mutex::new(TelemetryStats::new()));
+    let pipeline = Pipeline::spawn(Monitor::default(), vec![Box::new(Arc::clone(&stats))]);
+    pipeline.send(PipelineCommand::Replay {
+        frames: vec![
+            thermal_bytes(1, 20.0),
+            vec![0u8; 3],
+            thermal_bytes(1000, 85.5),
+        ],
+        interval: Duration::ZERO,
+    });
+
+    let mut snapshot = PipelineSnapshot::default();
+    run_pipeline_until(&pipeline, &mut snapshot, |s| {
+        s.replay_total == 3 && s.replay_sent == 3 && s.alerts.len() == 2
+    });
+    assert_eq!(snapshot.last_packet, Some(thermal_packet(1000, 85.5)));
+    assert_eq!(snapshot.alerts[0].rule, "Temperature.yellow_high");
+    assert_eq!(snapshot.alerts[1].rule, "thermal.gap");
+    assert!(snapshot
+        .logs
+        .iter()
+        .any(|l| l.starts_with("Error parsing packet")));
+    assert_eq!(
+        stats
+            .lock()
+            .unwrap()
+            .get(&Subsystem::Thermal)
+            .unwrap()
+            .packets,
+        2
+    );
+
+    let id = snapshot.alerts[0].id;
+    pipeline.send(PipelineCommand::Acknowledge(id));
+    run_pipeline_until(&pipeline, &mut snapshot, |s| {
+        s.alerts[0].state == AlertState::Acknowledged
+    });
+
+    pipeline.send(PipelineCommand::Restart);
+    run_pipeline_until(&pipeline, &mut snapshot, |s| {
+        s.alerts.is_empty() && s.replay_sent == 3
+    });
+}
+
+#[test]
+fn test_pipeline_pause_and_inject() {
+    use std::time::Duration;
+
+    let pipeline = Pipeline::spawn(Monitor::default(), Vec::new());
+    pipeline.send(PipelineCommand::Pause);
+    pipeline.send(PipelineCommand::Replay {
+        frames: vec![thermal_bytes(1, 20.0)],
+        interval: Duration::ZERO,
+    });
+
+    let mut snapshot = PipelineSnapshot::default();
+    run_pipeline_until(&pipeline, &mut snapshot, |s| {
+        s.paused && s.replay_total == 1
+    });
+
+    pipeline.send(PipelineCommand::Inject(thermal_bytes(5, 30.0)));
+    run_pipeline_until(&pipeline, &mut snapshot, |s| s.last_packet.is_some());
+    assert_eq!(snapshot.last_packet, Some(thermal_packet(5, 30.0)));
+    assert_eq!(snapshot.replay_sent, 0);
+
+    pipeline.send(PipelineCommand::Resume);
+    run_pipeline_until(&pipeline, &mut snapshot, |s| s.replay_sent == 1);
+    assert_eq!(snapshot.last_packet, Some(thermal_packet(1, 20.0)));
+}
+
+#[test]
+fn test_pipeline_uses_configured_crc_and_restart_resets_corrupted() {
+    use std::time::Duration;
+
+    let pipeline = Pipeline::spawn(Monitor::default(), Vec::new());
+    pipeline.send(PipelineCommand::SetCrc(CrcMode::Required));
+    pipeline.send(PipelineCommand::Inject(thermal_bytes(5, 30.0)));
+
+    let mut snapshot = PipelineSnapshot::default();
+    run_pipeline_until(&pipeline, &mut snapshot, |s| s.corrupted == 1);
+    assert_eq!(snapshot.last_packet, None);
+
+    pipeline.send(PipelineCommand::Replay {
+        frames: simulation::generate_simulated_packets(CrcMode::Required),
+        interval: Duration::ZERO,
+    });
+    run_pipeline_until(&pipeline, &mut snapshot, |s| {
+        s.replay_total > 0 && s.replay_sent == s.replay_total
+    });
+    assert_eq!(snapshot.corrupted, 1);
+
+    pipeline.send(PipelineCommand::Restart);
+    run_pipeline_until(&pipeline, &mut snapshot, |s| s.corrupted == 0);
+}
+
+#[test]
+fn test_pipeline_event_queue_is_bounded() {
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+
+    let stats = Arc::new(Mutex::new(TelemetryStats::new()));
+    let pipeline = Pipeline::spawn(Monitor::default(), vec![Box::new(Arc::clone(&stats))]);
+    let frames = 2 * EVENT_CAPACITY;
+    for i in 0..frames {
+        pipeline.send(PipelineCommand::Inject(thermal_bytes(i as u64, 20.0)));
+    }
+    let processed = || {
+        stats
+            .lock()
+            .unwrap()
+            .get(&Subsystem::Thermal)
+            .map_or(0, |s| s.packets)
+    };
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while processed() < frames {
+        assert!(
+            Instant::now() < deadline,
+            "pipeline did not process all frames"
+        );
+        std::thread::sleep(Duration::from_millis(10));
+    }
+
+    let queued = pipeline.try_events().count();
+    assert!(queued <= EVENT_CAPACITY, "{} events queued", queued);
+
+    let mut snapshot = PipelineSnapshot::default();
+    pipeline.send(PipelineCommand::Inject(thermal_bytes(frames as u64, 20.0)));
+    run_pipeline_until(&pipeline, &mut snapshot, |s| {
+        s.logs.iter().any(|l| l.starts_with("Dropped "))
+    });
+}
+
+/// Fresh directory under the system temp dir for one test.
+fn temp_archive_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("astro_monitor_{}_{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+#[test]
+fn test_archive_queries_by_subsystem_and_range() {
+    let dir = temp_archive_dir("archive_query");
+    let mut archive = Archive::open(&dir).unwrap().segment_records(2);
+    for packet in [
+        thermal_packet(100, 20.0),
+        TelemetryPacket {
+            timestamp: 110,
+            subsystem: Subsystem::Power,
+            payload: TelemetryPayload::Power(PowerData {
+                voltage: 28.0,
+                current: 2.5,
+                battery_level: 15.0,
+            }),
+            raw_counts: Default::default(),
+            derived: Default::default(),
+        },
+        thermal_packet(120, 85.5),
+        thermal_packet(5000, 21.0),
+    ] {
+        let alerts = Monitor::default().analyze(&packet);
+        archive.process(&packet, &alerts);
+    }
+    assert_eq!(archive.segments().len(), 3);
+
+    let packets = archive
+        .packets(
+            &ArchiveQuery::new()
+                .subsystem(Subsystem::Thermal)
+                .range(0, 1000),
+        )
+        .unwrap();
+    assert_eq!(
+        packets,
+        vec![thermal_packet(100, 20.0), thermal_packet(120, 85.5)]
+    );
+
+    let alerts = archive
+        .alerts(&ArchiveQuery::new().range(110, 120))
+        .unwrap();
+    let rules: Vec<&str> = alerts.iter().map(|a| a.alert.rule.as_str()).collect();
+    assert_eq!(rules, vec!["Battery.red_low", "Temperature.yellow_high"]);
+    assert_eq!(alerts[0].subsystem, Subsystem::Power);
+
+    assert_eq!(
+        archive.passes().unwrap(),
+        vec![
+            Pass {
+                start: 100,
+                end: 120,
+                packets: 3
+            },
+            Pass {
+                start: 5000,
+                end: 5000,
+                packets: 1
+            },
+        ]
+    );
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_archive_survives_reopen_and_torn_line() {
+    let dir = temp_archive_dir("archive_reopen");
+    {
+        let mut archive = Archive::open(&dir).unwrap();
+        archive.append_packet(&thermal_packet(1, 20.0)).unwrap();
+    }
+
+    // Simulate a crash halfway through writing a record.
+    let segment = dir.join("segment-000001.jsonl");
+    let mut file = std::fs::OpenOptions::new()
+        .append(true)
+        .open(&segment)
+        .unwrap();
+    std::io::Write::write_all(&mut file, b"{\"Packet\":{\"timestamp\":2").unwrap();
+    drop(file);
+
+    let mut archive = Archive::open(&dir).unwrap();
+    archive.append_packet(&thermal_packet(3, 22.0)).unwrap();
+    assert_eq!(
+        archive.packets(&ArchiveQuery::new()).unwrap(),
+        vec![thermal_packet(1, 20.0), thermal_packet(3, 22.0)]
+    );
+    assert_eq!(archive.segments()[0].records, 2);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_archive_never_reuses_a_segment_file() {
+    let dir = temp_archive_dir("archive_numbering");
+    {
+        let mut archive = Archive::open(&dir).unwrap().segment_records(1);
+        archive.append_packet(&thermal_packet(1, 20.0)).unwrap();
+        archive.append_packet(&thermal_packet(2, 21.0)).unwrap();
+    }
+    std::fs::remove_file(dir.join("segment-000001.jsonl")).unwrap();
+
+    let mut archive = Archive::open(&dir).unwrap().segment_records(1);
+    archive.append_packet(&thermal_packet(3, 22.0)).unwrap();
+    let files: Vec<&str> = archive.segments().iter().map(|s| s.file.as_str()).collect();
+    assert_eq!(files, ["segment-000002.jsonl", "segment-000003.jsonl"]);
+    assert_eq!(
+        archive.packets(&ArchiveQuery::new()).unwrap(),
+        vec![thermal_packet(2, 21.0), thermal_packet(3, 22.0)]
+    );
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_archive_keeps_packets_with_non_finite_values() {
+    let dir = temp_archive_dir("archive_non_finite");
+    let mut archive = Archive::open(&dir).unwrap();
+    archive.append_packet(&thermal_packet(1, 20.0)).unwrap();
+    let mut packet = battery_packet(2, f64::INFINITY);
+    packet
+        .derived
+        .insert("power.soc_error".to_string(), f64::NAN);
+    archive.append_packet(&packet).unwrap();
+
+    // Appends are on disk without a flush, and a reader sees them
+    let reader = archive.reader();
+    let lines = std::fs::read_to_string(dir.join("segment-000001.jsonl")).unwrap();
+    assert_eq!(lines.lines().count(), 2);
+    archive.append_packet(&thermal_packet(3, 22.0)).unwrap();
+    drop(archive);
+
+    let packets = reader.packets(&ArchiveQuery::new()).unwrap();
+    assert_eq!(packets.len(), 3);
+    assert!(packets[1].field("power.battery_level").unwrap().is_nan());
+    assert!(packets[1].field("power.soc_error").unwrap().is_nan());
+    assert_eq!(packets[1].field("power.voltage"), Some(28.0));
+    assert_eq!(packets[2], thermal_packet(3, 22.0));
+
+    // A line that is not a record is skipped
+    let mut file = std::fs::OpenOptions::new()
+        .append(true)
+        .open(dir.join("segment-000001.jsonl"))
+        .unwrap();
+    std::io::Write::write_all(&mut file, b"{\"Packet\":{}}\n").unwrap();
+    drop(file);
+    let archive = Archive::open(&dir).unwrap();
+    assert_eq!(archive.packets(&ArchiveQuery::new()).unwrap().len(), 3);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_archive_passes_come_from_the_index() {
+    let dir = temp_archive_dir("archive_passes");
+    {
+        let mut archive = Archive::open(&dir).unwrap().segment_records(2);
+        for timestamp in [100, 5000, 300, 5100, 2000] {
+            archive
+                .append_packet(&thermal_packet(timestamp, 20.0))
+                .unwrap();
+        }
+        let passes: Vec<(u64, u64, usize)> = archive
+            .passes()
+            .unwrap()
+            .iter()
+            .map(|p| (p.start, p.end, p.packets))
+            .collect();
+        assert_eq!(passes, [(100, 300, 2), (2000, 2000, 1), (5000, 5100, 2)]);
+    }
+
+    // A different gap re-splits once and is saved in the index
+    let mut archive = Archive::open(&dir).unwrap().pass_gap_seconds(3000);
+    let passes: Vec<(u64, u64, usize)> = archive
+        .passes()
+        .unwrap()
+        .iter()
+        .map(|p| (p.start, p.end, p.packets))
+        .collect();
+    assert_eq!(passes, [(100, 5100, 5)]);
+    drop(archive);
+    let index = std::fs::read_to_string(dir.join("index.json")).unwrap();
+    let segments: Vec<Segment> = serde_json::from_str(&index).unwrap();
+    assert!(segments.iter().all(|s| s.pass_gap_seconds == Some(3000)));
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_monitor_thresholds_for_field() {
+    let monitor = Monitor::default();
+    let values: Vec<(f64, AlertLevel)> = monitor
+        .thresholds("thermal.temp_celsius")
+        .iter()
+        .map(|t| (t.value, t.level))
+        .collect();
+    assert_eq!(
+        values,
+        vec![
+            (-20.0, AlertLevel::Critical),
+            (-10.0, AlertLevel::Warning),
+            (80.0, AlertLevel::Warning),
+            (95.0, AlertLevel::Critical),
+        ]
+    );
+
+    let confidence = monitor.thresholds("star_tracker.confidence");
+    assert_eq!(confidence.len(), 1);
+    assert_eq!(confidence[0].label, "low_star_confidence");
+    assert_eq!(confidence[0].value, 0.8);
+}
+
+#[test]
+fn test_history_records_samples_and_alert_markers() {
+    let mut monitor = Monitor::default();
+    let mut history = TelemetryHistory::new();
+    for (timestamp, temp) in [(100, 20.0), (1000, 85.5)] {
+        let packet = thermal_packet(timestamp, temp);
+        let alerts = monitor.analyze(&packet);
+        history.add(&packet, &alerts);
+    }
+
+    let field = "thermal.temp_celsius";
+    assert_eq!(
+        history.samples(field).collect::<Vec<_>>(),
+        vec![(100, 20.0), (1000, 85.5)]
+    );
+    assert_eq!(
+        (history.earliest(field), history.latest(field)),
+        (Some(100), Some(1000))
+    );
+    assert_eq!(
+        history.markers(field).collect::<Vec<_>>(),
+        vec![AlertMarker {
+            timestamp: 1000,
+            level: AlertLevel::Warning
+        }]
+    );
+    assert_eq!(history.samples("power.voltage").count(), 0);
+
+    for timestamp in 0..history::MAX_SAMPLES as u64 {
+        history.add(&thermal_packet(2000 + timestamp, 20.0), &[]);
+    }
+    assert_eq!(history.samples(field).count(), history::MAX_SAMPLES);
+    assert_eq!(history.earliest(field), Some(2000));
+}
+
+#[test]
+fn test_plot_view_range() {
+    let mut view = PlotView::default();
+    assert_eq!(view.x_range(0, 1000), (700.0, 1000.0));
+
+    view.window = None;
+    assert_eq!(view.x_range(200, 1000), (200.0, 1000.0));
+    assert_eq!(view.x_range(1000, 1000), (999.0, 1000.0));
+    // Out of order bounds do not underflow
+    assert_eq!(view.x_range(1000, 200), (199.0, 200.0));
+
+    view.follow = false;
+    view.reset();
+    assert!(view.follow);
+}
+
+#[test]
+fn test_history_range_ignores_arrival_order() {
+    let mut history = TelemetryHistory::new();
+    for timestamp in [500, 1000, 200] {
+        history.add(&thermal_packet(timestamp, 20.0), &[]);
+    }
+    let field = "thermal.temp_celsius";
+    assert_eq!(history.earliest(field), Some(200));
+    assert_eq!(history.latest(field), Some(1000));
+}
+
+#[test]
+fn test_sky_projections() {
+    let close = |(x, y): (f64, f64), (ex, ey): (f64, f64)| {
+        assert!(
+            (x - ex).abs() < 1e-9 && (y - ey).abs() < 1e-9,
+            "({}, {})",
+            x,
+            y
+        );
+    };
+    for projection in [Projection::Equirectangular, Projection::Aitoff] {
+        close(projection.project(180.0, 0.0), (0.0, 0.0));
+        close(projection.project(0.0, 0.0), (1.0, 0.0));
+        close(projection.project(180.0, 90.0), (0.0, 1.0));
+        close(projection.project(180.0, -90.0), (0.0, -1.0));
+        // RA increases to the left
+        assert!(projection.project(200.0, 10.0).0 < projection.project(190.0, 10.0).0);
+    }
+    close(Projection::Equirectangular.project(90.0, 45.0), (0.5, 0.5));
+
+    // The Aitoff ellipse never leaves the unit square
+    for star in &BRIGHT_STARS {
+        let (x, y) = Projection::Aitoff.project(star.right_ascension, star.declination);
+        assert!(x.abs() <= 1.0 && y.abs() <= 1.0, "{}", star.name);
+        assert!((0.0..360.0).contains(&star.right_ascension));
+        assert!((-90.0..=90.0).contains(&star.declination));
+    }
+}
+
+#[test]
+fn test_history_keeps_pointing_track() {
+    let mut history = TelemetryHistory::new();
+    let reading = StarTrackerReading {
+        target_id: Some("Vega".to_string()),
+        coordinates: CelestialCoordinates {
+            right_ascension: 279.235,
+            declination: 38.784,
+        },
+        confidence: 0.95,
+    };
+    history.add(&thermal_packet(1, 20.0), &[]);
+    history.add(
+        &TelemetryPacket {
+            timestamp: 2,
+            subsystem: Subsystem::StarTracker,
+            payload: TelemetryPayload::StarTracker(reading.clone()),
+            raw_counts: Default::default(),
+            derived: Default::default(),
+        },
+        &[],
+    );
+
+    assert_eq!(history.pointing().collect::<Vec<_>>(), vec![&(2, reading)]);
+    history.clear();
+    assert_eq!(history.pointing().count(), 0);
+}
+
+#[test]
+fn test_status_board_health() {
+    use std::time::{Duration, Instant};
+
+    let mut monitor = Monitor::default();
+    monitor.watchdog.periods.clear();
+    let mut manager = AlertManager::new();
+    let mut board = StatusBoard::new();
+    let start = Instant::now();
+    let health = |board: &StatusBoard, manager: &AlertManager, subsystem, at| {
+        let alerts: Vec<AlertRecord> = manager.all().to_vec();
+        board.health(&subsystem, &alerts, at)
+    };
+
+    assert_eq!(
+        health(&board, &manager, Subsystem::Thermal, start),
+        Health::NoData
+    );
+
+    for (timestamp, temp, expected) in [
+        (100, 20.0, Health::Nominal),
+        (1000, 85.5, Health::Warning),
+        (2000, 96.0, Health::Critical),
+        (3000, 20.0, Health::Nominal),
+    ] {
+        let packet = thermal_packet(timestamp, temp);
+        let alerts = monitor.analyze(&packet);
+        manager.update(&packet, &alerts);
+        board.update(&packet, start);
+        assert_eq!(
+            health(&board, &manager, Subsystem::Thermal, start),
+            expected
+        );
+    }
+    assert_eq!(
+        board.get(&Subsystem::Thermal).unwrap().latest,
+        Some(thermal_packet(3000, 20.0))
+    );
+    assert_eq!(
+        health(&board, &manager, Subsystem::Power, start),
+        Health::NoData
+    );
+
+    let later = start + DEFAULT_STALE_AFTER + Duration::from_secs(1);
+    assert_eq!(
+        health(&board, &manager, Subsystem::Thermal, later),
+        Health::Stale
+    );
+}
+
+#[test]
+fn test_watchdog_detects_gaps_and_time_reversal() {
+    use std::time::Instant;
+
+    let toml = r#"
+        [[watchdog]]
+        subsystem = "Thermal"
+        timeout_seconds = 60
+        max_gap_seconds = 300
+    "#;
+    let periods = RuleSet::from_toml_str(toml).unwrap().watchdog;
+    assert_eq!(
+        periods,
+        vec![ReportingPeriod::new(Subsystem::Thermal, 60).max_gap(300)]
+    );
+    let mut watchdog = Watchdog::new(periods);
+    let now = Instant::now();
+
+    assert!(watchdog.observe(&thermal_packet(100, 20.0), now).is_empty());
+    assert!(watchdog.observe(&thermal_packet(400, 20.0), now).is_empty());
+
+    let alerts = watchdog.observe(&thermal_packet(1000, 20.0), now);
+    assert_eq!(alerts.len(), 1);
+    assert_eq!(alerts[0].rule, "thermal.gap");
+    assert_eq!(alerts[0].field, "thermal");
+    assert_eq!(alerts[0].level, AlertLevel::Warning);
+
+    let alerts = watchdog.observe(&thermal_packet(900, 20.0), now);
+    assert_eq!(alerts.len(), 1);
+    assert_eq!(alerts[0].rule, "thermal.time_reversal");
+    assert_eq!(alerts[0].timestamp, 900);
+
+    watchdog.reset();
+    assert!(watchdog.observe(&thermal_packet(100, 20.0), now).is_empty());
+}
+
+#[test]
+fn test_watchdog_raises_stale_once_per_silence() {
+    use std::time::{Duration, Instant};
+
+    let mut watchdog = Watchdog::new(vec![
+        ReportingPeriod::new(Subsystem::Thermal, 60),
+        ReportingPeriod::new(Subsystem::Power, 10).level(AlertLevel::Warning),
+    ]);
+    let start = Instant::now();
+
+    // A subsystem that never reports is timed from creation
+    let stale = watchdog.check(start + Duration::from_secs(30));
+    assert_eq!(stale.len(), 1);
+    assert_eq!(stale[0].0, Subsystem::Power);
+    assert_eq!(stale[0].1.rule, "power.stale");
+    assert_eq!(stale[0].1.level, AlertLevel::Warning);
+
+    watchdog.observe(&thermal_packet(100, 20.0), start);
+    assert!(watchdog.check(start + Duration::from_secs(60)).is_empty());
+
+    let stale = watchdog.check(start + Duration::from_secs(61));
+    assert_eq!(stale.len(), 1);
+    let (subsystem, alert) = &stale[0];
+    assert_eq!(*subsystem, Subsystem::Thermal);
+    assert_eq!(alert.rule, "thermal.stale");
+    assert_eq!(alert.level, AlertLevel::Critical);
+    assert_eq!(alert.timestamp, 161);
+    assert!(watchdog.check(start + Duration::from_secs(120)).is_empty());
+
+    // A new packet re-arms the check
+    let resumed = start + Duration::from_secs(200);
+    watchdog.observe(&thermal_packet(300, 20.0), resumed);
+    assert!(watchdog.check(resumed).is_empty());
+    assert_eq!(watchdog.check(resumed + Duration::from_secs(61)).len(), 1);
+
+    // Reset restarts the timeout of every subsystem
+    watchdog.reset();
+    let rearmed = Instant::now();
+    assert!(watchdog.check(rearmed + Duration::from_secs(5)).is_empty());
+    let stale: Vec<Subsystem> = watchdog
+        .check(rearmed + Duration::from_secs(61))
+        .into_iter()
+        .map(|(subsystem, _)| subsystem)
+        .collect();
+    assert_eq!(stale, [Subsystem::Thermal, Subsystem::Power]);
+}
+
+#[test]
+fn test_watchdog_uses_the_given_observation_time() {
+    use std::time::{Duration, Instant};
+
+    let mut monitor = Monitor::new(vec![], vec![]);
+    monitor.watchdog = Watchdog::new(vec![ReportingPeriod::new(Subsystem::Thermal, 60)]);
+    let start = Instant::now();
+
+    // A replay passes times derived from packet timestamps
+    monitor.analyze_at(&thermal_packet(1000, 20.0), start);
+    monitor.analyze_at(&thermal_packet(1030, 20.0), start + Duration::from_secs(30));
+    assert!(monitor
+        .check_stale(start + Duration::from_secs(30))
+        .is_empty());
+    let stale = monitor.check_stale(start + Duration::from_secs(120));
+    assert_eq!(stale.len(), 1);
+    assert_eq!(stale[0].1.timestamp, 1120);
+
+    // Timestamps near the end of the range saturate
+    monitor.reset();
+    monitor.analyze_at(&thermal_packet(u64::MAX - 10, 20.0), start);
+    let stale = monitor.check_stale(start + Duration::from_secs(120));
+    assert_eq!(stale[0].1.timestamp, u64::MAX);
+}
+
+#[test]
+fn test_stale_alert_clears_when_telemetry_resumes() {
+    let mut monitor = Monitor::default();
+    let mut manager = AlertManager::new();
+    let start = std::time::Instant::now();
+
+    let packet = thermal_packet(100, 20.0);
+    let alerts = monitor.analyze(&packet);
+    manager.update(&packet, &alerts);
+
+    let later = start + std::time::Duration::from_secs(3600);
+    for (subsystem, alert) in monitor.check_stale(later) {
+        manager.raise(&subsystem, &[alert]);
+    }
+    // Subsystems that never reported are stale too
+    assert_eq!(manager.all().len(), monitor.watchdog.periods.len());
+    let thermal = |manager: &AlertManager| {
+        let record = manager.all().iter().find(|r| r.rule == "thermal.stale");
+        record.unwrap().state
+    };
+    assert_eq!(thermal(&manager), AlertState::Raised);
+
+    let packet = thermal_packet(200, 20.0);
+    let alerts = monitor.analyze(&packet);
+    manager.update(&packet, &alerts);
+    assert_eq!(thermal(&manager), AlertState::ReturnedToNominal);
+}
+
+#[test]
+fn test_config_profiles() {
+    let config = Config::from_toml_str(include_str!("../config.example.toml")).unwrap();
+    assert_eq!(config.profile, "cruise");
+    assert_eq!(config.simulation.interval_ms, 500);
+    assert_eq!(config.ui.sky_projection, Projection::Aitoff);
+    assert_eq!(config.monitor().unwrap().rules, RuleSet::default().rules);
+
+    let safe = config.profile("safe-mode").unwrap().rule_set();
+    let battery = safe.limits.iter().find(|l| l.name == "Battery").unwrap();
+    assert_eq!(battery.red_low, Some(30.0));
+    assert_eq!(safe.limits.len(), RuleSet::default().limits.len());
+    let confidence = safe
+        .rules
+        .iter()
+        .find(|r| r.name == "low_star_confidence")
+        .unwrap();
+    assert_eq!(confidence.threshold, 0.5);
+
+    let mut monitor = config.profile("safe-mode").unwrap().monitor();
+    let alerts = monitor.analyze(&TelemetryPacket {
+        timestamp: 100,
+        subsystem: Subsystem::Power,
+        payload: TelemetryPayload::Power(PowerData {
+            voltage: 28.0,
+            current: 2.0,
+            battery_level: 35.0,
+        }),
+        raw_counts: Default::default(),
+        derived: Default::default(),
+    });
+    assert_eq!(alerts[0].level, AlertLevel::Warning);
+    assert_eq!(alerts[0].rule, "Battery.yellow_low");
+
+    let defaults = Config::from_toml_str("").unwrap();
+    assert_eq!(defaults, Config::default());
+    assert_eq!(defaults.monitor().unwrap().rules, Monitor::default().rules);
+}
+
+#[test]
+fn test_config_validation_errors() {
+    let error = |toml: &str| Config::from_toml_str(toml).unwrap_err().to_string();
+
+    assert!(error("profile = \"launch\"").contains("profile 'launch' is not defined"));
+    assert!(error("[simulation]\ninterval = 5").contains("unknown field `interval`"));
+    assert!(
+        error("[source]\nkind = \"tcp-client\"\naddress = \"nowhere\"")
+            .contains("source.address 'nowhere'")
+    );
+
+    let unknown_field = r#"
+        [[profiles]]
+        name = "default"
+        [[profiles.limits]]
+        name = "Battery"
+        field = "power.battery"
+        red_low = 20.0
+    "#;
+    assert_eq!(
+        error(unknown_field),
+        "Invalid configuration: profile 'default': limit 'Battery': unknown field 'power.battery'"
+    );
+
+    let unordered = r#"
+        [[profiles]]
+        name = "default"
+        [[profiles.limits]]
+        name = "Battery"
+        field = "power.battery_level"
+        red_low = 40.0
+        yellow_low = 30.0
+    "#;
+    assert!(error(unordered).contains("limits must be ordered"));
+
+    let duplicate = r#"
+        [[profiles]]
+        name = "default"
+        [[profiles]]
+        name = "default"
+    "#;
+    assert!(error(duplicate).contains("duplicate profile 'default'"));
+
+    assert_eq!(
+        error("[battery]\ncapacity_ah = 0.0"),
+        "Invalid configuration: battery.capacity_ah 0 is not positive"
+    );
+}
+
+#[test]
+fn test_crc16_ccitt_check_value() {
+    assert_eq!(crc16_ccitt(b"123456789"), 0x29B1);
+    assert_eq!(crc16_ccitt(&[]), 0xFFFF);
+}
+
+#[test]
+fn test_parser_verifies_crc_trailer() {
+    let packet = thermal_packet(1, 20.0);
+    let frame = PacketEncoder::encode_with_crc(&packet).unwrap();
+    assert_eq!(frame.len(), thermal_bytes(1, 20.0).len() + 2);
+    assert_eq!(frame[8], 1 | parser::CRC_FLAG);
+    assert_eq!(Parser::parse(&frame).unwrap(), packet);
+    assert_eq!(
+        Parser::parse_with(&frame, CrcMode::Required).unwrap(),
+        packet
+    );
+
+    // A bit flip in the temperature is caught instead of parsed as a reading
+    let mut flipped = frame.clone();
+    flipped[parser::HEADER_LEN] ^= 0x01;
+    let trailer = u16::from_be_bytes([frame[frame.len() - 2], frame[frame.len() - 1]]);
+    match Parser::parse(&flipped) {
+        Err(e @ ParserError::ChecksumMismatch { expected, actual }) => {
+            assert_eq!(expected, trailer);
+            assert_ne!(actual, trailer);
+            assert!(e.is_corruption());
+        }
+        other => panic!("expected checksum mismatch, got {:?}", other),
+    }
+
+    // Legacy frames are accepted unless a trailer is required
+    let legacy = thermal_bytes(1, 20.0);
+    assert_eq!(Parser::parse(&legacy).unwrap(), packet);
+    assert_eq!(
+        Parser::parse_with(&legacy, CrcMode::Absent).unwrap(),
+        packet
+    );
+    assert!(matches!(
+        Parser::parse_with(&legacy, CrcMode::Required),
+        Err(ParserError::MissingChecksum)
+    ));
+    assert!(matches!(
+        Parser::parse_with(&frame, CrcMode::Absent),
+        Err(ParserError::InvalidSubsystem(0x81))
+    ));
+}
+
+#[test]
+fn test_framer_and_pipeline_count_corrupted_frames() {
+    let mut corrupted = PacketEncoder::encode_with_crc(&thermal_packet(1, 20.0)).unwrap();
+    corrupted[parser::HEADER_LEN + 3] ^= 0x10;
+    let good = PacketEncoder::encode_with_crc(&thermal_packet(2, 21.0)).unwrap();
+
+    let mut framer = PacketFramer::with_crc(CrcMode::Required);
+    framer.push(&corrupted);
+    framer.push(&good);
+    assert!(matches!(
+        framer.next_packet(),
+        Some(Err(ParserError::ChecksumMismatch { .. }))
+    ));
+    assert_eq!(
+        framer.next_packet().unwrap().unwrap(),
+        (thermal_packet(2, 21.0), good.len())
+    );
+
+    let pipeline = Pipeline::spawn(Monitor::default(), Vec::new());
+    pipeline.send(PipelineCommand::Replay {
+        frames: vec![corrupted, good],
+        interval: std::time::Duration::ZERO,
+    });
+    let mut snapshot = PipelineSnapshot::default();
+    run_pipeline_until(&pipeline, &mut snapshot, |s| {
+        s.replay_sent == 2 && s.last_packet.is_some()
+    });
+    assert_eq!(snapshot.corrupted, 1);
+    assert!(snapshot
+        .logs
+        .iter()
+        .any(|l| l.starts_with("Corrupted packet")));
+}
+
+#[test]
+fn test_calibration_curves() {
+    let poly = Calibration::Polynomial {
+        coefficients: vec![1.0, 0.5, 0.25],
+    };
+    assert_eq!(poly.apply(2.0), 3.0);
+
+    let table = Calibration::Table {
+        points: vec![(0.0, 0.0), (100.0, 10.0), (200.0, 50.0)],
+    };
+    assert_eq!(table.apply(50.0), 5.0);
+    assert_eq!(table.apply(150.0), 30.0);
+    assert_eq!(table.apply(-10.0), 0.0);
+    assert_eq!(table.apply(500.0), 50.0);
+
+    // At mid-scale the 10k thermistor equals the series resistor: 25 C
+    let thermistor = CalibrationSet::default()
+        .get("thermal.temp_celsius")
+        .unwrap()
+        .clone();
+    assert!((thermistor.apply(65_535.0 / 2.0) - 25.0).abs() < 0.01);
+    assert!(thermistor.apply(20_000.0) > 25.0);
+    assert!(thermistor.apply(0.0).is_finite());
+
+    let unordered = Calibration::Table {
+        points: vec![(10.0, 0.0), (5.0, 1.0)],
+    };
+    assert!(unordered
+        .validate()
+        .unwrap_err()
+        .contains("strictly increasing"));
+    assert!(Calibration::Polynomial {
+        coefficients: vec![]
+    }
+    .validate()
+    .is_err());
+
+    // A full scale of 2 or less leaves no count between open and short
+    let thermistor = |full_scale| Calibration::SteinhartHart {
+        a: 1.129148e-3,
+        b: 2.34125e-4,
+        c: 8.76741e-8,
+        series_ohms: 10_000.0,
+        full_scale,
+    };
+    assert!(thermistor(1.5).validate().is_err());
+    assert!(thermistor(2.0).validate().is_err());
+    assert!(thermistor(2.5).validate().is_ok());
+    assert!(thermistor(2.5).apply(0.0).is_finite());
+}
+
+#[test]
+fn test_raw_counts_are_calibrated() {
+    let packet = TelemetryPacket {
+        timestamp: 7,
+        subsystem: Subsystem::Power,
+        payload: TelemetryPayload::Power(PowerData {
+            voltage: 24.0,
+            current: 1.0,
+            battery_level: 15.0,
+        }),
+        raw_counts: [
+            ("power.voltage".to_string(), 2400),
+            ("power.current".to_string(), 33768),
+            ("power.battery_level".to_string(), 10500),
+        ]
+        .into(),
+        derived: Default::default(),
+    };
+    let frame = PacketEncoder::encode_with_crc(&packet).unwrap();
+    assert_eq!(frame.len(), parser::HEADER_LEN + 6 + parser::CRC_LEN);
+    assert_eq!(frame[8], parser::CRC_FLAG | parser::RAW_FLAG);
+
+    // The parser keeps the counts; calibration turns them into the payload
+    let mut parsed = Parser::parse(&frame).unwrap();
+    assert_eq!(parsed.raw_counts, packet.raw_counts);
+    assert_eq!(parsed.payload.field("power.voltage"), Some(2400.0));
+    CalibrationSet::default().apply(&mut parsed);
+    for (field, value) in packet.payload.fields() {
+        assert!((parsed.payload.field(&field).unwrap() - value).abs() < 1e-9);
+    }
+
+    let identity = Quaternion {
+        w: 1.0,
+        x: 0.0,
+        y: 0.0,
+        z: 0.0,
+    };
+    let mut aocs = aocs_packet(0.0, 0.0, identity);
+    aocs.raw_counts.insert("aocs.rate_x".to_string(), 1);
+    assert!(matches!(
+        PacketEncoder::encode(&aocs),
+        Err(EncoderError::UnsupportedPayload(_))
+    ));
+    let mut wide = packet.clone();
+    wide.raw_counts.insert("power.voltage".to_string(), 70_000);
+    assert!(matches!(
+        PacketEncoder::encode(&wide),
+        Err(EncoderError::RawCountTooLarge(_, 70_000))
+    ));
+    let mut data = thermal_bytes(1, 20.0);
+    data[8] = 2 | parser::RAW_FLAG;
+    assert!(matches!(
+        Parser::parse(&data),
+        Err(ParserError::RawNotSupported(2))
+    ));
+}
+
+#[test]
+fn test_config_calibrations() {
+    let config = Config::from_toml_str(
+        r#"
+        [[calibrations]]
+        field = "thermal.temp_celsius"
+        type = "table"
+        points = [[0.0, -40.0], [4095.0, 125.0]]
+        "#,
+    )
+    .unwrap();
+    let calibration = config.calibration(&PacketDefinitions::built_in());
+    assert_eq!(
+        calibration
+            .get("thermal.temp_celsius")
+            .unwrap()
+            .apply(4095.0),
+        125.0
+    );
+    assert!(calibration.get("power.voltage").is_some());
+
+    let error = |toml: &str| Config::from_toml_str(toml).unwrap_err().to_string();
+    let unknown = r#"
+        [[calibrations]]
+        field = "power.battery"
+        type = "polynomial"
+        coefficients = [0.0, 1.0]
+    "#;
+    assert!(error(unknown).contains("unknown field 'power.battery'"));
+    let duplicate = r#"
+        [[calibrations]]
+        field = "power.voltage"
+        type = "polynomial"
+        coefficients = [0.0, 1.0]
+        [[calibrations]]
+        field = "power.voltage"
+        type = "polynomial"
+        coefficients = [0.0, 2.0]
+    "#;
+    assert!(error(duplicate).contains("duplicate calibration for 'power.voltage'"));
+}
+
+#[test]
+fn test_builtin_definitions_decode_typed_payloads() {
+    let identity = Quaternion {
+        w: 1.0,
+        x: 0.0,
+        y: 0.0,
+        z: 0.0,
+    };
+    let packet = aocs_packet(0.5, 4200.0, identity);
+    let bytes = PacketEncoder::encode(&packet).unwrap();
+
+    let definitions = PacketDefinitions::default();
+    let aocs = definitions.get(2).unwrap();
+    assert_eq!(aocs.min_len(), 58);
+    let (parameters, used) = aocs.decode(&bytes[parser::HEADER_LEN..]).unwrap();
+    assert_eq!(used, bytes.len() - parser::HEADER_LEN);
+    assert_eq!(parameters["aocs.rate_y"], Value::Number(0.5));
+    assert_eq!(
+        parameters["aocs.wheel_speeds"],
+        Value::List(vec![4200.0, 0.0, 0.0, 0.0])
+    );
+    assert_eq!(
+        Parser::typed_payload(2, parameters).unwrap(),
+        (Subsystem::Aocs, packet.payload)
+    );
+}
+
+#[test]
+fn test_definition_file_adds_instrument() {
+    let definitions =
+        PacketDefinitions::from_toml_str(include_str!("../packets.example.toml")).unwrap();
+    assert!(definitions.get(0).is_some());
+    let magnetometer = definitions.get(4).unwrap();
+    assert_eq!(
+        magnetometer.field("bx").unwrap().unit.as_deref(),
+        Some("nT")
+    );
+
+    // Offset binary counts of 2 nT: 100, -100 and 14 nT
+    let mut payload = Vec::new();
+    for axis in [32818u16, 32718, 32775] {
+        payload.extend_from_slice(&axis.to_le_bytes());
+    }
+    payload.push(2);
+    payload.extend_from_slice(&20.5f32.to_be_bytes());
+    payload.extend_from_slice(&21.0f32.to_be_bytes());
+    payload.push(4);
+    payload.extend_from_slice(b"SAFE");
+
+    let mut data = Vec::new();
+    data.extend_from_slice(&42u64.to_be_bytes());
+    data.push(4);
+    data.extend_from_slice(&(payload.len() as u16).to_be_bytes());
+    data.extend_from_slice(&payload);
+
+    // Only parsers given the definitions know ID 4
+    assert!(matches!(
+        Parser::parse(&data),
+        Err(ParserError::InvalidSubsystem(4))
+    ));
+    let mut packet = Parser::parse_using(&data, CrcMode::Optional, &definitions).unwrap();
+    assert_eq!(packet.subsystem, Subsystem::Other(4));
+    assert_eq!(packet.payload.field("magnetometer.by"), Some(32718.0));
+
+    // Definition calibrations go through the calibration set, which keeps
+    // the counts
+    let calibration = Config::default().calibration(&definitions);
+    calibration.apply(&mut packet);
+    calibration.apply(&mut packet);
+    assert_eq!(packet.raw_counts["magnetometer.by"], 32718);
+    assert_eq!(packet.raw_counts.len(), 3);
+    let TelemetryPayload::Generic(parameters) = &packet.payload else {
+        panic!("expected a generic payload, got {:?}", packet.payload);
+    };
+    assert_eq!(parameters["magnetometer.by"], Value::Number(-100.0));
+    assert_eq!(
+        parameters["magnetometer.board_temps"],
+        Value::List(vec![20.5, 21.0])
+    );
+    assert_eq!(parameters["magnetometer.mode"], Value::Text("SAFE".into()));
+    assert_eq!(packet.payload.field("magnetometer.bz"), Some(14.0));
+    assert_eq!(packet.payload.field("magnetometer.mode"), None);
+
+    assert!(matches!(
+        Parser::parse_using(&data[..data.len() - 1], CrcMode::Optional, &definitions),
+        Err(ParserError::BufferTooShort | ParserError::LengthMismatch { .. })
+    ));
+    let mut framer = PacketFramer::new().definitions(std::sync::Arc::new(definitions.clone()));
+    framer.push(&data);
+    let mut framed = framer.next_packet().unwrap().unwrap().0;
+    calibration.apply(&mut framed);
+    assert_eq!(framed, packet);
+
+    assert!(matches!(
+        PacketEncoder::encode(&packet),
+        Err(EncoderError::UnsupportedPayload(_))
+    ));
+    // Calibrated fields are written back as their counts
+    assert_eq!(
+        PacketEncoder::encode_using(&packet, &definitions).unwrap(),
+        data
+    );
+
+    let signed = include_str!("../packets.example.toml").replacen("\"u16\"", "\"i16\"", 1);
+    assert!(PacketDefinitions::from_toml_str(&signed)
+        .unwrap_err()
+        .to_string()
+        .contains("field 'bx': only single u8, u16 or u32 counts can be calibrated"));
+    let probe = PacketDefinitions::from_toml_str(
+        r#"
+        [[packets]]
+        id = 5
+        name = "probe"
+        [[packets.fields]]
+        name = "level"
+        type = "f32"
+        [[packets.fields]]
+        name = "count"
+        type = "u32"
+        "#,
+    )
+    .unwrap();
+    let set = |field: &str| CalibrationSet {
+        parameters: vec![ParameterCalibration::new(
+            field,
+            Calibration::Polynomial {
+                coefficients: vec![0.0, 1.0],
+            },
+        )],
+    };
+    assert!(set("probe.count").validate_using(&probe).is_ok());
+    assert!(set("probe.level")
+        .validate_using(&probe)
+        .unwrap_err()
+        .contains("'probe.level' is not a count field"));
+}
+
+#[test]
+fn test_monitor_checks_instruments_from_definitions() {
+    use std::time::{Duration, Instant};
+
+    let profile = r#"
+        [[profiles]]
+        name = "default"
+        [[profiles.limits]]
+        name = "Field Z"
+        field = "magnetometer.bz"
+        yellow_high = 10.0
+        [[profiles.watchdog]]
+        subsystem = { Other = 4 }
+        timeout_seconds = 30
+    "#;
+    let error = |toml: &str| Config::from_toml_str(toml).unwrap_err().to_string();
+    assert!(error(profile).contains("limit 'Field Z': unknown field 'magnetometer.bz'"));
+    let with_definitions = format!("definitions = \"packets.example.toml\"\n{}", profile);
+    let config = Config::from_toml_str(&with_definitions).unwrap();
+    assert!(
+        error(&with_definitions.replace("magnetometer.bz", "magnetometer.mode"))
+            .contains("unknown field 'magnetometer.mode'")
+    );
+    assert!(error(
+        &profile
+            .replace("Field Z", "X")
+            .replace("magnetometer.bz", "power.voltage")
+    )
+    .contains("reporting period for Other(4): no packet definition for id 4"));
+
+    let definitions = std::sync::Arc::new(config.packet_definitions().unwrap());
+    let mut monitor = config.monitor().unwrap();
+    monitor.watchdog.set_definitions(definitions);
+    let start = Instant::now();
+    let packet = TelemetryPacket {
+        timestamp: 10,
+        subsystem: Subsystem::Other(4),
+        payload: TelemetryPayload::Generic(
+            [("magnetometer.bz".to_string(), Value::Number(14.0))].into(),
+        ),
+        raw_counts: Default::default(),
+        derived: Default::default(),
+    };
+    let alerts = monitor.analyze(&packet);
+    assert_eq!(alerts.len(), 1);
+    assert_eq!(alerts[0].field, "magnetometer.bz");
+    assert_eq!(alerts[0].level, AlertLevel::Warning);
+
+    // Watchdog alerts use the definition name too
+    let stale = monitor.check_stale(start + Duration::from_secs(60));
+    let (_, alert) = stale
+        .iter()
+        .find(|(subsystem, _)| *subsystem == Subsystem::Other(4))
+        .unwrap();
+    assert_eq!(alert.rule, "magnetometer.stale");
+    assert_eq!(alert.field, "magnetometer");
+}
+
+#[test]
+fn test_definition_repeat_counts_are_bounded() {
+    let definitions = PacketDefinitions::from_toml_str(
+        r#"
+        [[packets]]
+        id = 5
+        name = "sensor"
+        [[packets.fields]]
+        name = "n"
+        type = "u64"
+        [[packets.fields]]
+        name = "samples"
+        type = "f64"
+        count = "n"
+
+        [[packets]]
+        id = 6
+        name = "log"
+        [[packets.fields]]
+        name = "n"
+        type = "u32"
+        [[packets.fields]]
+        name = "lines"
+        type = "string"
+        count = "n"
+        "#,
+    )
+    .unwrap();
+
+    // A count whose byte length overflows is a mismatch, not a panic
+    let mut payload = u64::MAX.to_be_bytes().to_vec();
+    payload.extend_from_slice(&1.0f64.to_be_bytes());
+    let sensor = definitions.get(5).unwrap();
+    assert!(matches!(
+        sensor.decode(&payload),
+        Err(ParserError::LengthMismatch { .. })
+    ));
+
+    // More strings than bytes left
+    let mut payload = u32::MAX.to_be_bytes().to_vec();
+    payload.extend_from_slice(&[1, b'a']);
+    let log = definitions.get(6).unwrap();
+    assert!(matches!(
+        log.decode(&payload),
+        Err(ParserError::LengthMismatch { .. })
+    ));
+
+    let mut payload = 2u32.to_be_bytes().to_vec();
+    payload.extend_from_slice(&[1, b'a', 0]);
+    let (parameters, used) = log.decode(&payload).unwrap();
+    assert_eq!(used, payload.len());
+    assert_eq!(
+        parameters["log.lines"],
+        Value::TextList(vec!["a".into(), String::new()])
+    );
+}
+
+#[test]
+fn test_definition_validation_errors() {
+    let error = |toml: &str| {
+        PacketDefinitions::from_toml_str(toml)
+            .unwrap_err()
+            .to_string()
+    };
+
+    let flag = r#"
+        [[packets]]
+        id = 0x45
+        name = "sensor"
+        fields = []
+    "#;
+    assert!(error(flag).contains("collides with the CRC and raw flags"));
+
+    let count = r#"
+        [[packets]]
+        id = 5
+        name = "sensor"
+        [[packets.fields]]
+        name = "samples"
+        type = "u16"
+        count = "n"
+        [[packets.fields]]
+        name = "n"
+        type = "u8"
+    "#;
+    assert_eq!(
+        error(count),
+        "Invalid packet definitions: packet 'sensor' (id 5): field 'samples': count 'n' is not an earlier uncalibrated integer field"
+    );
+
+    let thermal = r#"
+        [[packets]]
+        id = 1
+        name = "thermal"
+        [[packets.fields]]
+        name = "temp"
+        type = "u16"
+    "#;
+    assert!(error(thermal).contains("field 1 must be 'temp_celsius' as built in"));
+
+    // Built-in IDs keep the layout the typed payloads are encoded in
+    let power = |voltage: &str, extra: &str| {
+        format!(
+            r#"
+            [[packets]]
+            id = 0
+            name = "power"
+            [[packets.fields]]
+            name = "voltage"
+            {}
+            [[packets.fields]]
+            name = "current"
+            type = "f64"
+            [[packets.fields]]
+            name = "battery_level"
+            type = "f64"
+            {}
+            "#,
+            voltage, extra
+        )
+    };
+    let f32_voltage = power("type = \"f32\"", "");
+    assert!(error(&f32_voltage).contains("field 1 must be 'voltage' as built in"));
+    let required = "[[packets.fields]]\nname = \"bus\"\ntype = \"u8\"";
+    let added = power("type = \"f64\"\nunit = \"V\"", required);
+    assert!(error(&added).contains("added field 'bus' must be optional"));
+    let optional = format!("{}\noptional = true", required);
+    let definitions =
+        PacketDefinitions::from_toml_str(&power("type = \"f64\"", &optional)).unwrap();
+    let bytes = PacketEncoder::encode(&battery_packet(1, 50.0)).unwrap();
+    assert_eq!(
+        Parser::parse_using(&bytes, CrcMode::Optional, &definitions).unwrap(),
+        battery_packet(1, 50.0)
+    );
+
+    assert!(
+        error("[[packets]]\nid = 4\nname = \"x\"\nfields = []\nsize = 3")
+            .contains("unknown field `size`")
+    );
+}
+
+fn zoned_thermal_packet(timestamp: u64, zones: &[(&str, f64)]) -> TelemetryPacket {
+    let mut packet = thermal_packet(timestamp, 20.0);
+    if let TelemetryPayload::Thermal(t) = &mut packet.payload {
+        t.zones = zones
+            .iter()
+            .map(|(name, temp)| ThermalZone {
+                name: name.to_string(),
+                temp_celsius: *temp,
+            })
+            .collect();
+    }
+    packet
+}
+
+#[test]
+fn test_thermal_zones_round_trip() {
+    let packet = zoned_thermal_packet(5, &[("battery", 21.5), ("radiator_px", -40.0)]);
+    let bytes = PacketEncoder::encode(&packet).unwrap();
+    // Temperature, zone count, two length-prefixed names, two temperatures
+    assert_eq!(bytes.len(), 11 + 8 + 1 + (1 + 7) + (1 + 11) + 16);
+    let parsed = Parser::parse(&bytes).unwrap();
+    assert_eq!(parsed, packet);
+    assert_eq!(
+        parsed.payload.field("thermal.zone.radiator_px"),
+        Some(-40.0)
+    );
+    let TelemetryPayload::Thermal(thermal) = &parsed.payload else {
+        panic!("expected a thermal payload");
+    };
+    assert_eq!(thermal.zone("battery").unwrap().temp_celsius, 21.5);
+
+    // Frames from senders without zones still parse
+    let legacy = thermal_bytes(6, 18.0);
+    assert_eq!(legacy.len(), 11 + 8);
+    assert_eq!(Parser::parse(&legacy).unwrap(), thermal_packet(6, 18.0));
+
+    let many: Vec<(String, f64)> = (0..300).map(|i| (format!("z{}", i), 0.0)).collect();
+    let many: Vec<(&str, f64)> = many.iter().map(|(n, t)| (n.as_str(), *t)).collect();
+    assert!(matches!(
+        PacketEncoder::encode(&zoned_thermal_packet(7, &many)),
+        Err(EncoderError::TooManyZones(300))
+    ));
+}
+
+#[test]
+fn test_thermal_zone_limits() {
+    let mut monitor = Monitor::default();
+    let packet = zoned_thermal_packet(1, &[("battery", 47.0), ("obc", 30.0)]);
+    let alerts = monitor.analyze(&packet);
+    assert_eq!(alerts.len(), 1);
+    assert_eq!(alerts[0].field, "thermal.zone.battery");
+    assert_eq!(alerts[0].level, AlertLevel::Critical);
+
+    let toml = r#"
+        [[limits]]
+        name = "Battery Heater"
+        field = "thermal.zone.battery_heater"
+        yellow_high = 60.0
+    "#;
+    let validate = |toml: &str| RuleSet::from_toml_str(toml).unwrap().validate();
+    assert!(validate(toml).is_ok());
+    assert!(validate(&toml.replace("battery_heater", "")).is_err());
+    assert!(models::is_field_path("thermal.zone.obc"));
+    assert!(!models::is_field_path("thermal.zone.a.b"));
+}
+
+fn power_packet(timestamp: u64, current: f64, battery_level: f64) -> TelemetryPacket {
+    let mut packet = battery_packet(timestamp, battery_level);
+    if let TelemetryPayload::Power(p) = &mut packet.payload {
+        p.current = current;
+    }
+    packet
+}
+
+#[test]
+fn test_power_analytics_orbits() {
+    let mut power = PowerAnalytics::new(BatteryConfig {
+        capacity_ah: 10.0,
+        ..BatteryConfig::default()
+    });
+    let mut step = |timestamp: u64, current: f64, level: f64| {
+        let mut packet = power_packet(timestamp, current, level);
+        power.apply(&mut packet);
+        packet
+    };
+
+    // Discharging at 6 A enters eclipse; 70% above the minimum lasts 4200 s
+    let p = step(0, 6.0, 90.0);
+    assert_eq!(p.field("power.eclipse"), Some(1.0));
+    assert!((p.field("power.time_to_min_battery").unwrap() - 4200.0).abs() < 1e-6);
+
+    // 1 Ah used in 10 minutes: 10% of capacity
+    let p = step(600, 6.0, 80.0);
+    assert!((p.field("power.charge_used_ah").unwrap() - 1.0).abs() < 1e-9);
+    assert!((p.field("power.soc_estimate").unwrap() - 80.0).abs() < 1e-9);
+    assert!(p.field("power.soc_error").unwrap().abs() < 1e-9);
+    assert!((p.field("power.depth_of_discharge").unwrap() - 10.0).abs() < 1e-9);
+
+    // Charging means sunlight; the orbit keeps its deepest discharge
+    let p = step(1200, -4.0, 72.0);
+    assert_eq!(p.field("power.eclipse"), Some(0.0));
+    assert_eq!(p.field("power.time_to_min_battery"), None);
+    let p = step(1800, -4.0, 75.0);
+    assert!((p.field("power.charge_used_ah").unwrap() - 0.5).abs() < 1e-9);
+    assert!((p.field("power.depth_of_discharge").unwrap() - 35.0 / 3.0).abs() < 1e-9);
+
+    // The next eclipse starts orbit 2; the gap before it is not integrated
+    let p = step(3000, 6.0, 60.0);
+    assert!((p.field("power.charge_used_ah").unwrap() - 0.5).abs() < 1e-9);
+    assert_eq!(p.field("power.depth_of_discharge"), Some(0.0));
+
+    let orbits = power.orbits();
+    assert_eq!(orbits.len(), 2);
+    assert_eq!(orbits[0].eclipse_seconds(), Some(1200));
+    assert_eq!(orbits[0].end, Some(3000));
+    assert_eq!((orbits[1].number, orbits[1].eclipse_seconds()), (2, None));
+
+    // Other subsystems are left alone
+    let mut thermal = thermal_packet(3100, 20.0);
+    power.apply(&mut thermal);
+    assert!(thermal.derived.is_empty());
+}
+
+#[test]
+fn test_power_analytics_ignores_noise_and_non_finite_values() {
+    let mut power = PowerAnalytics::new(BatteryConfig {
+        capacity_ah: 10.0,
+        ..BatteryConfig::default()
+    });
+    let mut step = |timestamp: u64, current: f64, level: f64| {
+        let mut packet = power_packet(timestamp, current, level);
+        power.apply(&mut packet);
+        packet
+    };
+
+    step(0, 6.0, 90.0);
+    // Current noise within 0.1 A of zero keeps the eclipse state
+    for (i, current) in [0.05, -0.05, 0.08, -0.09].into_iter().enumerate() {
+        let p = step(60 * (i as u64 + 1), current, 85.0);
+        assert_eq!(p.field("power.eclipse"), Some(1.0));
+    }
+
+    // A NaN current or level is skipped rather than integrated
+    let p = step(300, f64::NAN, 85.0);
+    assert!(p.derived.is_empty());
+    let p = step(310, 6.0, f64::INFINITY);
+    assert!(p.derived.is_empty());
+    let p = step(360, 6.0, 80.0);
+    let used = p.field("power.charge_used_ah").unwrap();
+    assert!(used.is_finite() && used > 0.0);
+    assert!(p.field("power.soc_estimate").unwrap().is_finite());
+    assert!(p.field("power.depth_of_discharge").unwrap().is_finite());
+
+    step(420, -4.0, 80.0);
+    assert_eq!(power.orbits().len(), 1);
+    assert_eq!(power.orbits()[0].eclipse_seconds(), Some(420));
+
+    let config = BatteryConfig {
+        eclipse_current: -1.0,
+        ..BatteryConfig::default()
+    };
+    assert!(config.validate().is_err());
+}
+
+#[test]
+fn test_raw_power_counts_follow_the_discharge_sign() {
+    let mut power = PowerAnalytics::default();
+    let calibration = CalibrationSet::default();
+    let mut step = |timestamp: u64, current_count: u32| {
+        let mut packet = power_packet(timestamp, 0.0, 0.0);
+        packet.raw_counts = [
+            ("power.voltage".to_string(), 2400),
+            ("power.current".to_string(), current_count),
+            ("power.battery_level".to_string(), 10500),
+        ]
+        .into();
+        let frame = PacketEncoder::encode(&packet).unwrap();
+        let mut parsed = Parser::parse(&frame).unwrap();
+        calibration.apply(&mut parsed);
+        power.apply(&mut parsed);
+        parsed
+    };
+
+    // Counts above the 32768 offset are discharge: eclipse
+    let p = step(100, 33768);
+    assert!((p.field("power.current").unwrap() - 1.0).abs() < 1e-9);
+    assert_eq!(p.field("power.eclipse"), Some(1.0));
+    assert_eq!(p.field("power.depth_of_discharge"), Some(0.0));
+
+    // Counts below it are charge: sunlight
+    let p = step(200, 31768);
+    assert!((p.field("power.current").unwrap() + 1.0).abs() < 1e-9);
+    assert_eq!(p.field("power.eclipse"), Some(0.0));
+
+    // A sunlit timestamp before the eclipse entry does not underflow
+    step(300, 33768);
+    step(50, 31768);
+    assert_eq!(power.orbits().back().unwrap().eclipse_seconds(), Some(0));
+}
+
+#[test]
+fn test_monitor_checks_derived_power_fields() {
+    let mut monitor = Monitor::default();
+    let mut power = PowerAnalytics::new(BatteryConfig {
+        capacity_ah: 20.0,
+        ..BatteryConfig::default()
+    });
+
+    // 3.2 Ah above the minimum at 2 A: 5760 s left
+    let mut packet = power_packet(0, 2.0, 36.0);
+    power.apply(&mut packet);
+    assert!(monitor.analyze(&packet).is_empty());
+
+    // 3 Ah at 4 A: 2700 s left
+    let mut packet = power_packet(60, 4.0, 35.0);
+    power.apply(&mut packet);
+    packet
+        .derived
+        .insert("power.depth_of_discharge".to_string(), 30.0);
+    let alerts = monitor.analyze(&packet);
+    let rules: Vec<&str> = alerts.iter().map(|a| a.rule.as_str()).collect();
+    assert_eq!(rules, ["battery_margin", "Depth of Discharge.yellow_high"]);
 }
@@ -1,19 +1,78 @@
 use astro_monitor::gui::AstroMonitorApp;
+use astro_monitor::Config;
 use eframe::egui;
+use std::process::ExitCode;
 
-fn main() -> eframe::Result<()> {
+const USAGE: &str = "\
+Usage: astro_monitor [OPTIONS]
+
+Options:
+  --config <PATH>  Load thresholds, profiles, sources and UI options from a TOML file
+  -h, --help       Print this help";
+
+/// Returns the `--config` path, if any.
+fn parse_args() -> Result<Option<String>, String> {
+    let mut args = std::env::args().skip(1);
+    let mut path = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--config" => path = Some(args.next().ok_or("--config needs a path")?),
+            "-h" | "--help" => return Err(String::new()),
+            _ => return Err(format!("Unknown argument: {}", arg)),
+        }
+    }
+
+    Ok(path)
+}
+
+fn main() -> ExitCode {
     env_logger::init();
+
+    let path = match parse_args() {
+        Ok(path) => path,
+        Err(e) => {
+            if !e.is_empty() {
+                eprintln!("Error: {}\n", e);
+            }
+            eprintln!("{}", USAGE);
+            return ExitCode::from(2);
+        }
+    };
+    let config = match &path {
+        Some(path) => match Config::load(path) {
+            Ok(config) => config,
+            Err(e) => {
+                eprintln!("Error loading config from {}: {}", path, e);
+                return ExitCode::from(2);
+            }
+        },
+        None => Config::default(),
+    };
+    let definitions = match config.packet_definitions() {
+        Ok(definitions) => definitions,
+        Err(e) => {
+            eprintln!("Error loading packet definitions: {}", e);
+            return ExitCode::from(2);
+        }
+    };
     println!("Starting Astro Monitor GUI...");
 
     let options = eframe::NativeOptions {
-        viewport: egui::ViewportBuilder::default()
-            .with_inner_size([800.0, 600.0]),
+        viewport: egui::ViewportBuilder::default().with_inner_size([800.0, 600.0]),
         ..Default::default()
     };
 
-    eframe::run_native(
+    let result = eframe::run_native(
         "Astro Monitor",
         options,
-        Box::new(|_cc| Ok(Box::new(AstroMonitorApp::default()))),
-    )
+        Box::new(|_cc| Ok(Box::new(AstroMonitorApp::new(config, definitions)))),
+    );
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
 }
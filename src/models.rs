@@ -1,4 +1,32 @@
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::BTreeMap;
+
+// serde_json writes NaN and infinities as `null`; these read them back as
+// NaN so archived packets with a bad value still load.
+
+fn nullable<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+    Ok(Option::<f64>::deserialize(deserializer)?.unwrap_or(f64::NAN))
+}
+
+fn nullable_rates<'de, D: Deserializer<'de>>(deserializer: D) -> Result<[f64; 3], D::Error> {
+    let rates = <[Option<f64>; 3]>::deserialize(deserializer)?;
+    Ok(rates.map(|r| r.unwrap_or(f64::NAN)))
+}
+
+fn nullable_list<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<f64>, D::Error> {
+    let list = Vec::<Option<f64>>::deserialize(deserializer)?;
+    Ok(list.into_iter().map(|v| v.unwrap_or(f64::NAN)).collect())
+}
+
+fn nullable_map<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<BTreeMap<String, f64>, D::Error> {
+    let map = BTreeMap::<String, Option<f64>>::deserialize(deserializer)?;
+    Ok(map
+        .into_iter()
+        .map(|(k, v)| (k, v.unwrap_or(f64::NAN)))
+        .collect())
+}
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Subsystem {
@@ -6,44 +34,384 @@ pub enum Subsystem {
     Thermal,
     Aocs, // Attitude and Orbit Control System
     StarTracker,
+    Other(u8), // Instrument known only from a packet definition, by ID
+}
+
+impl Subsystem {
+    /// Subsystem ID used on the wire.
+    pub fn id(&self) -> u8 {
+        match self {
+            Subsystem::Power => 0,
+            Subsystem::Thermal => 1,
+            Subsystem::Aocs => 2,
+            Subsystem::StarTracker => 3,
+            Subsystem::Other(id) => *id,
+        }
+    }
+
+    /// Prefix of the subsystem's field paths, e.g. `star_tracker`. An
+    /// instrument known from a packet definition is keyed by the definition
+    /// name instead, see `PacketDefinitions::key`; `subsystem_<id>` is only
+    /// a fallback for IDs without one.
+    pub fn key(&self) -> String {
+        match self {
+            Subsystem::Power => "power".to_string(),
+            Subsystem::Thermal => "thermal".to_string(),
+            Subsystem::Aocs => "aocs".to_string(),
+            Subsystem::StarTracker => "star_tracker".to_string(),
+            Subsystem::Other(id) => format!("subsystem_{}", id),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct CelestialCoordinates {
+    #[serde(deserialize_with = "nullable")]
     pub right_ascension: f64, // degrees
-    pub declination: f64,     // degrees
+    #[serde(deserialize_with = "nullable")]
+    pub declination: f64, // degrees
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PowerData {
-    pub voltage: f64,       // Volts
-    pub current: f64,       // Amperes
+    #[serde(deserialize_with = "nullable")]
+    pub voltage: f64, // Volts
+    #[serde(deserialize_with = "nullable")]
+    pub current: f64, // Amperes, positive while the battery discharges
+    #[serde(deserialize_with = "nullable")]
     pub battery_level: f64, // Percentage
 }
 
+/// One named thermistor, e.g. `battery` or `radiator_px`.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub struct ThermalData {
+pub struct ThermalZone {
+    pub name: String,
+    #[serde(deserialize_with = "nullable")]
     pub temp_celsius: f64,
 }
 
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ThermalData {
+    #[serde(deserialize_with = "nullable")]
+    pub temp_celsius: f64, // Primary (structure) sensor
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub zones: Vec<ThermalZone>,
+}
+
+impl ThermalData {
+    pub fn zone(&self, name: &str) -> Option<&ThermalZone> {
+        self.zones.iter().find(|z| z.name == name)
+    }
+}
+
+/// Prefix of the field path of each thermal zone, see `zone_path`.
+pub const ZONE_PREFIX: &str = "thermal.zone.";
+
+/// Field path of a thermal zone's temperature, e.g. `thermal.zone.battery`.
+pub fn zone_path(zone: &str) -> String {
+    format!("{}{}", ZONE_PREFIX, zone)
+}
+
+/// Whether `path` is one of `FIELD_PATHS`, `DERIVED_FIELD_PATHS` or a
+/// thermal zone.
+pub fn is_field_path(path: &str) -> bool {
+    FIELD_PATHS.contains(&path)
+        || DERIVED_FIELD_PATHS.contains(&path)
+        || path
+            .strip_prefix(ZONE_PREFIX)
+            .is_some_and(|zone| !zone.is_empty() && !zone.contains('.'))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Quaternion {
+    #[serde(deserialize_with = "nullable")]
+    pub w: f64,
+    #[serde(deserialize_with = "nullable")]
+    pub x: f64,
+    #[serde(deserialize_with = "nullable")]
+    pub y: f64,
+    #[serde(deserialize_with = "nullable")]
+    pub z: f64,
+}
+
+impl Quaternion {
+    pub fn norm(&self) -> f64 {
+        (self.w * self.w + self.x * self.x + self.y * self.y + self.z * self.z).sqrt()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ControlMode {
+    Standby,
+    Detumble,
+    SunPointing,
+    NadirPointing,
+    FinePointing,
+}
+
+impl ControlMode {
+    /// Mode code used on the wire.
+    pub fn code(&self) -> u8 {
+        match self {
+            ControlMode::Standby => 0,
+            ControlMode::Detumble => 1,
+            ControlMode::SunPointing => 2,
+            ControlMode::NadirPointing => 3,
+            ControlMode::FinePointing => 4,
+        }
+    }
+
+    pub fn from_code(code: u8) -> Option<Self> {
+        match code {
+            0 => Some(ControlMode::Standby),
+            1 => Some(ControlMode::Detumble),
+            2 => Some(ControlMode::SunPointing),
+            3 => Some(ControlMode::NadirPointing),
+            4 => Some(ControlMode::FinePointing),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AocsData {
+    pub attitude: Quaternion, // Body to inertial, scalar first
+    #[serde(deserialize_with = "nullable_rates")]
+    pub angular_rate: [f64; 3], // deg/s about body X, Y, Z
+    #[serde(deserialize_with = "nullable_list")]
+    pub wheel_speeds: Vec<f64>, // Reaction wheel speeds, RPM
+    pub control_mode: ControlMode,
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct StarTrackerReading {
     pub target_id: Option<String>,
     pub coordinates: CelestialCoordinates,
+    #[serde(deserialize_with = "nullable")]
     pub confidence: f64, // 0.0 to 1.0
 }
 
+/// Every fixed path returned by `TelemetryPayload::fields`, in subsystem
+/// order. Thermal zones add one `thermal.zone.<name>` path each.
+pub const FIELD_PATHS: [&str; 13] = [
+    "power.voltage",
+    "power.current",
+    "power.battery_level",
+    "thermal.temp_celsius",
+    "aocs.rate_x",
+    "aocs.rate_y",
+    "aocs.rate_z",
+    "aocs.max_angular_rate",
+    "aocs.max_wheel_speed",
+    "aocs.quaternion_norm_error",
+    "star_tracker.right_ascension",
+    "star_tracker.declination",
+    "star_tracker.confidence",
+];
+
+/// Paths of the parameters `PowerAnalytics` derives from power packets, see
+/// `TelemetryPacket::derived`.
+pub const DERIVED_FIELD_PATHS: [&str; 6] = [
+    "power.charge_used_ah",
+    "power.soc_estimate",
+    "power.soc_error",
+    "power.eclipse",
+    "power.depth_of_discharge",
+    "power.time_to_min_battery",
+];
+
+/// A decoded parameter value.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Value {
+    Number(#[serde(deserialize_with = "nullable")] f64),
+    Text(String),
+    List(#[serde(deserialize_with = "nullable_list")] Vec<f64>), // A repeated numeric field
+    TextList(Vec<String>),                                       // A repeated string field
+}
+
+impl Value {
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::Text(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&[f64]> {
+        match self {
+            Value::List(l) => Some(l),
+            _ => None,
+        }
+    }
+
+    pub fn as_text_list(&self) -> Option<&[String]> {
+        match self {
+            Value::TextList(l) => Some(l),
+            _ => None,
+        }
+    }
+}
+
+/// Decoded parameters by path, e.g. `power.voltage`.
+pub type ParameterMap = BTreeMap<String, Value>;
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum TelemetryPayload {
     Power(PowerData),
     Thermal(ThermalData),
+    Aocs(AocsData),
     StarTracker(StarTrackerReading),
+    /// Parameters of a packet that has a definition but no typed payload.
+    Generic(ParameterMap),
     Unknown,
 }
 
+impl TelemetryPayload {
+    /// Every numeric field of the payload with its path, e.g. `power.voltage`.
+    /// AOCS also exposes derived fields such as `aocs.max_angular_rate`,
+    /// thermal payloads one field per zone, and generic payloads their
+    /// scalar numeric parameters.
+    pub fn fields(&self) -> Vec<(String, f64)> {
+        let fields: Vec<(&str, f64)> = match self {
+            TelemetryPayload::Power(p) => vec![
+                ("power.voltage", p.voltage),
+                ("power.current", p.current),
+                ("power.battery_level", p.battery_level),
+            ],
+            TelemetryPayload::Thermal(t) => vec![("thermal.temp_celsius", t.temp_celsius)],
+            TelemetryPayload::Aocs(a) => vec![
+                ("aocs.rate_x", a.angular_rate[0]),
+                ("aocs.rate_y", a.angular_rate[1]),
+                ("aocs.rate_z", a.angular_rate[2]),
+                (
+                    "aocs.max_angular_rate",
+                    a.angular_rate.iter().fold(0.0f64, |m, r| m.max(r.abs())),
+                ),
+                (
+                    "aocs.max_wheel_speed",
+                    a.wheel_speeds.iter().fold(0.0f64, |m, w| m.max(w.abs())),
+                ),
+                (
+                    "aocs.quaternion_norm_error",
+                    (1.0 - a.attitude.norm()).abs(),
+                ),
+            ],
+            TelemetryPayload::StarTracker(s) => vec![
+                (
+                    "star_tracker.right_ascension",
+                    s.coordinates.right_ascension,
+                ),
+                ("star_tracker.declination", s.coordinates.declination),
+                ("star_tracker.confidence", s.confidence),
+            ],
+            TelemetryPayload::Generic(parameters) => parameters
+                .iter()
+                .filter_map(|(path, value)| Some((path.as_str(), value.as_f64()?)))
+                .collect(),
+            TelemetryPayload::Unknown => Vec::new(),
+        };
+        let mut fields: Vec<(String, f64)> = fields
+            .into_iter()
+            .map(|(path, value)| (path.to_string(), value))
+            .collect();
+        if let TelemetryPayload::Thermal(t) = self {
+            fields.extend(t.zones.iter().map(|z| (zone_path(&z.name), z.temp_celsius)));
+        }
+        fields
+    }
+
+    /// Looks up a numeric field by path, e.g. `power.voltage`.
+    /// Returns `None` if the path does not belong to this payload.
+    pub fn field(&self, path: &str) -> Option<f64> {
+        self.fields()
+            .into_iter()
+            .find(|(name, _)| *name == path)
+            .map(|(_, value)| value)
+    }
+
+    /// Overwrites a measured field, e.g. with a calibrated value. Returns
+    /// `false` for derived fields and paths that do not belong to this payload.
+    pub fn set_field(&mut self, path: &str, value: f64) -> bool {
+        let slot = match (self, path) {
+            (TelemetryPayload::Power(p), "power.voltage") => &mut p.voltage,
+            (TelemetryPayload::Power(p), "power.current") => &mut p.current,
+            (TelemetryPayload::Power(p), "power.battery_level") => &mut p.battery_level,
+            (TelemetryPayload::Thermal(t), "thermal.temp_celsius") => &mut t.temp_celsius,
+            (TelemetryPayload::Thermal(t), path) if path.starts_with(ZONE_PREFIX) => {
+                match t
+                    .zones
+                    .iter_mut()
+                    .find(|z| path[ZONE_PREFIX.len()..] == z.name)
+                {
+                    Some(zone) => &mut zone.temp_celsius,
+                    None => return false,
+                }
+            }
+            (TelemetryPayload::Aocs(a), "aocs.rate_x") => &mut a.angular_rate[0],
+            (TelemetryPayload::Aocs(a), "aocs.rate_y") => &mut a.angular_rate[1],
+            (TelemetryPayload::Aocs(a), "aocs.rate_z") => &mut a.angular_rate[2],
+            (TelemetryPayload::StarTracker(s), "star_tracker.right_ascension") => {
+                &mut s.coordinates.right_ascension
+            }
+            (TelemetryPayload::StarTracker(s), "star_tracker.declination") => {
+                &mut s.coordinates.declination
+            }
+            (TelemetryPayload::StarTracker(s), "star_tracker.confidence") => &mut s.confidence,
+            (TelemetryPayload::Generic(parameters), path) => match parameters.get_mut(path) {
+                Some(Value::Number(n)) => n,
+                _ => return false,
+            },
+            _ => return false,
+        };
+        *slot = value;
+        true
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct TelemetryPacket {
     pub timestamp: u64, // Unix timestamp
     pub subsystem: Subsystem,
     pub payload: TelemetryPayload,
+    /// Raw sensor counts by field path, for packets sent in the raw format.
+    /// The payload holds the matching calibrated values. The raw format
+    /// sends u16 counts; u32 leaves room for wider converters.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub raw_counts: BTreeMap<String, u32>,
+    /// Parameters computed from this and earlier packets by field path,
+    /// e.g. `power.depth_of_discharge`.
+    #[serde(
+        default,
+        skip_serializing_if = "BTreeMap::is_empty",
+        deserialize_with = "nullable_map"
+    )]
+    pub derived: BTreeMap<String, f64>,
+}
+
+impl TelemetryPacket {
+    /// The payload fields followed by the derived parameters.
+    pub fn fields(&self) -> Vec<(String, f64)> {
+        let mut fields = self.payload.fields();
+        fields.extend(
+            self.derived
+                .iter()
+                .map(|(path, &value)| (path.clone(), value)),
+        );
+        fields
+    }
+
+    /// Looks up a payload field or derived parameter by path.
+    pub fn field(&self, path: &str) -> Option<f64> {
+        self.payload
+            .field(path)
+            .or_else(|| self.derived.get(path).copied())
+    }
 }
@@ -1,86 +1,432 @@
-use crate::models::{TelemetryPacket, TelemetryPayload};
-use serde::Serialize;
+use crate::models::{Subsystem, TelemetryPacket};
+use crate::rules::{Rule, RuleError, RuleSet};
+use crate::trend::TrendCheck;
+use crate::watchdog::Watchdog;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
+use std::time::Instant;
 
-#[derive(Debug, Serialize, PartialEq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
 pub enum AlertLevel {
     Info,
     Warning,
     Critical,
 }
 
-#[derive(Debug, Serialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Alert {
+    pub rule: String,
+    #[serde(default)]
+    pub field: String, // Field that tripped, e.g. `power.voltage`, or a subsystem key
     pub level: AlertLevel,
     pub message: String,
     pub timestamp: u64,
 }
 
+/// How long a breach must last before a check trips.
+///
+/// A check trips once it has seen `samples` consecutive violating samples and
+/// the violation has lasted `seconds` since the first of them. Zero disables
+/// either condition, so the default trips on the first violating sample.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Persistence {
+    #[serde(default)]
+    pub samples: u32,
+    #[serde(default)]
+    pub seconds: u64,
+}
+
+impl Persistence {
+    pub fn samples(samples: u32) -> Self {
+        Self {
+            samples,
+            seconds: 0,
+        }
+    }
+
+    pub fn seconds(seconds: u64) -> Self {
+        Self {
+            samples: 0,
+            seconds,
+        }
+    }
+
+    fn satisfied(&self, state: &CheckState, timestamp: u64) -> bool {
+        let since = state.since.unwrap_or(timestamp);
+        state.consecutive >= self.samples && timestamp.saturating_sub(since) >= self.seconds
+    }
+}
+
+/// Per-check history kept by `Monitor` between packets.
+#[derive(Debug, Clone, Default)]
+struct CheckState {
+    consecutive: u32,   // Violating samples in the current run
+    since: Option<u64>, // Timestamp of the first of them
+    active: bool,       // Tripped and not yet cleared
+    band: Option<LimitBand>,
+}
+
+impl CheckState {
+    /// Records one violating sample and returns whether the check is tripped.
+    fn violate(&mut self, persistence: &Persistence, timestamp: u64) -> bool {
+        if !self.active {
+            self.consecutive += 1;
+            self.since.get_or_insert(timestamp);
+            self.active = persistence.satisfied(self, timestamp);
+        }
+        self.active
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum LimitBand {
+    RedLow,
+    YellowLow,
+    YellowHigh,
+    RedHigh,
+}
+
+impl LimitBand {
+    pub fn level(&self) -> AlertLevel {
+        match self {
+            LimitBand::RedLow | LimitBand::RedHigh => AlertLevel::Critical,
+            LimitBand::YellowLow | LimitBand::YellowHigh => AlertLevel::Warning,
+        }
+    }
+
+    pub fn id(&self) -> &'static str {
+        match self {
+            LimitBand::RedLow => "red_low",
+            LimitBand::YellowLow => "yellow_low",
+            LimitBand::YellowHigh => "yellow_high",
+            LimitBand::RedHigh => "red_high",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            LimitBand::RedLow => "Red Low",
+            LimitBand::YellowLow => "Yellow Low",
+            LimitBand::YellowHigh => "Yellow High",
+            LimitBand::RedHigh => "Red High",
+        }
+    }
+
+    pub fn is_low(&self) -> bool {
+        matches!(self, LimitBand::RedLow | LimitBand::YellowLow)
+    }
+
+    fn is_red(&self) -> bool {
+        matches!(self, LimitBand::RedLow | LimitBand::RedHigh)
+    }
+}
+
+/// Four-level limit check on one telemetry field.
+///
+/// Readings below `red_low` or above `red_high` are Critical, readings below
+/// `yellow_low` or above `yellow_high` are Warning. Comparisons are strict and
+/// any limit may be left out. Once in a band, a reading has to move back past
+/// the limit by `hysteresis` before the band is left.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LimitSet {
+    pub name: String, // Display name, e.g. "Battery"
+    pub field: String,
+    #[serde(default)]
+    pub unit: String,
+    #[serde(default)]
+    pub red_low: Option<f64>,
+    #[serde(default)]
+    pub yellow_low: Option<f64>,
+    #[serde(default)]
+    pub yellow_high: Option<f64>,
+    #[serde(default)]
+    pub red_high: Option<f64>,
+    #[serde(default)]
+    pub hysteresis: f64,
+    #[serde(default)]
+    pub persistence: Persistence,
+}
+
+impl LimitSet {
+    pub fn new(name: &str, field: &str, unit: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            field: field.to_string(),
+            unit: unit.to_string(),
+            red_low: None,
+            yellow_low: None,
+            yellow_high: None,
+            red_high: None,
+            hysteresis: 0.0,
+            persistence: Persistence::default(),
+        }
+    }
+
+    pub fn low(mut self, red: Option<f64>, yellow: Option<f64>) -> Self {
+        self.red_low = red;
+        self.yellow_low = yellow;
+        self
+    }
+
+    pub fn high(mut self, yellow: Option<f64>, red: Option<f64>) -> Self {
+        self.yellow_high = yellow;
+        self.red_high = red;
+        self
+    }
+
+    pub fn hysteresis(mut self, hysteresis: f64) -> Self {
+        self.hysteresis = hysteresis;
+        self
+    }
+
+    pub fn persistence(mut self, persistence: Persistence) -> Self {
+        self.persistence = persistence;
+        self
+    }
+
+    /// Returns the most severe band the value falls in, with the limit it crossed.
+    pub fn classify(&self, value: f64) -> Option<(LimitBand, f64)> {
+        let below = |limit: Option<f64>| limit.filter(|l| value < *l);
+        let above = |limit: Option<f64>| limit.filter(|l| value > *l);
+
+        below(self.red_low)
+            .map(|l| (LimitBand::RedLow, l))
+            .or_else(|| above(self.red_high).map(|l| (LimitBand::RedHigh, l)))
+            .or_else(|| below(self.yellow_low).map(|l| (LimitBand::YellowLow, l)))
+            .or_else(|| above(self.yellow_high).map(|l| (LimitBand::YellowHigh, l)))
+    }
+
+    fn limit(&self, band: LimitBand) -> Option<f64> {
+        match band {
+            LimitBand::RedLow => self.red_low,
+            LimitBand::YellowLow => self.yellow_low,
+            LimitBand::YellowHigh => self.yellow_high,
+            LimitBand::RedHigh => self.red_high,
+        }
+    }
+
+    /// Like `classify`, but stays in `previous` until the value has moved back
+    /// past its limit by `hysteresis`, unless it enters a more severe band.
+    pub fn classify_from(
+        &self,
+        value: f64,
+        previous: Option<LimitBand>,
+    ) -> Option<(LimitBand, f64)> {
+        let current = self.classify(value);
+        let Some(previous) = previous else {
+            return current;
+        };
+        if current.is_some_and(|(band, _)| band.is_red() && !previous.is_red()) {
+            return current;
+        }
+
+        let limit = self.limit(previous)?;
+        let held = if previous.is_low() {
+            value < limit + self.hysteresis
+        } else {
+            value > limit - self.hysteresis
+        };
+        if held {
+            Some((previous, limit))
+        } else {
+            current
+        }
+    }
+
+    /// Checks the packet against the limits without hysteresis or persistence.
+    pub fn check(&self, packet: &TelemetryPacket) -> Option<Alert> {
+        let value = packet.field(&self.field)?;
+        let (band, limit) = self.classify(value)?;
+        Some(self.alert(packet, value, band, limit))
+    }
+
+    fn alert(&self, packet: &TelemetryPacket, value: f64, band: LimitBand, limit: f64) -> Alert {
+        let direction = if band.is_low() { "Low" } else { "High" };
+
+        Alert {
+            rule: format!("{}.{}", self.name, band.id()),
+            field: self.field.clone(),
+            level: band.level(),
+            message: format!(
+                "{} {}: {:.2}{} ({} Limit: {:.2}{})",
+                direction,
+                self.name,
+                value,
+                self.unit,
+                band.label(),
+                limit,
+                self.unit
+            ),
+            timestamp: packet.timestamp,
+        }
+    }
+}
+
+/// A configured limit on one field, e.g. for drawing on a chart.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Threshold {
+    pub label: String,
+    pub value: f64,
+    pub level: AlertLevel,
+}
+
 pub struct Monitor {
-    // Thresholds
-    pub min_battery_level: f64,
-    pub max_temp_celsius: f64,
-    pub min_star_confidence: f64,
+    pub rules: Vec<Rule>,
+    pub limits: Vec<LimitSet>,
+    pub trends: Vec<TrendCheck>,
+    pub watchdog: Watchdog,
+    state: HashMap<String, CheckState>,
+    history: HashMap<String, VecDeque<(u64, f64)>>, // Trend samples by trend name
 }
 
 impl Default for Monitor {
     fn default() -> Self {
-        Self {
-            min_battery_level: 20.0,
-            max_temp_celsius: 80.0,
-            min_star_confidence: 0.8,
-        }
+        RuleSet::default().into()
+    }
+}
+
+impl From<RuleSet> for Monitor {
+    fn from(set: RuleSet) -> Self {
+        let mut monitor = Self::new(set.rules, set.limits);
+        monitor.trends = set.trends;
+        monitor.watchdog = Watchdog::new(set.watchdog);
+        monitor
     }
 }
 
 impl Monitor {
-    pub fn new(min_battery_level: f64, max_temp_celsius: f64, min_star_confidence: f64) -> Self {
+    pub fn new(rules: Vec<Rule>, limits: Vec<LimitSet>) -> Self {
         Self {
-            min_battery_level,
-            max_temp_celsius,
-            min_star_confidence,
+            rules,
+            limits,
+            trends: Vec::new(),
+            watchdog: Watchdog::default(),
+            state: HashMap::new(),
+            history: HashMap::new(),
         }
     }
 
-    pub fn analyze(&self, packet: &TelemetryPacket) -> Option<Alert> {
-        match &packet.payload {
-            TelemetryPayload::Power(data) => {
-                if data.battery_level < self.min_battery_level {
-                    return Some(Alert {
-                        level: AlertLevel::Critical,
-                        message: format!(
-                            "Low Battery: {:.2}% (Threshold: {:.2}%)",
-                            data.battery_level, self.min_battery_level
-                        ),
-                        timestamp: packet.timestamp,
-                    });
-                }
-            }
-            TelemetryPayload::Thermal(data) => {
-                if data.temp_celsius > self.max_temp_celsius {
-                    return Some(Alert {
-                        level: AlertLevel::Warning,
-                        message: format!(
-                            "High Temperature: {:.2}C (Threshold: {:.2}C)",
-                            data.temp_celsius, self.max_temp_celsius
-                        ),
-                        timestamp: packet.timestamp,
-                    });
-                }
+    /// Builds a monitor from a TOML or JSON rule file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, RuleError> {
+        Ok(RuleSet::load(path)?.into())
+    }
+
+    /// Every rule threshold and limit configured for `field`.
+    pub fn thresholds(&self, field: &str) -> Vec<Threshold> {
+        let rules = self
+            .rules
+            .iter()
+            .filter(|r| r.field == field)
+            .map(|r| Threshold {
+                label: r.name.clone(),
+                value: r.threshold,
+                level: r.level,
+            });
+        let limits = self
+            .limits
+            .iter()
+            .filter(|l| l.field == field)
+            .flat_map(|l| {
+                [
+                    LimitBand::RedLow,
+                    LimitBand::YellowLow,
+                    LimitBand::YellowHigh,
+                    LimitBand::RedHigh,
+                ]
+                .into_iter()
+                .filter_map(move |band| {
+                    Some(Threshold {
+                        label: format!("{} {}", l.name, band.label()),
+                        value: l.limit(band)?,
+                        level: band.level(),
+                    })
+                })
+            });
+        rules.chain(limits).collect()
+    }
+
+    /// Forgets all hysteresis, persistence, trend and reporting history.
+    pub fn reset(&mut self) {
+        self.state.clear();
+        self.history.clear();
+        self.watchdog.reset();
+    }
+
+    /// Stale-telemetry alerts for subsystems that have gone silent, with the
+    /// subsystem each belongs to. Call periodically; see `Watchdog::check`.
+    pub fn check_stale(&mut self, now: Instant) -> Vec<(Subsystem, Alert)> {
+        self.watchdog.check(now)
+    }
+
+    /// Evaluates every rule, limit set and trend check and returns one alert
+    /// per check that is tripped, in that order and each in configured order,
+    /// followed by any data-gap or timestamp alert from the watchdog.
+    ///
+    /// Hysteresis, persistence and trend history are tracked across calls
+    /// using packet timestamps, so packets should be passed in time order.
+    /// The packet is taken to be received now; see `analyze_at`.
+    pub fn analyze(&mut self, packet: &TelemetryPacket) -> Vec<Alert> {
+        self.analyze_at(packet, Instant::now())
+    }
+
+    /// Like `analyze`, for a packet received at `now`. Replays of recorded
+    /// data pass a time derived from the packet timestamps, so that
+    /// `check_stale` measures silences in spacecraft time.
+    pub fn analyze_at(&mut self, packet: &TelemetryPacket, now: Instant) -> Vec<Alert> {
+        let mut alerts = Vec::new();
+
+        for rule in &self.rules {
+            let Some(value) = packet.field(&rule.field) else {
+                continue;
+            };
+            let state = self.state.entry(format!("rule:{}", rule.name)).or_default();
+            let threshold = match rule.clear_threshold {
+                Some(clear) if state.active => clear,
+                _ => rule.threshold,
+            };
+
+            if !rule.comparison.holds(value, threshold) {
+                *state = CheckState::default();
+            } else if state.violate(&rule.persistence, packet.timestamp) {
+                alerts.push(Alert {
+                    rule: rule.name.clone(),
+                    field: rule.field.clone(),
+                    level: rule.level,
+                    message: rule.render(value),
+                    timestamp: packet.timestamp,
+                });
             }
-            TelemetryPayload::StarTracker(data) => {
-                if data.confidence < self.min_star_confidence {
-                    return Some(Alert {
-                        level: AlertLevel::Info,
-                        message: format!(
-                            "Low Star Confidence: {:.2} (Threshold: {:.2})",
-                            data.confidence, self.min_star_confidence
-                        ),
-                        timestamp: packet.timestamp,
-                    });
+        }
+
+        for limits in &self.limits {
+            let Some(value) = packet.field(&limits.field) else {
+                continue;
+            };
+            let state = self
+                .state
+                .entry(format!("limit:{}", limits.name))
+                .or_default();
+            let previous = state.band.filter(|_| state.active);
+
+            match limits.classify_from(value, previous) {
+                None => *state = CheckState::default(),
+                Some((band, limit)) => {
+                    if state.violate(&limits.persistence, packet.timestamp) {
+                        state.band = Some(band);
+                        alerts.push(limits.alert(packet, value, band, limit));
+                    }
                 }
             }
-            _ => {}
         }
-        None
+
+        for trend in &self.trends {
+            let history = self.history.entry(trend.name.clone()).or_default();
+            alerts.extend(trend.update(history, packet));
+        }
+
+        alerts.extend(self.watchdog.observe(packet, now));
+
+        alerts
     }
 }
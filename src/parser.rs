@@ -1,140 +1,518 @@
+use crate::crc::{crc16_ccitt, CrcMode};
+use crate::definition::PacketDefinitions;
 use crate::models::{
-    CelestialCoordinates, PowerData, StarTrackerReading, Subsystem, TelemetryPacket,
-    TelemetryPayload, ThermalData,
+    AocsData, CelestialCoordinates, ControlMode, ParameterMap, PowerData, Quaternion,
+    StarTrackerReading, Subsystem, TelemetryPacket, TelemetryPayload, ThermalData, ThermalZone,
+    Value,
 };
+use std::collections::BTreeMap;
+use std::sync::Arc;
 use thiserror::Error;
 
+/// Size of the custom header: timestamp (8) + subsystem id (1) + payload length (2).
+pub const HEADER_LEN: usize = 11;
+
+/// Set in the subsystem ID byte when a CRC trailer follows the payload.
+pub const CRC_FLAG: u8 = 0x80;
+
+/// Size of the CRC-16 trailer, computed over the header and payload.
+pub const CRC_LEN: usize = 2;
+
+/// Set in the subsystem ID byte when the payload holds raw sensor counts.
+pub const RAW_FLAG: u8 = 0x40;
+
+/// Fields sent as big-endian u16 counts in the raw format, in wire order.
+pub fn raw_fields(subsystem_id: u8) -> Option<&'static [&'static str]> {
+    match subsystem_id {
+        0 => Some(&["power.voltage", "power.current", "power.battery_level"]),
+        1 => Some(&["thermal.temp_celsius"]),
+        _ => None,
+    }
+}
+
 #[derive(Error, Debug)]
 pub enum ParserError {
     #[error("Buffer too short")]
     BufferTooShort,
     #[error("Invalid subsystem ID: {0}")]
     InvalidSubsystem(u8),
+    #[error("Unknown APID: {0}")]
+    UnknownApid(u16),
+    #[error("Length mismatch: header declares {declared} payload bytes, found {actual}")]
+    LengthMismatch { declared: usize, actual: usize },
+    /// The header's length cannot be right for its subsystem ID.
+    #[error("Invalid length {declared} for subsystem ID {id}")]
+    InvalidLength { id: u8, declared: usize },
+    /// The coarse time plus the epoch offset does not fit a Unix timestamp.
+    #[error("Timestamp overflow: coarse time {coarse} + epoch offset {epoch_offset}")]
+    TimestampOverflow { coarse: u64, epoch_offset: u64 },
+    #[error("Invalid control mode: {0}")]
+    InvalidControlMode(u8),
+    /// `expected` is the CRC carried in the trailer, `actual` the CRC of the
+    /// bytes received.
+    #[error("Checksum mismatch: expected {expected:#06x}, computed {actual:#06x}")]
+    ChecksumMismatch { expected: u16, actual: u16 },
+    #[error("Missing CRC trailer")]
+    MissingChecksum,
+    #[error("Raw format not supported for subsystem ID: {0}")]
+    RawNotSupported(u8),
+    #[error("Missing or mistyped parameter: {0}")]
+    MissingParameter(String),
     #[error("UTF-8 error")]
     Utf8Error(#[from] std::string::FromUtf8Error),
     #[error("Unknown error")]
     Unknown,
 }
 
+impl ParserError {
+    /// True for frames that failed the integrity check.
+    pub fn is_corruption(&self) -> bool {
+        matches!(
+            self,
+            ParserError::ChecksumMismatch { .. } | ParserError::MissingChecksum
+        )
+    }
+}
+
+/// Reads the custom header format. Payloads are decoded with the built-in
+/// `PacketDefinitions` unless the `_using` variants are given others.
 pub struct Parser;
 
 impl Parser {
+    /// Parses exactly one packet, verifying a CRC trailer if present. The
+    /// buffer must end where the header's length field says the frame ends.
     pub fn parse(data: &[u8]) -> Result<TelemetryPacket, ParserError> {
-        let mut offset = 0;
+        Self::parse_with(data, CrcMode::default())
+    }
+
+    /// Like `parse`, with the given CRC handling.
+    pub fn parse_with(data: &[u8], crc: CrcMode) -> Result<TelemetryPacket, ParserError> {
+        Self::parse_using(data, crc, &PacketDefinitions::built_in())
+    }
 
-        if data.len() < 11 {
+    /// Like `parse_with`, decoding payloads with `definitions`.
+    pub fn parse_using(
+        data: &[u8],
+        crc: CrcMode,
+        definitions: &PacketDefinitions,
+    ) -> Result<TelemetryPacket, ParserError> {
+        let (packet, used) = Self::parse_prefix_using(data, crc, definitions)?;
+        if used != data.len() {
+            return Err(ParserError::LengthMismatch {
+                declared: used - HEADER_LEN,
+                actual: data.len() - HEADER_LEN,
+            });
+        }
+        Ok(packet)
+    }
+
+    /// Parses the packet at the start of `data` and returns it together with
+    /// the number of bytes it occupied. Anything after that is left untouched.
+    pub fn parse_prefix(data: &[u8]) -> Result<(TelemetryPacket, usize), ParserError> {
+        Self::parse_prefix_with(data, CrcMode::default())
+    }
+
+    /// Like `parse_prefix`, with the given CRC handling.
+    pub fn parse_prefix_with(
+        data: &[u8],
+        crc: CrcMode,
+    ) -> Result<(TelemetryPacket, usize), ParserError> {
+        Self::parse_prefix_using(data, crc, &PacketDefinitions::built_in())
+    }
+
+    /// Like `parse_prefix_with`, decoding payloads with `definitions`.
+    pub fn parse_prefix_using(
+        data: &[u8],
+        crc: CrcMode,
+        definitions: &PacketDefinitions,
+    ) -> Result<(TelemetryPacket, usize), ParserError> {
+        let (timestamp, id, declared) = Self::parse_header(data)?;
+        let has_crc = crc != CrcMode::Absent && id & CRC_FLAG != 0;
+        if crc == CrcMode::Required && !has_crc {
+            return Err(ParserError::MissingChecksum);
+        }
+        let id = if has_crc { id & !CRC_FLAG } else { id };
+        let raw = id & RAW_FLAG != 0;
+        let subsystem_id = id & !RAW_FLAG;
+
+        let body_len = HEADER_LEN + declared;
+        let frame_len = Self::frame_len(data, crc)?;
+        if data.len() < frame_len {
+            return Err(ParserError::BufferTooShort);
+        }
+        if has_crc {
+            let trailer = [data[body_len], data[body_len + 1]];
+            let expected = u16::from_be_bytes(trailer);
+            let actual = crc16_ccitt(&data[..body_len]);
+            if expected != actual {
+                return Err(ParserError::ChecksumMismatch { expected, actual });
+            }
+        }
+        let payload_bytes = &data[HEADER_LEN..body_len];
+
+        let (subsystem, payload, raw_counts, actual) = if raw {
+            Self::parse_raw_payload(subsystem_id, payload_bytes)?
+        } else {
+            let (subsystem, payload, actual) =
+                Self::parse_payload(subsystem_id, payload_bytes, definitions)?;
+            (subsystem, payload, BTreeMap::new(), actual)
+        };
+        if actual != declared {
+            return Err(ParserError::LengthMismatch { declared, actual });
+        }
+
+        Ok((
+            TelemetryPacket {
+                timestamp,
+                subsystem,
+                payload,
+                raw_counts,
+                derived: Default::default(),
+            },
+            frame_len,
+        ))
+    }
+
+    /// Decodes a raw-count payload. The payload fields hold the counts as-is
+    /// until `CalibrationSet::apply` converts them to engineering units.
+    fn parse_raw_payload(
+        subsystem_id: u8,
+        data: &[u8],
+    ) -> Result<(Subsystem, TelemetryPayload, BTreeMap<String, u32>, usize), ParserError> {
+        let fields = raw_fields(subsystem_id).ok_or(ParserError::RawNotSupported(subsystem_id))?;
+        let len = fields.len() * 2;
+        if data.len() < len {
+            return Err(ParserError::BufferTooShort);
+        }
+
+        let (subsystem, mut payload) = match subsystem_id {
+            0 => (
+                Subsystem::Power,
+                TelemetryPayload::Power(PowerData {
+                    voltage: 0.0,
+                    current: 0.0,
+                    battery_level: 0.0,
+                }),
+            ),
+            _ => (
+                Subsystem::Thermal,
+                TelemetryPayload::Thermal(ThermalData {
+                    temp_celsius: 0.0,
+                    zones: Vec::new(),
+                }),
+            ),
+        };
+        let mut counts = BTreeMap::new();
+        for (field, bytes) in fields.iter().zip(data.chunks_exact(2)) {
+            let count = u16::from_be_bytes([bytes[0], bytes[1]]);
+            payload.set_field(field, count as f64);
+            counts.insert(field.to_string(), count as u32);
+        }
+        Ok((subsystem, payload, counts, len))
+    }
+
+    /// Length of the frame starting at `data`, including a CRC trailer if
+    /// the header announces one and `crc` is not `Absent`.
+    pub fn frame_len(data: &[u8], crc: CrcMode) -> Result<usize, ParserError> {
+        let (_, id, declared) = Self::parse_header(data)?;
+        let trailer = if crc != CrcMode::Absent && id & CRC_FLAG != 0 {
+            CRC_LEN
+        } else {
+            0
+        };
+        Ok(HEADER_LEN + declared + trailer)
+    }
+
+    /// Reads the header and returns (timestamp, subsystem id, payload length).
+    /// The subsystem id still carries `CRC_FLAG` and `RAW_FLAG` if set.
+    pub fn parse_header(data: &[u8]) -> Result<(u64, u8, usize), ParserError> {
+        if data.len() < HEADER_LEN {
             return Err(ParserError::BufferTooShort);
         }
 
         // Timestamp (8 bytes)
-        let timestamp_bytes: [u8; 8] = data[offset..offset + 8]
+        let timestamp_bytes: [u8; 8] = data[0..8]
             .try_into()
             .map_err(|_| ParserError::BufferTooShort)?;
         let timestamp = u64::from_be_bytes(timestamp_bytes);
-        offset += 8;
 
         // Subsystem ID (1 byte)
-        let subsystem_id = data[offset];
-        offset += 1;
+        let subsystem_id = data[8];
 
         // Payload Length (2 bytes)
-        let _len_bytes: [u8; 2] = data[offset..offset + 2]
+        let len_bytes: [u8; 2] = data[9..11]
             .try_into()
             .map_err(|_| ParserError::BufferTooShort)?;
-        offset += 2;
+        let len = u16::from_be_bytes(len_bytes) as usize;
 
-        let (subsystem, payload) = match subsystem_id {
-            0 => {
-                // Power: 3 * 8 bytes = 24 bytes
-                if data.len() < offset + 24 {
-                    return Err(ParserError::BufferTooShort);
-                }
-                
-                let voltage_bytes = data[offset..offset + 8].try_into().map_err(|_| ParserError::BufferTooShort)?;
-                let voltage = f64::from_be_bytes(voltage_bytes);
-                offset += 8;
+        Ok((timestamp, subsystem_id, len))
+    }
 
-                let current_bytes = data[offset..offset + 8].try_into().map_err(|_| ParserError::BufferTooShort)?;
-                let current = f64::from_be_bytes(current_bytes);
-                offset += 8;
+    /// Decodes a payload with `definitions` and returns the number of bytes
+    /// its layout requires. A payload cut short by the declared length is
+    /// reported as a mismatch.
+    pub(crate) fn parse_payload(
+        subsystem_id: u8,
+        data: &[u8],
+        definitions: &PacketDefinitions,
+    ) -> Result<(Subsystem, TelemetryPayload, usize), ParserError> {
+        let definition = definitions
+            .get(subsystem_id)
+            .ok_or(ParserError::InvalidSubsystem(subsystem_id))?;
+        let (parameters, used) = definition.decode(data)?;
+        let (subsystem, payload) = Self::typed_payload(subsystem_id, parameters)?;
+        Ok((subsystem, payload, used))
+    }
 
-                let battery_bytes = data[offset..offset + 8].try_into().map_err(|_| ParserError::BufferTooShort)?;
-                let battery_level = f64::from_be_bytes(battery_bytes);
-                // offset += 8;
+    /// Builds the typed payload for the built-in subsystem IDs from decoded
+    /// parameters. Other IDs keep the parameters as a generic payload.
+    pub fn typed_payload(
+        subsystem_id: u8,
+        parameters: ParameterMap,
+    ) -> Result<(Subsystem, TelemetryPayload), ParserError> {
+        let get = |path: &str| {
+            parameters
+                .get(path)
+                .ok_or_else(|| ParserError::MissingParameter(path.to_string()))
+        };
+        let number = |path: &str| {
+            get(path)?
+                .as_f64()
+                .ok_or_else(|| ParserError::MissingParameter(path.to_string()))
+        };
 
+        let result = match subsystem_id {
+            0 => (
+                Subsystem::Power,
+                TelemetryPayload::Power(PowerData {
+                    voltage: number("power.voltage")?,
+                    current: number("power.current")?,
+                    battery_level: number("power.battery_level")?,
+                }),
+            ),
+            1 => {
+                let names = parameters
+                    .get("thermal.zone_names")
+                    .and_then(Value::as_text_list)
+                    .unwrap_or_default();
+                let temps = parameters
+                    .get("thermal.zone_temps")
+                    .and_then(Value::as_list)
+                    .unwrap_or_default();
+                let zones = names
+                    .iter()
+                    .zip(temps)
+                    .map(|(name, temp)| ThermalZone {
+                        name: name.clone(),
+                        temp_celsius: *temp,
+                    })
+                    .collect();
                 (
-                    Subsystem::Power,
-                    TelemetryPayload::Power(PowerData {
-                        voltage,
-                        current,
-                        battery_level,
+                    Subsystem::Thermal,
+                    TelemetryPayload::Thermal(ThermalData {
+                        temp_celsius: number("thermal.temp_celsius")?,
+                        zones,
                     }),
                 )
             }
-            1 => {
-                // Thermal: 8 bytes
-                if data.len() < offset + 8 {
-                    return Err(ParserError::BufferTooShort);
-                }
-                let temp_bytes = data[offset..offset + 8].try_into().map_err(|_| ParserError::BufferTooShort)?;
-                let temp_celsius = f64::from_be_bytes(temp_bytes);
-                // offset += 8;
-
+            2 => {
+                let mode_code = number("aocs.control_mode")? as u8;
+                let control_mode = ControlMode::from_code(mode_code)
+                    .ok_or(ParserError::InvalidControlMode(mode_code))?;
+                let wheel_speeds = get("aocs.wheel_speeds")?
+                    .as_list()
+                    .ok_or_else(|| ParserError::MissingParameter("aocs.wheel_speeds".into()))?
+                    .to_vec();
                 (
-                    Subsystem::Thermal,
-                    TelemetryPayload::Thermal(ThermalData { temp_celsius }),
+                    Subsystem::Aocs,
+                    TelemetryPayload::Aocs(AocsData {
+                        attitude: Quaternion {
+                            w: number("aocs.attitude_w")?,
+                            x: number("aocs.attitude_x")?,
+                            y: number("aocs.attitude_y")?,
+                            z: number("aocs.attitude_z")?,
+                        },
+                        angular_rate: [
+                            number("aocs.rate_x")?,
+                            number("aocs.rate_y")?,
+                            number("aocs.rate_z")?,
+                        ],
+                        wheel_speeds,
+                        control_mode,
+                    }),
                 )
             }
             3 => {
-                // StarTracker: RA(8) + Dec(8) + Conf(8) + ID_Len(1) + ID(N)
-                if data.len() < offset + 25 {
-                    return Err(ParserError::BufferTooShort);
-                }
-                
-                let ra_bytes = data[offset..offset + 8].try_into().map_err(|_| ParserError::BufferTooShort)?;
-                let ra = f64::from_be_bytes(ra_bytes);
-                offset += 8;
-                
-                let dec_bytes = data[offset..offset + 8].try_into().map_err(|_| ParserError::BufferTooShort)?;
-                let dec = f64::from_be_bytes(dec_bytes);
-                offset += 8;
-
-                let conf_bytes = data[offset..offset + 8].try_into().map_err(|_| ParserError::BufferTooShort)?;
-                let confidence = f64::from_be_bytes(conf_bytes);
-                offset += 8;
-                
-                let id_len = data[offset] as usize;
-                offset += 1;
-
-                if data.len() < offset + id_len {
-                    return Err(ParserError::BufferTooShort);
-                }
-                let id_bytes = &data[offset..offset + id_len];
-                let target_id = if id_len > 0 {
-                    Some(String::from_utf8(id_bytes.to_vec())?)
-                } else {
-                    None
-                };
-
+                let target_id = get("star_tracker.target_id")?
+                    .as_str()
+                    .filter(|id| !id.is_empty())
+                    .map(str::to_string);
                 (
                     Subsystem::StarTracker,
                     TelemetryPayload::StarTracker(StarTrackerReading {
                         target_id,
                         coordinates: CelestialCoordinates {
-                            right_ascension: ra,
-                            declination: dec,
+                            right_ascension: number("star_tracker.right_ascension")?,
+                            declination: number("star_tracker.declination")?,
                         },
-                        confidence,
+                        confidence: number("star_tracker.confidence")?,
                     }),
                 )
             }
-            _ => return Err(ParserError::InvalidSubsystem(subsystem_id)),
+            id => (Subsystem::Other(id), TelemetryPayload::Generic(parameters)),
         };
 
-        Ok(TelemetryPacket {
-            timestamp,
-            subsystem,
-            payload,
-        })
+        Ok(result)
+    }
+}
+
+/// Splits a growing byte stream into packets using the header length field.
+///
+/// Bytes are appended with [`PacketFramer::push`] and complete packets are
+/// pulled out with [`PacketFramer::next_packet`]. A frame that fails to parse
+/// is still dropped by its declared length, so one bad packet does not stall
+/// the rest of the stream.
+#[derive(Debug)]
+pub struct PacketFramer {
+    buffer: Vec<u8>,
+    /// Start of the unconsumed bytes in `buffer`.
+    start: usize,
+    /// Set after a bad header until a plausible one is found.
+    resyncing: bool,
+    crc: CrcMode,
+    definitions: Arc<PacketDefinitions>,
+}
+
+impl Default for PacketFramer {
+    fn default() -> Self {
+        Self::with_crc(CrcMode::default())
+    }
+}
+
+impl PacketFramer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_crc(crc: CrcMode) -> Self {
+        Self {
+            buffer: Vec::new(),
+            start: 0,
+            resyncing: false,
+            crc,
+            definitions: PacketDefinitions::built_in(),
+        }
+    }
+
+    /// Decodes payloads with `definitions` instead of the built-in layouts.
+    pub fn definitions(mut self, definitions: Arc<PacketDefinitions>) -> Self {
+        self.definitions = definitions;
+        self
+    }
+
+    pub fn push(&mut self, bytes: &[u8]) {
+        // Consumed bytes are dropped once they make up half the buffer, so
+        // each byte is moved a bounded number of times
+        if self.start > 0 && self.start * 2 >= self.buffer.len() {
+            self.buffer.drain(..self.start);
+            self.start = 0;
+        }
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    /// Number of buffered bytes not yet consumed by a packet.
+    pub fn pending(&self) -> usize {
+        self.buffer.len() - self.start
+    }
+
+    /// Returns the next packet and the number of bytes it used, or `None`
+    /// if the buffer does not yet hold a complete frame.
+    ///
+    /// A header whose length cannot fit its subsystem ID is reported once,
+    /// then the framer resyncs on the next frame that parses. Frames that
+    /// fail to parse otherwise are skipped whole.
+    pub fn next_packet(&mut self) -> Option<Result<(TelemetryPacket, usize), ParserError>> {
+        if self.resyncing {
+            return self.resync().map(Ok);
+        }
+        let data = &self.buffer[self.start..];
+        let (_, id, declared) = Parser::parse_header(data).ok()?;
+        let id = self.without_crc_flag(id);
+        if !self.plausible(id, declared) {
+            self.start += 1;
+            self.resyncing = true;
+            return Some(Err(ParserError::InvalidLength { id, declared }));
+        }
+
+        let frame_len = Parser::frame_len(data, self.crc).ok()?;
+        if data.len() < frame_len {
+            return None;
+        }
+        let result = Parser::parse_using(&data[..frame_len], self.crc, &self.definitions)
+            .map(|packet| (packet, frame_len));
+        self.start += frame_len;
+        Some(result)
+    }
+
+    /// Finds the first offset with a plausible header whose frame parses.
+    /// A header found by hunting may be a false match waiting for bytes
+    /// that never come, so later offsets are tried as well.
+    fn resync(&mut self) -> Option<(TelemetryPacket, usize)> {
+        let candidate = |offset: usize| {
+            let data = &self.buffer[offset..];
+            let (_, id, declared) = Parser::parse_header(data).ok()?;
+            if !self.plausible(self.without_crc_flag(id), declared) {
+                return None;
+            }
+            Parser::parse_prefix_using(data, self.crc, &self.definitions).ok()
+        };
+        match (self.start..self.buffer.len()).find_map(|offset| Some((offset, candidate(offset)?)))
+        {
+            Some((offset, (packet, frame_len))) => {
+                self.start = offset + frame_len;
+                self.resyncing = false;
+                Some((packet, frame_len))
+            }
+            None => {
+                // Bytes before the first plausible header cannot start a frame
+                while let Ok((_, id, declared)) = Parser::parse_header(&self.buffer[self.start..]) {
+                    if self.plausible(self.without_crc_flag(id), declared) {
+                        break;
+                    }
+                    self.start += 1;
+                }
+                None
+            }
+        }
+    }
+
+    fn without_crc_flag(&self, id: u8) -> u8 {
+        match self.crc {
+            CrcMode::Absent => id,
+            _ => id & !CRC_FLAG,
+        }
+    }
+
+    /// Whether `declared` payload bytes fit subsystem ID byte `id`, without
+    /// the CRC flag. Unknown IDs are left to the parser, which skips their
+    /// frame since their length may well be right, except while resyncing.
+    fn plausible(&self, id: u8, declared: usize) -> bool {
+        if id & RAW_FLAG != 0 {
+            if let Some(fields) = raw_fields(id & !RAW_FLAG) {
+                return declared == fields.len() * 2;
+            }
+        }
+        match self.definitions.get(id) {
+            Some(definition) => (definition.min_len()..=definition.max_len()).contains(&declared),
+            None => !self.resyncing,
+        }
+    }
+}
+
+impl Iterator for PacketFramer {
+    type Item = Result<(TelemetryPacket, usize), ParserError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_packet()
     }
 }
@@ -0,0 +1,607 @@
+use crate::alerts::{AlertManager, AlertRecord};
+use crate::calibration::CalibrationSet;
+use crate::crc::CrcMode;
+use crate::definition::PacketDefinitions;
+use crate::history::TelemetryHistory;
+use crate::models::{Subsystem, TelemetryPacket};
+use crate::monitor::{Alert, Monitor};
+use crate::parser::Parser;
+use crate::power::{BatteryConfig, Orbit, PowerAnalytics};
+use crate::source::{SourceEvent, SourceStatus, TelemetrySource, POLL_INTERVAL};
+use crate::stats::TelemetryStats;
+use crate::status::StatusBoard;
+use std::collections::VecDeque;
+use std::sync::mpsc::{
+    self, Receiver, RecvTimeoutError, Sender, SyncSender, TryIter, TrySendError,
+};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// Most replay frames processed in one pass, so commands are still handled
+/// promptly when replaying with a zero interval.
+const REPLAY_BATCH: usize = 1000;
+
+/// Events queued for the dashboard before the pipeline starts dropping log
+/// lines and packets; state events are coalesced instead.
+pub const EVENT_CAPACITY: usize = 4096;
+
+/// Log lines kept by `PipelineSnapshot`.
+pub const MAX_LOG_LINES: usize = 1000;
+
+/// Receives every parsed packet together with the alerts it raised.
+pub trait PacketSink: Send {
+    fn process(&mut self, packet: &TelemetryPacket, alerts: &[Alert]);
+
+    /// Receives alerts raised without a packet, such as stale telemetry.
+    fn alert(&mut self, _subsystem: &Subsystem, _alert: &Alert) {}
+}
+
+impl PacketSink for TelemetryStats {
+    fn process(&mut self, packet: &TelemetryPacket, _alerts: &[Alert]) {
+        self.add(packet);
+    }
+}
+
+/// Lets another thread read a sink while the pipeline writes to it.
+impl<S: PacketSink> PacketSink for Arc<Mutex<S>> {
+    fn process(&mut self, packet: &TelemetryPacket, alerts: &[Alert]) {
+        if let Ok(mut sink) = self.lock() {
+            sink.process(packet, alerts);
+        }
+    }
+
+    fn alert(&mut self, subsystem: &Subsystem, alert: &Alert) {
+        if let Ok(mut sink) = self.lock() {
+            sink.alert(subsystem, alert);
+        }
+    }
+}
+
+pub enum PipelineCommand {
+    /// Replaces the replay queue and starts it from the first frame.
+    Replay {
+        frames: Vec<Vec<u8>>,
+        interval: Duration,
+    },
+    /// Changes the delay between replayed frames.
+    SetInterval(Duration),
+    Pause,
+    Resume,
+    /// Rewinds the replay and forgets monitor state and alerts.
+    Restart,
+    /// Processes one encoded packet immediately.
+    Inject(Vec<u8>),
+    /// Replaces the monitor, e.g. when switching profiles. Open alerts are
+    /// kept and re-evaluated by the new monitor's checks.
+    SetMonitor {
+        profile: String,
+        monitor: Box<Monitor>,
+    },
+    /// Replaces the calibrations applied to packets sent as raw counts and
+    /// to calibrated instrument fields; `Config::calibration` includes
+    /// those of the packet definitions.
+    SetCalibration(CalibrationSet),
+    /// Replaces the battery model and restarts the power analytics.
+    SetBattery(BatteryConfig),
+    /// Sets how replayed and injected frames are checked for a CRC trailer.
+    SetCrc(CrcMode),
+    /// Sets the packet layouts replayed and injected frames are decoded with.
+    SetDefinitions(Arc<PacketDefinitions>),
+    /// Replaces the live source with `source` and starts it.
+    StartSource(Box<dyn TelemetrySource>),
+    StopSource,
+    Acknowledge(u64),
+    Shutdown,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PipelineEvent {
+    Log(String),
+    Packet {
+        packet: TelemetryPacket,
+        alerts: Vec<Alert>,
+    },
+    /// Open alerts, sent whenever they change.
+    Alerts(Vec<AlertRecord>),
+    Replay {
+        sent: usize,
+        total: usize,
+        paused: bool,
+    },
+    Source(Option<LiveSource>),
+    /// Total frames rejected by the CRC check so far.
+    Corrupted(usize),
+    /// Recent orbits from the power analytics, sent after every power packet.
+    Orbits(Vec<Orbit>),
+}
+
+/// The running live source as seen by the dashboard.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LiveSource {
+    pub name: String,
+    pub status: Option<SourceStatus>,
+    pub packets: usize,
+    pub corrupted: usize,
+}
+
+/// Dashboard state rebuilt from `PipelineEvent`s.
+#[derive(Debug, Clone, Default)]
+pub struct PipelineSnapshot {
+    pub logs: VecDeque<String>,
+    pub alerts: Vec<AlertRecord>,
+    pub last_packet: Option<TelemetryPacket>,
+    pub history: TelemetryHistory,
+    pub status: StatusBoard,
+    pub replay_sent: usize,
+    pub replay_total: usize,
+    pub paused: bool,
+    pub source: Option<LiveSource>,
+    pub corrupted: usize,
+    pub orbits: Vec<Orbit>,
+}
+
+impl PipelineSnapshot {
+    pub fn apply(&mut self, event: PipelineEvent) {
+        match event {
+            PipelineEvent::Log(line) => {
+                if self.logs.len() == MAX_LOG_LINES {
+                    self.logs.pop_front();
+                }
+                self.logs.push_back(line);
+            }
+            PipelineEvent::Packet { packet, alerts } => {
+                self.history.add(&packet, &alerts);
+                self.status.update(&packet, Instant::now());
+                self.last_packet = Some(packet);
+            }
+            PipelineEvent::Alerts(alerts) => self.alerts = alerts,
+            PipelineEvent::Replay {
+                sent,
+                total,
+                paused,
+            } => {
+                self.replay_sent = sent;
+                self.replay_total = total;
+                self.paused = paused;
+            }
+            PipelineEvent::Source(source) => self.source = source,
+            PipelineEvent::Corrupted(total) => self.corrupted = total,
+            PipelineEvent::Orbits(orbits) => self.orbits = orbits,
+        }
+    }
+}
+
+/// Parses, monitors and forwards packets on a background thread.
+///
+/// Frames come from a paced replay queue, manual injection and an optional
+/// live source. Each parsed packet goes through the `Monitor` and the
+/// `AlertManager`, then to every sink; results are reported as
+/// `PipelineEvent`s.
+pub struct Pipeline {
+    commands: Sender<PipelineCommand>,
+    events: Receiver<PipelineEvent>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Pipeline {
+    pub fn spawn(monitor: Monitor, sinks: Vec<Box<dyn PacketSink>>) -> Self {
+        let (commands, command_rx) = mpsc::channel();
+        let (event_tx, events) = mpsc::sync_channel(EVENT_CAPACITY);
+        let mut worker = Worker {
+            monitor,
+            calibration: CalibrationSet::default(),
+            power: PowerAnalytics::default(),
+            alerts: AlertManager::new(),
+            published: Vec::new(),
+            sinks,
+            events: event_tx,
+            pending: Vec::new(),
+            dropped: 0,
+            crc: CrcMode::default(),
+            definitions: PacketDefinitions::built_in(),
+            replay: Vec::new(),
+            replay_index: 0,
+            interval: Duration::ZERO,
+            paused: false,
+            next_due: Instant::now(),
+            source: None,
+            source_events: None,
+            live: None,
+            corrupted: 0,
+        };
+        let handle = std::thread::spawn(move || worker.run(command_rx));
+
+        Self {
+            commands,
+            events,
+            handle: Some(handle),
+        }
+    }
+
+    /// Sends a command; ignored if the pipeline has shut down.
+    pub fn send(&self, command: PipelineCommand) {
+        let _ = self.commands.send(command);
+    }
+
+    /// Events produced since the last call, without blocking.
+    pub fn try_events(&self) -> TryIter<'_, PipelineEvent> {
+        self.events.try_iter()
+    }
+
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<PipelineEvent> {
+        self.events.recv_timeout(timeout).ok()
+    }
+
+    /// Stops the live source and waits for the pipeline thread to exit.
+    pub fn shutdown(&mut self) {
+        self.send(PipelineCommand::Shutdown);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for Pipeline {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+struct Worker {
+    monitor: Monitor,
+    calibration: CalibrationSet,
+    power: PowerAnalytics,
+    alerts: AlertManager,
+    published: Vec<AlertRecord>, // Open alerts as last sent
+    sinks: Vec<Box<dyn PacketSink>>,
+    events: SyncSender<PipelineEvent>,
+    pending: Vec<PipelineEvent>, // Latest state events not yet queued
+    dropped: usize,              // Log and packet events dropped since the last report
+    crc: CrcMode,
+    definitions: Arc<PacketDefinitions>,
+
+    replay: Vec<Vec<u8>>,
+    replay_index: usize,
+    interval: Duration,
+    paused: bool,
+    next_due: Instant,
+
+    source: Option<Box<dyn TelemetrySource>>,
+    source_events: Option<Receiver<SourceEvent>>,
+    live: Option<LiveSource>,
+
+    corrupted: usize, // Frames rejected by the CRC check
+}
+
+impl Worker {
+    fn run(&mut self, commands: Receiver<PipelineCommand>) {
+        loop {
+            let timeout = if self.replay_pending() {
+                self.next_due
+                    .saturating_duration_since(Instant::now())
+                    .min(POLL_INTERVAL)
+            } else {
+                POLL_INTERVAL
+            };
+
+            let command = match commands.recv_timeout(timeout) {
+                Ok(command) => Some(command),
+                Err(RecvTimeoutError::Timeout) => None,
+                Err(RecvTimeoutError::Disconnected) => break,
+            };
+            let mut shutdown = false;
+            for command in command.into_iter().chain(commands.try_iter()) {
+                shutdown |= !self.handle_command(command);
+            }
+            if shutdown {
+                break;
+            }
+
+            self.drain_source();
+            self.step_replay();
+            self.check_stale();
+            self.flush();
+        }
+        self.stop_source();
+    }
+
+    /// Returns `false` on `Shutdown`.
+    fn handle_command(&mut self, command: PipelineCommand) -> bool {
+        match command {
+            PipelineCommand::Replay { frames, interval } => {
+                self.replay = frames;
+                self.replay_index = 0;
+                self.interval = interval;
+                self.next_due = Instant::now();
+                self.publish_replay();
+            }
+            PipelineCommand::SetInterval(interval) => {
+                self.next_due = self.next_due.min(Instant::now() + interval);
+                self.interval = interval;
+            }
+            PipelineCommand::Pause => {
+                self.paused = true;
+                self.publish_replay();
+            }
+            PipelineCommand::Resume => {
+                self.paused = false;
+                self.next_due = Instant::now();
+                self.publish_replay();
+            }
+            PipelineCommand::Restart => {
+                self.replay_index = 0;
+                self.paused = false;
+                self.next_due = Instant::now();
+                self.monitor.reset();
+                self.power.reset();
+                self.alerts.clear();
+                self.corrupted = 0;
+                self.send(PipelineEvent::Corrupted(0));
+                self.publish_alerts();
+                self.publish_replay();
+            }
+            PipelineCommand::Inject(frame) => {
+                self.log("Processing manual packet...".to_string());
+                self.process_frame(&frame);
+            }
+            PipelineCommand::SetMonitor { profile, monitor } => {
+                self.monitor = *monitor;
+                let definitions = Arc::clone(&self.definitions);
+                self.monitor.watchdog.set_definitions(definitions);
+                self.log(format!("Switched to monitor profile '{}'", profile));
+            }
+            PipelineCommand::SetCalibration(calibration) => self.calibration = calibration,
+            PipelineCommand::SetBattery(battery) => self.power = PowerAnalytics::new(battery),
+            PipelineCommand::SetCrc(crc) => self.crc = crc,
+            PipelineCommand::SetDefinitions(definitions) => {
+                self.monitor
+                    .watchdog
+                    .set_definitions(Arc::clone(&definitions));
+                self.definitions = definitions;
+            }
+            PipelineCommand::StartSource(source) => self.start_source(source),
+            PipelineCommand::StopSource => self.stop_source(),
+            PipelineCommand::Acknowledge(id) => {
+                self.alerts.acknowledge(id);
+                self.publish_alerts();
+            }
+            PipelineCommand::Shutdown => return false,
+        }
+        true
+    }
+
+    fn replay_pending(&self) -> bool {
+        !self.paused && self.replay_index < self.replay.len()
+    }
+
+    fn step_replay(&mut self) {
+        let mut processed = 0;
+        while self.replay_pending() && processed < REPLAY_BATCH && Instant::now() >= self.next_due {
+            let frame = self.replay[self.replay_index].clone();
+            self.replay_index += 1;
+            self.log(format!("Processing packet {}...", self.replay_index));
+            self.process_frame(&frame);
+
+            self.next_due += self.interval;
+            processed += 1;
+        }
+        if processed > 0 {
+            // Do not try to catch up after a stall.
+            self.next_due = self.next_due.max(Instant::now());
+            self.publish_replay();
+        }
+    }
+
+    fn process_frame(&mut self, frame: &[u8]) {
+        match Parser::parse_using(frame, self.crc, &self.definitions) {
+            Ok(packet) => self.process_packet(packet),
+            Err(e) if e.is_corruption() => self.count_corrupted(format!("Corrupted packet: {}", e)),
+            Err(e) => self.log(format!("Error parsing packet: {}", e)),
+        }
+    }
+
+    fn count_corrupted(&mut self, line: String) {
+        self.corrupted += 1;
+        self.log(line);
+        self.send(PipelineEvent::Corrupted(self.corrupted));
+    }
+
+    fn process_packet(&mut self, mut packet: TelemetryPacket) {
+        self.calibration.apply(&mut packet);
+        self.log(format!(
+            "Parsed: {:?} - {:?}",
+            packet.subsystem, packet.payload
+        ));
+        if packet.subsystem == Subsystem::Power {
+            self.update_power(&mut packet);
+        }
+
+        let alerts = self.monitor.analyze(&packet);
+        for alert in &alerts {
+            self.log(format!(
+                "*** ALERT: [{:?}] {} ({}) ***",
+                alert.level, alert.message, alert.rule
+            ));
+        }
+        self.alerts.update(&packet, &alerts);
+        for sink in &mut self.sinks {
+            sink.process(&packet, &alerts);
+        }
+
+        self.send(PipelineEvent::Packet { packet, alerts });
+        self.publish_alerts();
+    }
+
+    fn update_power(&mut self, packet: &mut TelemetryPacket) {
+        let eclipse = self.power.eclipse();
+        self.power.apply(packet);
+        match (eclipse, self.power.eclipse()) {
+            (Some(false) | None, Some(true)) => {
+                self.log(format!("Entered eclipse at {}", packet.timestamp))
+            }
+            (Some(true), Some(false)) => {
+                self.log(format!("Entered sunlight at {}", packet.timestamp))
+            }
+            _ => {}
+        }
+        let orbits = self.power.orbits().iter().cloned().collect();
+        self.send(PipelineEvent::Orbits(orbits));
+    }
+
+    fn check_stale(&mut self) {
+        for (subsystem, alert) in self.monitor.check_stale(Instant::now()) {
+            self.log(format!(
+                "*** ALERT: [{:?}] {} ({}) ***",
+                alert.level, alert.message, alert.rule
+            ));
+            self.alerts.raise(&subsystem, std::slice::from_ref(&alert));
+            for sink in &mut self.sinks {
+                sink.alert(&subsystem, &alert);
+            }
+            self.publish_alerts();
+        }
+    }
+
+    fn start_source(&mut self, mut source: Box<dyn TelemetrySource>) {
+        self.stop_source();
+
+        let (sender, receiver) = mpsc::channel();
+        match source.start(sender) {
+            Ok(()) => {
+                self.log(format!("Started live source: {}", source.name()));
+                self.live = Some(LiveSource {
+                    name: source.name(),
+                    status: None,
+                    packets: 0,
+                    corrupted: 0,
+                });
+                self.source = Some(source);
+                self.source_events = Some(receiver);
+                self.send(PipelineEvent::Source(self.live.clone()));
+            }
+            Err(e) => self.log(format!("Error starting {}: {}", source.name(), e)),
+        }
+    }
+
+    fn stop_source(&mut self) {
+        if let Some(mut source) = self.source.take() {
+            source.stop();
+            self.log(format!("Stopped live source: {}", source.name()));
+        }
+        self.source_events = None;
+        if self.live.take().is_some() {
+            self.send(PipelineEvent::Source(None));
+        }
+    }
+
+    fn drain_source(&mut self) {
+        let Some(events) = &self.source_events else {
+            return;
+        };
+        let events: Vec<SourceEvent> = events.try_iter().collect();
+        if events.is_empty() {
+            return;
+        }
+
+        for event in events {
+            match event {
+                SourceEvent::Packet(packet) => {
+                    let Some(live) = &mut self.live else {
+                        continue;
+                    };
+                    live.packets += 1;
+                    let count = live.packets;
+                    self.log(format!("Received live packet {}...", count));
+                    self.process_packet(packet);
+                }
+                SourceEvent::Error(e) => self.log(e),
+                SourceEvent::Corrupted(e) => {
+                    if let Some(live) = &mut self.live {
+                        live.corrupted += 1;
+                    }
+                    self.count_corrupted(e);
+                }
+                SourceEvent::Status(status) => {
+                    let Some(live) = &mut self.live else {
+                        continue;
+                    };
+                    let line = (live.status.as_ref().map(|s| &s.state) != Some(&status.state))
+                        .then(|| format!("{}: {}", live.name, status.state));
+                    live.status = Some(status);
+                    if let Some(line) = line {
+                        self.log(line);
+                    }
+                }
+            }
+        }
+        self.send(PipelineEvent::Source(self.live.clone()));
+    }
+
+    fn publish_replay(&mut self) {
+        self.send(PipelineEvent::Replay {
+            sent: self.replay_index,
+            total: self.replay.len(),
+            paused: self.paused,
+        });
+    }
+
+    fn publish_alerts(&mut self) {
+        let open: Vec<AlertRecord> = self.alerts.open().cloned().collect();
+        if open != self.published {
+            self.published = open.clone();
+            self.send(PipelineEvent::Alerts(open));
+        }
+    }
+
+    fn log(&mut self, line: String) {
+        self.send(PipelineEvent::Log(line));
+    }
+
+    /// Queues `event` without blocking. When the queue is full, log lines
+    /// and packets are dropped and counted, and any other event replaces a
+    /// pending one of the same kind until `flush` finds room for it.
+    fn send(&mut self, event: PipelineEvent) {
+        self.flush();
+        match event {
+            PipelineEvent::Log(_) | PipelineEvent::Packet { .. } => {
+                if self.events.try_send(event).is_err() {
+                    self.dropped += 1;
+                }
+            }
+            event => {
+                let kind = std::mem::discriminant(&event);
+                self.pending.retain(|e| std::mem::discriminant(e) != kind);
+                self.pending.push(event);
+                self.flush();
+            }
+        }
+    }
+
+    /// Queues pending state events, then reports dropped events, while there
+    /// is room.
+    fn flush(&mut self) {
+        while !self.pending.is_empty() {
+            match self.events.try_send(self.pending.remove(0)) {
+                Ok(()) => {}
+                Err(TrySendError::Full(event)) => {
+                    self.pending.insert(0, event);
+                    return;
+                }
+                Err(TrySendError::Disconnected(_)) => {
+                    self.pending.clear();
+                    return;
+                }
+            }
+        }
+        if self.dropped > 0 {
+            let line = format!(
+                "Dropped {} events while the event queue was full",
+                self.dropped
+            );
+            if self.events.try_send(PipelineEvent::Log(line)).is_ok() {
+                self.dropped = 0;
+            }
+        }
+    }
+}
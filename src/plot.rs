@@ -0,0 +1,140 @@
+use crate::history::TelemetryHistory;
+use crate::monitor::{AlertLevel, Threshold};
+use eframe::egui::{self, Color32};
+use egui_plot::{HLine, Line, LineStyle, Plot, PlotBounds, PlotPoints, Points, VLine};
+
+/// Time windows offered by the charts; `None` shows every sample.
+pub const TIME_WINDOWS: [(&str, Option<u64>); 5] = [
+    ("1 min", Some(60)),
+    ("5 min", Some(300)),
+    ("15 min", Some(900)),
+    ("1 h", Some(3600)),
+    ("All", None),
+];
+
+const PLOT_HEIGHT: f32 = 120.0;
+
+/// Visible time range of one chart.
+///
+/// While `follow` is set the chart shows the last `window` seconds up to the
+/// newest sample. Dragging or scrolling clears it, and a double click sets it
+/// again.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlotView {
+    pub window: Option<u64>, // Seconds shown while following
+    pub follow: bool,
+}
+
+impl Default for PlotView {
+    fn default() -> Self {
+        Self {
+            window: Some(300),
+            follow: true,
+        }
+    }
+}
+
+impl PlotView {
+    /// Start and end timestamps shown while following samples from
+    /// `earliest` to `latest`.
+    pub fn x_range(&self, earliest: u64, latest: u64) -> (f64, f64) {
+        let full = self
+            .window
+            .unwrap_or(latest.saturating_sub(earliest))
+            .max(1);
+        let end = latest as f64;
+        (end - full as f64, end)
+    }
+
+    pub fn reset(&mut self) {
+        self.follow = true;
+    }
+}
+
+pub fn level_color(level: AlertLevel) -> Color32 {
+    match level {
+        AlertLevel::Critical => Color32::RED,
+        AlertLevel::Warning => Color32::YELLOW,
+        AlertLevel::Info => Color32::LIGHT_BLUE,
+    }
+}
+
+/// Draws `field` from `history` as a line chart with `thresholds` as
+/// dashed lines and alert markers as vertical lines.
+pub fn show(
+    ui: &mut egui::Ui,
+    view: &mut PlotView,
+    history: &TelemetryHistory,
+    field: &str,
+    thresholds: &[Threshold],
+) {
+    let mut points: Vec<[f64; 2]> = history.samples(field).map(|(t, v)| [t as f64, v]).collect();
+    points.sort_by(|a, b| a[0].total_cmp(&b[0]));
+    let range = history
+        .earliest(field)
+        .zip(history.latest(field))
+        .map(|(earliest, latest)| view.x_range(earliest, latest));
+
+    let response = Plot::new(("chart", field))
+        .height(PLOT_HEIGHT)
+        .allow_scroll(false)
+        .allow_double_click_reset(false)
+        .label_formatter(|_, point| format!("t={:.0} value={:.3}", point.x, point.y))
+        .show(ui, |plot_ui| {
+            if let (true, Some((x_min, x_max))) = (view.follow, range) {
+                let (y_min, y_max) = y_range(&points, thresholds, x_min, x_max);
+                plot_ui.set_plot_bounds(PlotBounds::from_min_max([x_min, y_min], [x_max, y_max]));
+            }
+            let scroll = plot_ui.ctx().input(|i| i.smooth_scroll_delta.y);
+            if plot_ui.response().hovered() && scroll != 0.0 {
+                let zoom = (scroll * 0.005).exp();
+                plot_ui.zoom_bounds_around_hovered(egui::vec2(zoom, 1.0));
+                view.follow = false;
+            }
+
+            for threshold in thresholds {
+                let color = level_color(threshold.level).gamma_multiply(0.6);
+                plot_ui.hline(
+                    HLine::new(threshold.value)
+                        .color(color)
+                        .style(LineStyle::dashed_loose())
+                        .name(&threshold.label),
+                );
+            }
+            for marker in history.markers(field) {
+                let color = level_color(marker.level).gamma_multiply(0.5);
+                plot_ui.vline(VLine::new(marker.timestamp as f64).color(color));
+            }
+            let color = plot_ui.ctx().style().visuals.strong_text_color();
+            if points.len() == 1 {
+                plot_ui.points(Points::new(points.clone()).radius(2.5).color(color));
+            }
+            plot_ui.line(Line::new(PlotPoints::new(points)).width(1.5).color(color));
+        })
+        .response;
+
+    if response.dragged() {
+        view.follow = false;
+    }
+    if response.double_clicked() {
+        view.reset();
+    }
+}
+
+/// Padded value range of the samples between `x_min` and `x_max` and of the
+/// thresholds.
+fn y_range(points: &[[f64; 2]], thresholds: &[Threshold], x_min: f64, x_max: f64) -> (f64, f64) {
+    let (mut y_min, mut y_max) = points
+        .iter()
+        .filter(|p| p[0] >= x_min && p[0] <= x_max)
+        .map(|p| p[1])
+        .chain(thresholds.iter().map(|t| t.value))
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), v| {
+            (lo.min(v), hi.max(v))
+        });
+    if !y_min.is_finite() {
+        (y_min, y_max) = (0.0, 1.0);
+    }
+    let pad = ((y_max - y_min) * 0.05).max(0.5);
+    (y_min - pad, y_max + pad)
+}
@@ -0,0 +1,207 @@
+use crate::models::{TelemetryPacket, TelemetryPayload};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Orbits kept by `PowerAnalytics`; older ones are dropped first.
+pub const MAX_ORBITS: usize = 100;
+
+/// Battery parameters used by `PowerAnalytics`.
+///
+/// `min_level` is the state of charge, in percent, that `time_to_min_battery`
+/// projects to. Power packets more than `max_gap_seconds` apart are not
+/// integrated across. The eclipse state only changes once the current is
+/// more than `eclipse_current` amperes from zero.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BatteryConfig {
+    pub capacity_ah: f64,
+    pub min_level: f64,
+    pub max_gap_seconds: u64,
+    pub eclipse_current: f64,
+}
+
+impl Default for BatteryConfig {
+    fn default() -> Self {
+        Self {
+            capacity_ah: 40.0,
+            min_level: 20.0,
+            max_gap_seconds: 600,
+            eclipse_current: 0.1,
+        }
+    }
+}
+
+impl BatteryConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.capacity_ah <= 0.0 {
+            return Err(format!("capacity_ah {} is not positive", self.capacity_ah));
+        }
+        if !(0.0..=100.0).contains(&self.min_level) {
+            return Err(format!("min_level {} is outside 0 to 100", self.min_level));
+        }
+        if self.max_gap_seconds == 0 {
+            return Err("max_gap_seconds is 0".to_string());
+        }
+        if !(self.eclipse_current >= 0.0 && self.eclipse_current.is_finite()) {
+            return Err(format!(
+                "eclipse_current {} is not a finite non-negative number",
+                self.eclipse_current
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// One orbit, from entering eclipse to entering the next eclipse.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Orbit {
+    pub number: u32, // 1 for the first eclipse seen
+    pub eclipse_start: u64,
+    pub sunlit_start: Option<u64>,
+    pub end: Option<u64>,        // Start of the next orbit
+    pub depth_of_discharge: f64, // Deepest discharge since eclipse entry, % of capacity
+    start_charge_ah: f64,
+}
+
+impl Orbit {
+    /// Seconds spent in eclipse, once the orbit has left it.
+    pub fn eclipse_seconds(&self) -> Option<u64> {
+        self.sunlit_start
+            .map(|t| t.saturating_sub(self.eclipse_start))
+    }
+}
+
+/// Battery state of charge, eclipse and depth-of-discharge tracking.
+///
+/// Battery current is integrated over packet timestamps (positive while
+/// discharging) into the charge used since the first power packet, and a
+/// coulomb-counted state of charge is compared with the reported
+/// `battery_level`. A current above `eclipse_current` means eclipse, one
+/// below `-eclipse_current` sunlight; anything in between keeps the previous
+/// state, so noise around zero does not start new orbits. Each eclipse entry
+/// starts a new `Orbit`. Packets with a non-finite current or battery level
+/// are skipped.
+#[derive(Debug, Clone, Default)]
+pub struct PowerAnalytics {
+    pub battery: BatteryConfig,
+    last: Option<(u64, f64)>, // Timestamp and current of the previous power packet
+    charge_used_ah: f64,
+    initial_level: Option<f64>,
+    eclipse: Option<bool>,
+    orbits: VecDeque<Orbit>,
+}
+
+impl PowerAnalytics {
+    pub fn new(battery: BatteryConfig) -> Self {
+        Self {
+            battery,
+            ..Self::default()
+        }
+    }
+
+    /// Forgets the integrated charge, eclipse state and orbits.
+    pub fn reset(&mut self) {
+        *self = Self::new(self.battery.clone());
+    }
+
+    /// Whether the spacecraft was in eclipse at the last power packet, if
+    /// that is known yet.
+    pub fn eclipse(&self) -> Option<bool> {
+        self.eclipse
+    }
+
+    /// Recent orbits, oldest first; the last one may still be in progress.
+    pub fn orbits(&self) -> &VecDeque<Orbit> {
+        &self.orbits
+    }
+
+    /// Updates the analytics with a power packet and stores the derived
+    /// parameters in `packet.derived`. Other packets, and power packets with
+    /// a non-finite current or battery level, are left unchanged.
+    pub fn apply(&mut self, packet: &mut TelemetryPacket) {
+        let TelemetryPayload::Power(power) = &packet.payload else {
+            return;
+        };
+        if !power.current.is_finite() || !power.battery_level.is_finite() {
+            return;
+        }
+        let now = packet.timestamp;
+        let capacity = self.battery.capacity_ah;
+
+        // Trapezoidal integration; gaps and backward jumps are skipped
+        if let Some((then, previous)) = self.last {
+            let dt = now.saturating_sub(then);
+            if now > then && dt <= self.battery.max_gap_seconds {
+                self.charge_used_ah += (previous + power.current) / 2.0 * dt as f64 / 3600.0;
+            }
+        }
+        self.last = Some((now, power.current));
+
+        let initial = *self.initial_level.get_or_insert(power.battery_level);
+        let soc_estimate = (initial - 100.0 * self.charge_used_ah / capacity).clamp(0.0, 100.0);
+
+        let deadband = self.battery.eclipse_current;
+        let eclipse = match power.current {
+            c if c > deadband => Some(true),
+            c if c < -deadband => Some(false),
+            _ => self.eclipse,
+        };
+        if eclipse == Some(true) && self.eclipse != Some(true) {
+            self.enter_eclipse(now);
+        } else if eclipse == Some(false) && self.eclipse == Some(true) {
+            if let Some(orbit) = self.orbits.back_mut() {
+                orbit.sunlit_start = Some(now);
+            }
+        }
+        self.eclipse = eclipse;
+
+        let charge_used_ah = self.charge_used_ah;
+        let depth = self.orbits.back_mut().map(|orbit| {
+            let depth = 100.0 * (charge_used_ah - orbit.start_charge_ah) / capacity;
+            orbit.depth_of_discharge = orbit.depth_of_discharge.max(depth);
+            orbit.depth_of_discharge
+        });
+
+        let derived = &mut packet.derived;
+        derived.insert("power.charge_used_ah".to_string(), charge_used_ah);
+        derived.insert("power.soc_estimate".to_string(), soc_estimate);
+        derived.insert(
+            "power.soc_error".to_string(),
+            power.battery_level - soc_estimate,
+        );
+        if let Some(eclipse) = eclipse {
+            derived.insert("power.eclipse".to_string(), if eclipse { 1.0 } else { 0.0 });
+        }
+        if let Some(depth) = depth {
+            derived.insert("power.depth_of_discharge".to_string(), depth);
+        }
+        if power.current > 0.0 && power.battery_level > self.battery.min_level {
+            let remaining_ah = (power.battery_level - self.battery.min_level) / 100.0 * capacity;
+            derived.insert(
+                "power.time_to_min_battery".to_string(),
+                remaining_ah / power.current * 3600.0,
+            );
+        }
+    }
+
+    fn enter_eclipse(&mut self, now: u64) {
+        let number = match self.orbits.back_mut() {
+            Some(orbit) => {
+                orbit.end = Some(now);
+                orbit.number + 1
+            }
+            None => 1,
+        };
+        if self.orbits.len() == MAX_ORBITS {
+            self.orbits.pop_front();
+        }
+        self.orbits.push_back(Orbit {
+            number,
+            eclipse_start: now,
+            sunlit_start: None,
+            end: None,
+            depth_of_discharge: 0.0,
+            start_charge_ah: self.charge_used_ah,
+        });
+    }
+}
@@ -0,0 +1,438 @@
+use crate::definition::PacketDefinitions;
+use crate::models::{self, Subsystem, TelemetryPacket};
+use crate::monitor::{AlertLevel, LimitSet, Persistence};
+use crate::trend::{Direction, TrendCheck};
+use crate::watchdog::ReportingPeriod;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum RuleError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("TOML error: {0}")]
+    Toml(#[from] toml::de::Error),
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Unsupported rule file format: {0}")]
+    UnsupportedFormat(String),
+    #[error("Invalid rule set: {0}")]
+    Invalid(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Comparison {
+    #[serde(rename = "<")]
+    LessThan,
+    #[serde(rename = "<=")]
+    LessOrEqual,
+    #[serde(rename = ">")]
+    GreaterThan,
+    #[serde(rename = ">=")]
+    GreaterOrEqual,
+    #[serde(rename = "==")]
+    Equal,
+    #[serde(rename = "!=")]
+    NotEqual,
+}
+
+impl Comparison {
+    pub fn holds(&self, value: f64, threshold: f64) -> bool {
+        match self {
+            Comparison::LessThan => value < threshold,
+            Comparison::LessOrEqual => value <= threshold,
+            Comparison::GreaterThan => value > threshold,
+            Comparison::GreaterOrEqual => value >= threshold,
+            Comparison::Equal => value == threshold,
+            Comparison::NotEqual => value != threshold,
+        }
+    }
+}
+
+/// A single limit check on one telemetry field.
+///
+/// `field` is a path understood by `TelemetryPacket::field`, such as
+/// `power.voltage`. The rule fires when `value <comparison> threshold` holds.
+/// `message` may use `{value}`, `{threshold}`, `{field}` and `{name}`, with an
+/// optional precision such as `{value:.2}`.
+///
+/// Once tripped, the rule stays active while `value <comparison> clear_threshold`
+/// holds, so a clear threshold on the safe side of `threshold` adds hysteresis.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Rule {
+    pub name: String,
+    pub field: String,
+    pub comparison: Comparison,
+    pub threshold: f64,
+    pub level: AlertLevel,
+    pub message: String,
+    #[serde(default)]
+    pub clear_threshold: Option<f64>,
+    #[serde(default)]
+    pub persistence: Persistence,
+}
+
+impl Rule {
+    pub fn new(
+        name: &str,
+        field: &str,
+        comparison: Comparison,
+        threshold: f64,
+        level: AlertLevel,
+        message: &str,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            field: field.to_string(),
+            comparison,
+            threshold,
+            level,
+            message: message.to_string(),
+            clear_threshold: None,
+            persistence: Persistence::default(),
+        }
+    }
+
+    pub fn clear_threshold(mut self, clear_threshold: f64) -> Self {
+        self.clear_threshold = Some(clear_threshold);
+        self
+    }
+
+    pub fn persistence(mut self, persistence: Persistence) -> Self {
+        self.persistence = persistence;
+        self
+    }
+
+    /// Returns the field value if the rule's trip condition holds for this
+    /// packet, ignoring hysteresis and persistence.
+    pub fn check(&self, packet: &TelemetryPacket) -> Option<f64> {
+        let value = packet.field(&self.field)?;
+        self.comparison
+            .holds(value, self.threshold)
+            .then_some(value)
+    }
+
+    pub fn render(&self, value: f64) -> String {
+        let mut out = String::with_capacity(self.message.len());
+        let mut rest = self.message.as_str();
+
+        while let Some(start) = rest.find('{') {
+            out.push_str(&rest[..start]);
+            let Some(len) = rest[start..].find('}') else {
+                rest = &rest[start..];
+                break;
+            };
+            let placeholder = &rest[start + 1..start + len];
+            let (key, precision) = match placeholder.split_once(":.") {
+                Some((key, p)) => (key, p.parse::<usize>().ok()),
+                None => (placeholder, None),
+            };
+            let number = |n: f64| match precision {
+                Some(p) => format!("{:.*}", p, n),
+                None => n.to_string(),
+            };
+            match key {
+                "value" => out.push_str(&number(value)),
+                "threshold" => out.push_str(&number(self.threshold)),
+                "field" => out.push_str(&self.field),
+                "name" => out.push_str(&self.name),
+                _ => out.push_str(&rest[start..=start + len]),
+            }
+            rest = &rest[start + len + 1..];
+        }
+        out.push_str(rest);
+        out
+    }
+}
+
+/// Ordered rules, limit sets, trend checks and reporting periods, as stored
+/// in a TOML or JSON rule file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RuleSet {
+    #[serde(default)]
+    pub rules: Vec<Rule>,
+    #[serde(default)]
+    pub limits: Vec<LimitSet>,
+    #[serde(default)]
+    pub trends: Vec<TrendCheck>,
+    #[serde(default)]
+    pub watchdog: Vec<ReportingPeriod>,
+}
+
+impl Default for RuleSet {
+    fn default() -> Self {
+        Self {
+            rules: vec![
+                Rule::new(
+                    "high_angular_rate",
+                    "aocs.max_angular_rate",
+                    Comparison::GreaterThan,
+                    2.0,
+                    AlertLevel::Critical,
+                    "High Angular Rate: {value:.3} deg/s (Threshold: {threshold:.3} deg/s)",
+                ),
+                Rule::new(
+                    "wheel_saturation",
+                    "aocs.max_wheel_speed",
+                    Comparison::GreaterThan,
+                    6000.0,
+                    AlertLevel::Warning,
+                    "Reaction Wheel Saturation: {value:.0} RPM (Threshold: {threshold:.0} RPM)",
+                ),
+                Rule::new(
+                    "quaternion_norm_drift",
+                    "aocs.quaternion_norm_error",
+                    Comparison::GreaterThan,
+                    0.01,
+                    AlertLevel::Warning,
+                    "Quaternion Norm Drift: {value:.4} (Threshold: {threshold:.4})",
+                ),
+                Rule::new(
+                    "battery_margin",
+                    "power.time_to_min_battery",
+                    Comparison::LessThan,
+                    3600.0,
+                    AlertLevel::Warning,
+                    "Battery Predicted to Reach Minimum in {value:.0} s (Threshold: {threshold:.0} s)",
+                ),
+                Rule::new(
+                    "low_star_confidence",
+                    "star_tracker.confidence",
+                    Comparison::LessThan,
+                    0.8,
+                    AlertLevel::Info,
+                    "Low Star Confidence: {value:.2} (Threshold: {threshold:.2})",
+                ),
+            ],
+            limits: vec![
+                LimitSet::new("Bus Voltage", "power.voltage", "V")
+                    .low(Some(22.0), Some(24.0))
+                    .high(Some(32.0), Some(34.0)),
+                LimitSet::new("Current", "power.current", "A").high(Some(4.0), Some(5.0)),
+                LimitSet::new("Battery", "power.battery_level", "%").low(Some(20.0), Some(30.0)),
+                LimitSet::new("Depth of Discharge", "power.depth_of_discharge", "%")
+                    .high(Some(25.0), Some(35.0)),
+                LimitSet::new("Temperature", "thermal.temp_celsius", "C")
+                    .low(Some(-20.0), Some(-10.0))
+                    .high(Some(80.0), Some(95.0)),
+                // Per-zone operating (yellow) and survival (red) ranges
+                LimitSet::new("Battery Temperature", &models::zone_path("battery"), "C")
+                    .low(Some(-5.0), Some(0.0))
+                    .high(Some(40.0), Some(45.0)),
+                LimitSet::new("OBC Temperature", &models::zone_path("obc"), "C")
+                    .low(Some(-30.0), Some(-20.0))
+                    .high(Some(70.0), Some(85.0)),
+                LimitSet::new(
+                    "Star Tracker Head Temperature",
+                    &models::zone_path("star_tracker_head"),
+                    "C",
+                )
+                .low(Some(-35.0), Some(-25.0))
+                .high(Some(35.0), Some(45.0)),
+                LimitSet::new(
+                    "Radiator +X Temperature",
+                    &models::zone_path("radiator_px"),
+                    "C",
+                )
+                .low(Some(-90.0), Some(-80.0))
+                .high(Some(50.0), Some(60.0)),
+                LimitSet::new("Right Ascension", "star_tracker.right_ascension", " deg")
+                    .low(Some(0.0), None)
+                    .high(None, Some(360.0)),
+                LimitSet::new("Declination", "star_tracker.declination", " deg")
+                    .low(Some(-90.0), None)
+                    .high(None, Some(90.0)),
+            ],
+            trends: vec![
+                TrendCheck::new("Battery", "power.battery_level", "%", 300)
+                    .max_fall(2.0)
+                    .predict(Direction::Falling, 20.0, 600),
+                TrendCheck::new("Temperature", "thermal.temp_celsius", "C", 300)
+                    .max_rise(5.0)
+                    .predict(Direction::Rising, 80.0, 600),
+            ],
+            watchdog: [
+                Subsystem::Power,
+                Subsystem::Thermal,
+                Subsystem::Aocs,
+                Subsystem::StarTracker,
+            ]
+            .into_iter()
+            .map(|subsystem| ReportingPeriod::new(subsystem, 60).max_gap(300))
+            .collect(),
+        }
+    }
+}
+
+impl RuleSet {
+    pub fn from_toml_str(s: &str) -> Result<Self, RuleError> {
+        Ok(toml::from_str(s)?)
+    }
+
+    pub fn from_json_str(s: &str) -> Result<Self, RuleError> {
+        Ok(serde_json::from_str(s)?)
+    }
+
+    /// Loads and validates a rule file, choosing the format from the `.toml`
+    /// or `.json` extension.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, RuleError> {
+        Self::load_using(path, &PacketDefinitions::built_in())
+    }
+
+    /// Like `load`, also accepting fields of the instruments in
+    /// `definitions`.
+    pub fn load_using(
+        path: impl AsRef<Path>,
+        definitions: &PacketDefinitions,
+    ) -> Result<Self, RuleError> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)?;
+        let set = match path.extension().and_then(|e| e.to_str()) {
+            Some("toml") => Self::from_toml_str(&contents)?,
+            Some("json") => Self::from_json_str(&contents)?,
+            other => {
+                return Err(RuleError::UnsupportedFormat(
+                    other.unwrap_or_default().to_string(),
+                ))
+            }
+        };
+        set.validate_using(definitions)?;
+        Ok(set)
+    }
+
+    /// Replaces entries of `self` with the entries of `other` that have the
+    /// same name (or subsystem, for reporting periods) and appends the rest.
+    pub fn merge(&mut self, other: RuleSet) {
+        fn upsert<T>(items: &mut Vec<T>, item: T, same: impl Fn(&T, &T) -> bool) {
+            match items.iter_mut().find(|i| same(i, &item)) {
+                Some(existing) => *existing = item,
+                None => items.push(item),
+            }
+        }
+        for rule in other.rules {
+            upsert(&mut self.rules, rule, |a, b| a.name == b.name);
+        }
+        for limit in other.limits {
+            upsert(&mut self.limits, limit, |a, b| a.name == b.name);
+        }
+        for trend in other.trends {
+            upsert(&mut self.trends, trend, |a, b| a.name == b.name);
+        }
+        for period in other.watchdog {
+            upsert(&mut self.watchdog, period, |a, b| {
+                a.subsystem == b.subsystem
+            });
+        }
+    }
+
+    /// Checks that every field path exists, limits are ordered, windows and
+    /// timeouts are non-zero and names are unique.
+    pub fn validate(&self) -> Result<(), RuleError> {
+        self.validate_using(&PacketDefinitions::built_in())
+    }
+
+    /// Like `validate`, also accepting fields of the instruments in
+    /// `definitions` and reporting periods for their IDs.
+    pub fn validate_using(&self, definitions: &PacketDefinitions) -> Result<(), RuleError> {
+        let invalid = |msg: String| Err(RuleError::Invalid(msg));
+        let known = |path: &str| definitions.is_field_path(path);
+
+        for (i, rule) in self.rules.iter().enumerate() {
+            if !known(&rule.field) {
+                return invalid(format!(
+                    "rule '{}': unknown field '{}'",
+                    rule.name, rule.field
+                ));
+            }
+            if self.rules[..i].iter().any(|r| r.name == rule.name) {
+                return invalid(format!("duplicate rule name '{}'", rule.name));
+            }
+        }
+
+        for (i, limit) in self.limits.iter().enumerate() {
+            if !known(&limit.field) {
+                return invalid(format!(
+                    "limit '{}': unknown field '{}'",
+                    limit.name, limit.field
+                ));
+            }
+            if self.limits[..i].iter().any(|l| l.name == limit.name) {
+                return invalid(format!("duplicate limit name '{}'", limit.name));
+            }
+            let ordered = [
+                limit.red_low,
+                limit.yellow_low,
+                limit.yellow_high,
+                limit.red_high,
+            ]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .windows(2)
+            .all(|w| w[0] <= w[1]);
+            if !ordered {
+                return invalid(format!(
+                    "limit '{}': limits must be ordered red_low <= yellow_low <= yellow_high <= red_high",
+                    limit.name
+                ));
+            }
+            if limit.hysteresis < 0.0 {
+                return invalid(format!("limit '{}': hysteresis is negative", limit.name));
+            }
+        }
+
+        for (i, trend) in self.trends.iter().enumerate() {
+            if !known(&trend.field) {
+                return invalid(format!(
+                    "trend '{}': unknown field '{}'",
+                    trend.name, trend.field
+                ));
+            }
+            if self.trends[..i].iter().any(|t| t.name == trend.name) {
+                return invalid(format!("duplicate trend name '{}'", trend.name));
+            }
+            if trend.window_seconds == 0 {
+                return invalid(format!("trend '{}': window_seconds is 0", trend.name));
+            }
+            if trend.predict_threshold.is_some() && trend.horizon_seconds == 0 {
+                return invalid(format!(
+                    "trend '{}': predict_threshold needs a non-zero horizon_seconds",
+                    trend.name
+                ));
+            }
+            if trend.predict_threshold.is_some() != trend.predict_direction.is_some() {
+                return invalid(format!(
+                    "trend '{}': predict_threshold and predict_direction must be set together",
+                    trend.name
+                ));
+            }
+        }
+
+        for (i, period) in self.watchdog.iter().enumerate() {
+            if self.watchdog[..i]
+                .iter()
+                .any(|p| p.subsystem == period.subsystem)
+            {
+                return invalid(format!(
+                    "duplicate reporting period for {:?}",
+                    period.subsystem
+                ));
+            }
+            if definitions.get(period.subsystem.id()).is_none() {
+                return invalid(format!(
+                    "reporting period for {:?}: no packet definition for id {}",
+                    period.subsystem,
+                    period.subsystem.id()
+                ));
+            }
+            if period.timeout_seconds == 0 {
+                return invalid(format!(
+                    "reporting period for {:?}: timeout_seconds is 0",
+                    period.subsystem
+                ));
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,46 +1,101 @@
-pub fn generate_simulated_packets() -> Vec<Vec<u8>> {
-    let mut packets = Vec::new();
+use crate::crc::CrcMode;
+use crate::encoder::PacketEncoder;
+use crate::models::{
+    AocsData, CelestialCoordinates, ControlMode, PowerData, Quaternion, StarTrackerReading,
+    Subsystem, TelemetryPacket, TelemetryPayload, ThermalData, ThermalZone,
+};
 
-    // 1. Power Packet (Normal)
-    let mut p1 = Vec::new();
-    p1.extend_from_slice(&(1627849200u64).to_be_bytes()); // Timestamp
-    p1.push(0); // Subsystem: Power
-    p1.extend_from_slice(&(24u16).to_be_bytes()); // Len
-    p1.extend_from_slice(&(28.0f64).to_be_bytes()); // Voltage
-    p1.extend_from_slice(&(2.5f64).to_be_bytes()); // Current
-    p1.extend_from_slice(&(90.0f64).to_be_bytes()); // Battery
-    packets.push(p1);
-
-    // 2. Thermal Packet (High Temp)
-    let mut p2 = Vec::new();
-    p2.extend_from_slice(&(1627849210u64).to_be_bytes());
-    p2.push(1); // Subsystem: Thermal
-    p2.extend_from_slice(&(8u16).to_be_bytes());
-    p2.extend_from_slice(&(85.5f64).to_be_bytes()); // Temp > 80 (Threshold)
-    packets.push(p2);
-
-    // 3. Star Tracker Packet (Good Confidence)
-    let mut p3 = Vec::new();
-    p3.extend_from_slice(&(1627849220u64).to_be_bytes());
-    p3.push(3); // Subsystem: StarTracker
-    p3.extend_from_slice(&(31u16).to_be_bytes()); // Len
-    p3.extend_from_slice(&(12.5f64).to_be_bytes()); // RA
-    p3.extend_from_slice(&(45.0f64).to_be_bytes()); // Dec
-    p3.extend_from_slice(&(0.95f64).to_be_bytes()); // Confidence
-    let target = "Sirius";
-    p3.push(target.len() as u8);
-    p3.extend_from_slice(target.as_bytes());
-    packets.push(p3);
-
-    // 4. Power Packet (Low Battery)
-    let mut p4 = Vec::new();
-    p4.extend_from_slice(&(1627849230u64).to_be_bytes());
-    p4.push(0); // Subsystem: Power
-    p4.extend_from_slice(&(24u16).to_be_bytes());
-    p4.extend_from_slice(&(24.0f64).to_be_bytes());
-    p4.extend_from_slice(&(1.0f64).to_be_bytes());
-    p4.extend_from_slice(&(15.0f64).to_be_bytes()); // Battery < 20 (Threshold)
-    packets.push(p4);
+/// Encoded simulation frames, with a CRC trailer unless `crc` is `Absent`.
+pub fn generate_simulated_packets(crc: CrcMode) -> Vec<Vec<u8>> {
+    let packets = [
+        // 1. Power Packet (Normal)
+        TelemetryPacket {
+            timestamp: 1627849200,
+            subsystem: Subsystem::Power,
+            payload: TelemetryPayload::Power(PowerData {
+                voltage: 28.0,
+                current: 2.5,
+                battery_level: 90.0,
+            }),
+            raw_counts: Default::default(),
+            derived: Default::default(),
+        },
+        // 2. Thermal Packet (High Temp)
+        TelemetryPacket {
+            timestamp: 1627849210,
+            subsystem: Subsystem::Thermal,
+            payload: TelemetryPayload::Thermal(ThermalData {
+                temp_celsius: 85.5, // Temp > 80 (Threshold)
+                zones: [
+                    ("battery", 47.0), // Battery > 45 (Survival Limit)
+                    ("obc", 38.2),
+                    ("star_tracker_head", -12.4),
+                    ("radiator_px", -61.0),
+                ]
+                .into_iter()
+                .map(|(name, temp_celsius)| ThermalZone {
+                    name: name.to_string(),
+                    temp_celsius,
+                })
+                .collect(),
+            }),
+            raw_counts: Default::default(),
+            derived: Default::default(),
+        },
+        // 3. Star Tracker Packet (Good Confidence)
+        TelemetryPacket {
+            timestamp: 1627849220,
+            subsystem: Subsystem::StarTracker,
+            payload: TelemetryPayload::StarTracker(StarTrackerReading {
+                target_id: Some("Sirius".to_string()),
+                coordinates: CelestialCoordinates {
+                    right_ascension: 12.5,
+                    declination: 45.0,
+                },
+                confidence: 0.95,
+            }),
+            raw_counts: Default::default(),
+            derived: Default::default(),
+        },
+        // 4. Power Packet (Low Battery), sent as raw ADC counts
+        TelemetryPacket {
+            timestamp: 1627849230,
+            subsystem: Subsystem::Power,
+            payload: TelemetryPayload::Power(PowerData {
+                voltage: 24.0,
+                current: 1.0,
+                battery_level: 15.0, // Battery < 20 (Threshold)
+            }),
+            raw_counts: [
+                ("power.voltage".to_string(), 2400),
+                ("power.current".to_string(), 33768),
+                ("power.battery_level".to_string(), 10500),
+            ]
+            .into(),
+            derived: Default::default(),
+        },
+        // 5. AOCS Packet (Wheel Saturation)
+        TelemetryPacket {
+            timestamp: 1627849240,
+            subsystem: Subsystem::Aocs,
+            payload: TelemetryPayload::Aocs(AocsData {
+                attitude: Quaternion {
+                    w: 0.9239,
+                    x: 0.0,
+                    y: 0.3827,
+                    z: 0.0,
+                },
+                angular_rate: [0.01, -0.02, 0.005],
+                wheel_speeds: vec![1200.0, -850.0, 6200.0, 300.0], // Wheel 3 > 6000 (Threshold)
+                control_mode: ControlMode::FinePointing,
+            }),
+            raw_counts: Default::default(),
+            derived: Default::default(),
+        },
+    ];
 
     packets
+        .iter()
+        .map(|p| PacketEncoder::encode_for(p, crc).expect("simulated packets are encodable"))
+        .collect()
 }
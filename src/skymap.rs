@@ -0,0 +1,238 @@
+use crate::history::TelemetryHistory;
+use eframe::egui::{self, Align2, Color32, FontId, Pos2, Sense, Stroke};
+use serde::{Deserialize, Serialize};
+use std::f64::consts::{FRAC_PI_2, PI};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CatalogStar {
+    pub name: &'static str,
+    pub right_ascension: f64, // degrees, J2000
+    pub declination: f64,     // degrees, J2000
+    pub magnitude: f64,       // visual
+}
+
+const fn star(name: &'static str, ra: f64, dec: f64, magnitude: f64) -> CatalogStar {
+    CatalogStar {
+        name,
+        right_ascension: ra,
+        declination: dec,
+        magnitude,
+    }
+}
+
+/// The brightest stars in the sky, drawn as reference points.
+pub const BRIGHT_STARS: [CatalogStar; 21] = [
+    star("Sirius", 101.287, -16.716, -1.46),
+    star("Canopus", 95.988, -52.696, -0.74),
+    star("Rigil Kentaurus", 219.902, -60.834, -0.27),
+    star("Arcturus", 213.915, 19.182, -0.05),
+    star("Vega", 279.235, 38.784, 0.03),
+    star("Capella", 79.172, 45.998, 0.08),
+    star("Rigel", 78.634, -8.202, 0.13),
+    star("Procyon", 114.825, 5.225, 0.34),
+    star("Betelgeuse", 88.793, 7.407, 0.42),
+    star("Achernar", 24.429, -57.237, 0.46),
+    star("Hadar", 210.956, -60.373, 0.61),
+    star("Altair", 297.696, 8.868, 0.76),
+    star("Acrux", 186.650, -63.099, 0.76),
+    star("Aldebaran", 68.980, 16.509, 0.86),
+    star("Antares", 247.352, -26.432, 0.96),
+    star("Spica", 201.298, -11.161, 0.97),
+    star("Pollux", 116.329, 28.026, 1.14),
+    star("Fomalhaut", 344.413, -29.622, 1.16),
+    star("Deneb", 310.358, 45.280, 1.25),
+    star("Regulus", 152.093, 11.967, 1.40),
+    star("Polaris", 37.955, 89.264, 1.98),
+];
+
+/// Width over height of the full-sky map in both projections.
+const MAP_ASPECT: f32 = 2.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Projection {
+    Equirectangular,
+    Aitoff,
+}
+
+impl Projection {
+    /// Projects RA/Dec in degrees to map coordinates in `[-1, 1]`, with
+    /// RA 180 deg at the centre, RA increasing to the left and north up.
+    pub fn project(&self, right_ascension: f64, declination: f64) -> (f64, f64) {
+        let lambda = (180.0 - right_ascension.rem_euclid(360.0)).to_radians();
+        let phi = declination.clamp(-90.0, 90.0).to_radians();
+        match self {
+            Projection::Equirectangular => (lambda / PI, phi / FRAC_PI_2),
+            Projection::Aitoff => {
+                let alpha = (phi.cos() * (lambda / 2.0).cos()).acos();
+                let sinc = if alpha == 0.0 {
+                    1.0
+                } else {
+                    alpha.sin() / alpha
+                };
+                let x = 2.0 * phi.cos() * (lambda / 2.0).sin() / sinc;
+                let y = phi.sin() / sinc;
+                (x / PI, y / FRAC_PI_2)
+            }
+        }
+    }
+}
+
+/// Red at zero confidence through yellow to green at full confidence.
+pub fn confidence_color(confidence: f64) -> Color32 {
+    let c = confidence.clamp(0.0, 1.0) as f32;
+    let (r, g) = if c < 0.5 {
+        (1.0, c * 2.0)
+    } else {
+        (2.0 - c * 2.0, 1.0)
+    };
+    Color32::from_rgb((r * 255.0) as u8, (g * 255.0) as u8, 0)
+}
+
+/// Draws the reference catalogue and the star tracker pointing track.
+pub fn show(ui: &mut egui::Ui, projection: Projection, history: &TelemetryHistory) {
+    let width = ui.available_width();
+    let size = egui::vec2(width, width / MAP_ASPECT);
+    let (rect, response) = ui.allocate_exact_size(size, Sense::hover());
+    let painter = ui.painter_at(rect);
+    let visuals = ui.visuals();
+    let grid_color = visuals.weak_text_color().gamma_multiply(0.4);
+    let text_color = visuals.weak_text_color();
+    let font = FontId::proportional(10.0);
+
+    let to_screen = |ra: f64, dec: f64| {
+        let (x, y) = projection.project(ra, dec);
+        Pos2::new(
+            rect.center().x + x as f32 * rect.width() / 2.0,
+            rect.center().y - y as f32 * rect.height() / 2.0,
+        )
+    };
+    let polyline = |points: Vec<Pos2>, stroke: Stroke| {
+        painter.add(egui::Shape::line(points, stroke));
+    };
+
+    painter.rect_filled(rect, 2.0, visuals.extreme_bg_color);
+
+    // Grid: RA every 2 h, Dec every 30 deg
+    let grid = Stroke::new(0.5, grid_color);
+    for hours in (0..=24).step_by(2) {
+        let ra = hours as f64 * 15.0;
+        let ra = if hours == 24 { 359.999 } else { ra };
+        polyline(
+            (-90..=90)
+                .step_by(5)
+                .map(|dec| to_screen(ra, dec as f64))
+                .collect(),
+            grid,
+        );
+        if hours % 6 == 0 && hours < 24 {
+            painter.text(
+                to_screen(ra, 0.0),
+                Align2::LEFT_BOTTOM,
+                format!("{}h", hours),
+                font.clone(),
+                text_color,
+            );
+        }
+    }
+    for dec in (-60..=60).step_by(30) {
+        polyline(
+            (0..=72)
+                .map(|i| to_screen((i as f64 * 5.0).min(359.999), dec as f64))
+                .collect(),
+            grid,
+        );
+        if dec != 0 {
+            painter.text(
+                to_screen(180.0, dec as f64),
+                Align2::LEFT_BOTTOM,
+                format!("{:+}", dec),
+                font.clone(),
+                text_color,
+            );
+        }
+    }
+
+    // Reference catalogue
+    for star in &BRIGHT_STARS {
+        let pos = to_screen(star.right_ascension, star.declination);
+        let radius = (3.0 - star.magnitude as f32 * 0.6).clamp(1.0, 4.0);
+        painter.circle_filled(pos, radius, Color32::GRAY);
+        painter.text(
+            pos + egui::vec2(3.0, -3.0),
+            Align2::LEFT_BOTTOM,
+            star.name,
+            font.clone(),
+            Color32::GRAY,
+        );
+    }
+
+    // Pointing track, split where it wraps around the RA seam
+    let mut previous: Option<(f64, Pos2)> = None;
+    for (_, reading) in history.pointing() {
+        let ra = reading.coordinates.right_ascension;
+        let pos = to_screen(ra, reading.coordinates.declination);
+        if let Some((prev_ra, prev_pos)) = previous {
+            if (ra - prev_ra).abs() < 180.0 {
+                painter.line_segment([prev_pos, pos], Stroke::new(1.0, Color32::LIGHT_BLUE));
+            }
+        }
+        previous = Some((ra, pos));
+    }
+
+    let mut labelled: Vec<&str> = Vec::new();
+    for (_, reading) in history.pointing().rev() {
+        let c = &reading.coordinates;
+        let pos = to_screen(c.right_ascension, c.declination);
+        painter.circle_filled(pos, 3.0, confidence_color(reading.confidence));
+        if let Some(target) = reading.target_id.as_deref() {
+            if !labelled.contains(&target) {
+                labelled.push(target);
+                painter.text(
+                    pos + egui::vec2(4.0, 4.0),
+                    Align2::LEFT_TOP,
+                    target,
+                    font.clone(),
+                    visuals.strong_text_color(),
+                );
+            }
+        }
+    }
+    if let Some((_, latest)) = history.pointing().last() {
+        let c = &latest.coordinates;
+        let pos = to_screen(c.right_ascension, c.declination);
+        painter.circle_stroke(pos, 6.0, Stroke::new(1.5, visuals.strong_text_color()));
+    }
+
+    // Nearest reading under the pointer
+    if let Some(pointer) = response.hover_pos() {
+        let nearest = history
+            .pointing()
+            .map(|(t, r)| {
+                let c = &r.coordinates;
+                (
+                    to_screen(c.right_ascension, c.declination).distance(pointer),
+                    t,
+                    r,
+                )
+            })
+            .filter(|(d, _, _)| *d < 8.0)
+            .min_by(|a, b| a.0.total_cmp(&b.0));
+        if let Some((_, t, r)) = nearest {
+            painter.text(
+                rect.center_top() + egui::vec2(0.0, 2.0),
+                Align2::CENTER_TOP,
+                format!(
+                    "t={} RA {:.3} Dec {:+.3} confidence {:.2} {}",
+                    t,
+                    r.coordinates.right_ascension,
+                    r.coordinates.declination,
+                    r.confidence,
+                    r.target_id.as_deref().unwrap_or("")
+                ),
+                font,
+                visuals.strong_text_color(),
+            );
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use crate::models::TelemetryPacket;
+use std::fmt;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::mpsc::Sender;
+use std::time::Duration;
+
+/// How often background source threads wake up to check for a stop request.
+pub const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Least time between two status updates that only report more bytes.
+pub const STATUS_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConnectionState {
+    /// Bound and waiting for datagrams or an incoming connection.
+    Listening,
+    Connecting,
+    Connected(SocketAddr),
+    /// Waiting `delay` before reconnect attempt number `attempt`.
+    Retrying {
+        attempt: u32,
+        delay: Duration,
+    },
+    Stopped,
+}
+
+impl fmt::Display for ConnectionState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConnectionState::Listening => write!(f, "Listening"),
+            ConnectionState::Connecting => write!(f, "Connecting"),
+            ConnectionState::Connected(peer) => write!(f, "Connected to {}", peer),
+            ConnectionState::Retrying { attempt, delay } => write!(
+                f,
+                "Retrying in {:.1}s (attempt {})",
+                delay.as_secs_f64(),
+                attempt
+            ),
+            ConnectionState::Stopped => write!(f, "Stopped"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceStatus {
+    pub state: ConnectionState,
+    pub bytes_received: u64, // Since the source was started
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SourceEvent {
+    Packet(TelemetryPacket),
+    Error(String),
+    /// A frame that failed the CRC check, or lacked a required trailer.
+    Corrupted(String),
+    Status(SourceStatus),
+}
+
+/// A live packet source that delivers parsed packets from a background thread.
+pub trait TelemetrySource: Send {
+    /// Short description for logs and the dashboard, e.g. `UDP 0.0.0.0:5005`.
+    fn name(&self) -> String;
+
+    /// Starts the background thread. Events are sent to `sink` until `stop`
+    /// is called or the receiving side is dropped.
+    fn start(&mut self, sink: Sender<SourceEvent>) -> io::Result<()>;
+
+    /// Stops the background thread and waits for it to exit.
+    fn stop(&mut self);
+
+    fn is_running(&self) -> bool;
+}
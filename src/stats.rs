@@ -0,0 +1,91 @@
+use crate::models::{Subsystem, TelemetryPacket};
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct FieldStats {
+    pub count: usize,
+    pub min: f64,
+    pub max: f64,
+    pub sum: f64,
+}
+
+impl FieldStats {
+    fn new(value: f64) -> Self {
+        Self {
+            count: 1,
+            min: value,
+            max: value,
+            sum: value,
+        }
+    }
+
+    fn add(&mut self, value: f64) {
+        self.count += 1;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.sum += value;
+    }
+
+    pub fn mean(&self) -> f64 {
+        self.sum / self.count as f64
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SubsystemStats {
+    pub packets: usize,
+    pub fields: Vec<(String, FieldStats)>, // In payload field order
+}
+
+/// Per-subsystem packet counts and min/max/mean for every numeric field.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct TelemetryStats {
+    pub subsystems: Vec<(Subsystem, SubsystemStats)>, // In order first seen
+    pub errors: usize,
+    pub corrupted: usize, // Frames that failed the CRC check, not in `errors`
+}
+
+impl TelemetryStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, packet: &TelemetryPacket) {
+        let index = match self
+            .subsystems
+            .iter()
+            .position(|(s, _)| *s == packet.subsystem)
+        {
+            Some(index) => index,
+            None => {
+                self.subsystems
+                    .push((packet.subsystem.clone(), SubsystemStats::default()));
+                self.subsystems.len() - 1
+            }
+        };
+        let stats = &mut self.subsystems[index].1;
+        stats.packets += 1;
+
+        for (name, value) in packet.fields() {
+            match stats.fields.iter_mut().find(|(n, _)| *n == name) {
+                Some((_, field)) => field.add(value),
+                None => stats.fields.push((name, FieldStats::new(value))),
+            }
+        }
+    }
+
+    pub fn add_error(&mut self) {
+        self.errors += 1;
+    }
+
+    pub fn add_corrupted(&mut self) {
+        self.corrupted += 1;
+    }
+
+    pub fn get(&self, subsystem: &Subsystem) -> Option<&SubsystemStats> {
+        self.subsystems
+            .iter()
+            .find(|(s, _)| s == subsystem)
+            .map(|(_, stats)| stats)
+    }
+}
@@ -0,0 +1,114 @@
+use crate::alerts::{AlertRecord, AlertState};
+use crate::models::{Subsystem, TelemetryPacket};
+use crate::monitor::AlertLevel;
+use std::time::{Duration, Instant};
+
+/// Time without a packet after which a subsystem is shown as stale.
+pub const DEFAULT_STALE_AFTER: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Health {
+    /// No packet received yet.
+    NoData,
+    /// No packet for longer than the stale timeout.
+    Stale,
+    Nominal,
+    Warning,
+    Critical,
+}
+
+/// Latest packet from one subsystem and when it arrived.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubsystemStatus {
+    pub subsystem: Subsystem,
+    pub latest: Option<TelemetryPacket>,
+    pub received: Option<Instant>,
+}
+
+impl SubsystemStatus {
+    pub fn age(&self, now: Instant) -> Option<Duration> {
+        self.received.map(|r| now.saturating_duration_since(r))
+    }
+}
+
+/// One status tile per subsystem, in `Subsystem` order.
+#[derive(Debug, Clone)]
+pub struct StatusBoard {
+    pub tiles: Vec<SubsystemStatus>,
+    pub stale_after: Duration,
+}
+
+impl Default for StatusBoard {
+    fn default() -> Self {
+        let tiles = [
+            Subsystem::Power,
+            Subsystem::Thermal,
+            Subsystem::Aocs,
+            Subsystem::StarTracker,
+        ]
+        .into_iter()
+        .map(|subsystem| SubsystemStatus {
+            subsystem,
+            latest: None,
+            received: None,
+        })
+        .collect();
+
+        Self {
+            tiles,
+            stale_after: DEFAULT_STALE_AFTER,
+        }
+    }
+}
+
+impl StatusBoard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn update(&mut self, packet: &TelemetryPacket, now: Instant) {
+        if let Some(tile) = self
+            .tiles
+            .iter_mut()
+            .find(|t| t.subsystem == packet.subsystem)
+        {
+            tile.latest = Some(packet.clone());
+            tile.received = Some(now);
+        }
+    }
+
+    pub fn get(&self, subsystem: &Subsystem) -> Option<&SubsystemStatus> {
+        self.tiles.iter().find(|t| t.subsystem == *subsystem)
+    }
+
+    /// Health of one subsystem: grey when there is no fresh data, otherwise
+    /// the highest level among its alerts that are still in breach.
+    pub fn health(&self, subsystem: &Subsystem, alerts: &[AlertRecord], now: Instant) -> Health {
+        let Some(age) = self.get(subsystem).and_then(|t| t.age(now)) else {
+            return Health::NoData;
+        };
+        if age > self.stale_after {
+            return Health::Stale;
+        }
+
+        let in_breach = |a: &&AlertRecord| {
+            a.subsystem == *subsystem
+                && matches!(a.state, AlertState::Raised | AlertState::Acknowledged)
+        };
+        let levels: Vec<AlertLevel> = alerts.iter().filter(in_breach).map(|a| a.level).collect();
+        if levels.contains(&AlertLevel::Critical) {
+            Health::Critical
+        } else if levels.contains(&AlertLevel::Warning) {
+            Health::Warning
+        } else {
+            Health::Nominal
+        }
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self {
+            stale_after: self.stale_after,
+            ..Self::default()
+        };
+    }
+}
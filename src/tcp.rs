@@ -0,0 +1,355 @@
+use crate::crc::CrcMode;
+use crate::definition::PacketDefinitions;
+use crate::parser::PacketFramer;
+use crate::source::{ConnectionState, SourceEvent, SourceStatus, TelemetrySource, POLL_INTERVAL};
+use std::io::{self, ErrorKind, Read};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Exponential reconnect delay: `initial`, doubled per failed attempt, capped at `max`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Backoff {
+    pub initial: Duration,
+    pub max: Duration,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            initial: Duration::from_millis(500),
+            max: Duration::from_secs(10),
+        }
+    }
+}
+
+impl Backoff {
+    /// Delay before reconnect attempt `attempt`, counting from 1.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.initial.saturating_mul(factor).min(self.max)
+    }
+}
+
+/// State shared by the TCP sources' background threads.
+struct Link {
+    sink: Sender<SourceEvent>,
+    running: Arc<AtomicBool>,
+    bytes_received: u64,
+    crc: CrcMode,
+    definitions: Arc<PacketDefinitions>,
+}
+
+impl Link {
+    fn is_running(&self) -> bool {
+        self.running.load(Ordering::SeqCst)
+    }
+
+    /// Sends an event; a dropped receiver stops the source.
+    fn send(&self, event: SourceEvent) {
+        if self.sink.send(event).is_err() {
+            self.running.store(false, Ordering::SeqCst);
+        }
+    }
+
+    fn status(&self, state: ConnectionState) {
+        self.send(SourceEvent::Status(SourceStatus {
+            state,
+            bytes_received: self.bytes_received,
+        }));
+    }
+
+    /// Sleeps for `duration` in short steps so a stop request is not delayed.
+    fn sleep(&self, duration: Duration) {
+        let mut remaining = duration;
+        while self.is_running() && !remaining.is_zero() {
+            let step = remaining.min(POLL_INTERVAL);
+            std::thread::sleep(step);
+            remaining -= step;
+        }
+    }
+
+    /// Reads length-framed packets from `stream` until it closes, fails or
+    /// the source is stopped.
+    fn read_stream(&mut self, mut stream: TcpStream) {
+        let peer = match stream.peer_addr() {
+            Ok(peer) => peer,
+            Err(e) => {
+                self.send(SourceEvent::Error(format!("TCP peer error: {}", e)));
+                return;
+            }
+        };
+        if let Err(e) = stream.set_read_timeout(Some(POLL_INTERVAL)) {
+            self.send(SourceEvent::Error(format!("TCP socket error: {}", e)));
+            return;
+        }
+        self.status(ConnectionState::Connected(peer));
+
+        let mut framer =
+            PacketFramer::with_crc(self.crc).definitions(Arc::clone(&self.definitions));
+        let mut buf = [0u8; 4096];
+        while self.is_running() {
+            match stream.read(&mut buf) {
+                Ok(0) => {
+                    self.send(SourceEvent::Error(format!(
+                        "TCP connection to {} closed",
+                        peer
+                    )));
+                    break;
+                }
+                Ok(len) => {
+                    self.bytes_received += len as u64;
+                    framer.push(&buf[..len]);
+                    while let Some(result) = framer.next_packet() {
+                        self.send(match result {
+                            Ok((packet, _)) => SourceEvent::Packet(packet),
+                            Err(e) if e.is_corruption() => {
+                                SourceEvent::Corrupted(format!("Corrupted packet: {}", e))
+                            }
+                            Err(e) => SourceEvent::Error(format!("Error parsing packet: {}", e)),
+                        });
+                    }
+                    self.status(ConnectionState::Connected(peer));
+                }
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+                Err(e) => {
+                    self.send(SourceEvent::Error(format!("TCP receive error: {}", e)));
+                    break;
+                }
+            }
+        }
+        if framer.pending() > 0 {
+            self.send(SourceEvent::Error(format!(
+                "Dropped {} bytes of incomplete packet",
+                framer.pending()
+            )));
+        }
+    }
+}
+
+/// Handle to a source's background thread.
+struct Worker {
+    running: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+    crc: CrcMode,
+    definitions: Arc<PacketDefinitions>,
+}
+
+impl Default for Worker {
+    fn default() -> Self {
+        Self {
+            running: Arc::default(),
+            handle: None,
+            crc: CrcMode::default(),
+            definitions: PacketDefinitions::built_in(),
+        }
+    }
+}
+
+impl Worker {
+    fn spawn(&mut self, sink: Sender<SourceEvent>, body: impl FnOnce(&mut Link) + Send + 'static) {
+        let running = Arc::clone(&self.running);
+        running.store(true, Ordering::SeqCst);
+        let crc = self.crc;
+        let definitions = Arc::clone(&self.definitions);
+        self.handle = Some(std::thread::spawn(move || {
+            let mut link = Link {
+                sink,
+                running,
+                bytes_received: 0,
+                crc,
+                definitions,
+            };
+            body(&mut link);
+            link.running.store(false, Ordering::SeqCst);
+            link.status(ConnectionState::Stopped);
+        }));
+    }
+
+    fn stop(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+
+    fn is_running(&self) -> bool {
+        self.running.load(Ordering::SeqCst)
+    }
+}
+
+/// Connects to a gateway and reconnects with backoff whenever the link drops.
+pub struct TcpClientSource {
+    addr: SocketAddr,
+    backoff: Backoff,
+    worker: Worker,
+}
+
+impl TcpClientSource {
+    pub fn new(addr: SocketAddr, backoff: Backoff) -> Self {
+        Self {
+            addr,
+            backoff,
+            worker: Worker::default(),
+        }
+    }
+
+    pub fn crc(mut self, crc: CrcMode) -> Self {
+        self.worker.crc = crc;
+        self
+    }
+
+    /// Decodes payloads with `definitions` instead of the built-in layouts.
+    pub fn definitions(mut self, definitions: Arc<PacketDefinitions>) -> Self {
+        self.worker.definitions = definitions;
+        self
+    }
+}
+
+impl TelemetrySource for TcpClientSource {
+    fn name(&self) -> String {
+        format!("TCP client {}", self.addr)
+    }
+
+    fn start(&mut self, sink: Sender<SourceEvent>) -> io::Result<()> {
+        if self.is_running() {
+            return Err(io::Error::other("TCP client already running"));
+        }
+
+        let (addr, backoff) = (self.addr, self.backoff);
+        self.worker.spawn(sink, move |link| {
+            let mut attempt = 0;
+            while link.is_running() {
+                link.status(ConnectionState::Connecting);
+                match TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT) {
+                    Ok(stream) => {
+                        attempt = 0;
+                        link.read_stream(stream);
+                    }
+                    Err(e) => link.send(SourceEvent::Error(format!(
+                        "TCP connect to {} failed: {}",
+                        addr, e
+                    ))),
+                }
+                if !link.is_running() {
+                    break;
+                }
+
+                attempt += 1;
+                let delay = backoff.delay(attempt);
+                link.status(ConnectionState::Retrying { attempt, delay });
+                link.sleep(delay);
+            }
+        });
+        Ok(())
+    }
+
+    fn stop(&mut self) {
+        self.worker.stop();
+    }
+
+    fn is_running(&self) -> bool {
+        self.worker.is_running()
+    }
+}
+
+impl Drop for TcpClientSource {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Listens for a gateway connection and serves one connection at a time.
+pub struct TcpServerSource {
+    addr: SocketAddr,
+    local_addr: Option<SocketAddr>,
+    worker: Worker,
+}
+
+impl TcpServerSource {
+    pub fn new(addr: SocketAddr) -> Self {
+        Self {
+            addr,
+            local_addr: None,
+            worker: Worker::default(),
+        }
+    }
+
+    /// Listens on all interfaces on `port`.
+    pub fn on_port(port: u16) -> Self {
+        Self::new(SocketAddr::from(([0, 0, 0, 0], port)))
+    }
+
+    pub fn crc(mut self, crc: CrcMode) -> Self {
+        self.worker.crc = crc;
+        self
+    }
+
+    /// Decodes payloads with `definitions` instead of the built-in layouts.
+    pub fn definitions(mut self, definitions: Arc<PacketDefinitions>) -> Self {
+        self.worker.definitions = definitions;
+        self
+    }
+
+    /// Address the listener is bound to once started; useful with port 0.
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.local_addr
+    }
+}
+
+impl TelemetrySource for TcpServerSource {
+    fn name(&self) -> String {
+        format!("TCP server {}", self.local_addr.unwrap_or(self.addr))
+    }
+
+    fn start(&mut self, sink: Sender<SourceEvent>) -> io::Result<()> {
+        if self.is_running() {
+            return Err(io::Error::other("TCP server already running"));
+        }
+
+        let listener = TcpListener::bind(self.addr)?;
+        listener.set_nonblocking(true)?;
+        self.local_addr = Some(listener.local_addr()?);
+
+        self.worker.spawn(sink, move |link| {
+            link.status(ConnectionState::Listening);
+            while link.is_running() {
+                match listener.accept() {
+                    Ok((stream, _)) => {
+                        if let Err(e) = stream.set_nonblocking(false) {
+                            link.send(SourceEvent::Error(format!("TCP socket error: {}", e)));
+                            continue;
+                        }
+                        link.read_stream(stream);
+                        link.status(ConnectionState::Listening);
+                    }
+                    Err(e) if e.kind() == ErrorKind::WouldBlock => link.sleep(POLL_INTERVAL),
+                    Err(e) => {
+                        link.send(SourceEvent::Error(format!("TCP accept error: {}", e)));
+                        link.sleep(POLL_INTERVAL);
+                    }
+                }
+            }
+        });
+        Ok(())
+    }
+
+    fn stop(&mut self) {
+        self.worker.stop();
+    }
+
+    fn is_running(&self) -> bool {
+        self.worker.is_running()
+    }
+}
+
+impl Drop for TcpServerSource {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
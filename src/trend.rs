@@ -0,0 +1,181 @@
+use crate::models::TelemetryPacket;
+use crate::monitor::{Alert, AlertLevel};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+fn default_level() -> AlertLevel {
+    AlertLevel::Warning
+}
+
+/// Side from which a predicted threshold is approached.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    Rising,  // Nominal below the threshold, e.g. a temperature limit
+    Falling, // Nominal above the threshold, e.g. a battery minimum
+}
+
+/// Rate-of-change and time-to-threshold check on one telemetry field.
+///
+/// The rate is the least-squares slope of the samples whose packet timestamps
+/// fall within `window_seconds` of the newest one. If `predict_threshold` is
+/// set, a predictive Warning is raised when the value is still on the nominal
+/// side, moves toward the threshold in `predict_direction`, and linear
+/// extrapolation reaches the threshold within `horizon_seconds`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrendCheck {
+    pub name: String, // Display name, e.g. "Battery"
+    pub field: String,
+    #[serde(default)]
+    pub unit: String,
+    pub window_seconds: u64,
+    #[serde(default)]
+    pub max_rise_per_minute: Option<f64>,
+    #[serde(default)]
+    pub max_fall_per_minute: Option<f64>, // Magnitude, e.g. 2.0 for -2.0/min
+    #[serde(default = "default_level")]
+    pub level: AlertLevel,
+    #[serde(default)]
+    pub predict_threshold: Option<f64>,
+    #[serde(default)]
+    pub predict_direction: Option<Direction>,
+    #[serde(default)]
+    pub horizon_seconds: u64,
+}
+
+impl TrendCheck {
+    pub fn new(name: &str, field: &str, unit: &str, window_seconds: u64) -> Self {
+        Self {
+            name: name.to_string(),
+            field: field.to_string(),
+            unit: unit.to_string(),
+            window_seconds,
+            max_rise_per_minute: None,
+            max_fall_per_minute: None,
+            level: AlertLevel::Warning,
+            predict_threshold: None,
+            predict_direction: None,
+            horizon_seconds: 0,
+        }
+    }
+
+    pub fn max_rise(mut self, per_minute: f64) -> Self {
+        self.max_rise_per_minute = Some(per_minute);
+        self
+    }
+
+    pub fn max_fall(mut self, per_minute: f64) -> Self {
+        self.max_fall_per_minute = Some(per_minute);
+        self
+    }
+
+    pub fn predict(mut self, direction: Direction, threshold: f64, horizon_seconds: u64) -> Self {
+        self.predict_threshold = Some(threshold);
+        self.predict_direction = Some(direction);
+        self.horizon_seconds = horizon_seconds;
+        self
+    }
+
+    /// Adds the packet's sample to `history` and returns any trend alerts.
+    /// A timestamp older than the newest sample restarts the history.
+    pub fn update(
+        &self,
+        history: &mut VecDeque<(u64, f64)>,
+        packet: &TelemetryPacket,
+    ) -> Vec<Alert> {
+        let Some(value) = packet.field(&self.field) else {
+            return Vec::new();
+        };
+        let now = packet.timestamp;
+        if history.back().is_some_and(|&(t, _)| t > now) {
+            history.clear();
+        }
+        history.push_back((now, value));
+        while history
+            .front()
+            .is_some_and(|&(t, _)| now - t > self.window_seconds)
+        {
+            history.pop_front();
+        }
+
+        let Some(slope) = slope(history) else {
+            return Vec::new();
+        };
+        let per_minute = slope * 60.0;
+        let mut alerts = Vec::new();
+
+        if let Some(max) = self.max_rise_per_minute.filter(|m| per_minute > *m) {
+            alerts.push(self.alert(
+                "rise",
+                self.level,
+                format!(
+                    "{} Rising: {:.2} {}/min (Limit: {:.2} {}/min)",
+                    self.name, per_minute, self.unit, max, self.unit
+                ),
+                now,
+            ));
+        }
+        if let Some(max) = self.max_fall_per_minute.filter(|m| -per_minute > *m) {
+            alerts.push(self.alert(
+                "fall",
+                self.level,
+                format!(
+                    "{} Falling: {:.2} {}/min (Limit: -{:.2} {}/min)",
+                    self.name, per_minute, self.unit, max, self.unit
+                ),
+                now,
+            ));
+        }
+        if let (Some(threshold), Some(direction)) = (self.predict_threshold, self.predict_direction)
+        {
+            let approaching = match direction {
+                Direction::Rising => value < threshold && slope > 0.0,
+                Direction::Falling => value > threshold && slope < 0.0,
+            };
+            let seconds = (threshold - value) / slope;
+            if approaching && seconds <= self.horizon_seconds as f64 {
+                alerts.push(self.alert(
+                    "prediction",
+                    AlertLevel::Warning,
+                    format!(
+                        "{} Predicted to Reach {:.2}{} in {:.0} s",
+                        self.name, threshold, self.unit, seconds
+                    ),
+                    now,
+                ));
+            }
+        }
+        alerts
+    }
+
+    fn alert(&self, kind: &str, level: AlertLevel, message: String, timestamp: u64) -> Alert {
+        Alert {
+            rule: format!("{}.{}", self.name, kind),
+            field: self.field.clone(),
+            level,
+            message,
+            timestamp,
+        }
+    }
+}
+
+/// Least-squares slope in units per second, or `None` if the samples do not
+/// span any time.
+fn slope(samples: &VecDeque<(u64, f64)>) -> Option<f64> {
+    let (first, _) = *samples.front()?;
+    let n = samples.len() as f64;
+    let mean_t = samples
+        .iter()
+        .map(|&(t, _)| (t - first) as f64)
+        .sum::<f64>()
+        / n;
+    let mean_v = samples.iter().map(|&(_, v)| v).sum::<f64>() / n;
+
+    let (mut cov, mut var) = (0.0, 0.0);
+    for &(t, v) in samples {
+        let dt = (t - first) as f64 - mean_t;
+        cov += dt * (v - mean_v);
+        var += dt * dt;
+    }
+    (var > 0.0).then(|| cov / var)
+}
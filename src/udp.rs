@@ -0,0 +1,179 @@
+use crate::crc::CrcMode;
+use crate::definition::PacketDefinitions;
+use crate::parser::Parser;
+use crate::source::{
+    ConnectionState, SourceEvent, SourceStatus, TelemetrySource, POLL_INTERVAL, STATUS_INTERVAL,
+};
+use std::io::{self, ErrorKind};
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// Longest pause after repeated receive errors, which double the pause
+/// from `POLL_INTERVAL`.
+const MAX_ERROR_PAUSE: Duration = Duration::from_secs(5);
+
+/// Receives one packet per datagram and parses it with `Parser::parse_using`.
+pub struct UdpSource {
+    addr: SocketAddr,
+    crc: CrcMode,
+    definitions: Arc<PacketDefinitions>,
+    local_addr: Option<SocketAddr>,
+    running: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl UdpSource {
+    pub fn new(addr: SocketAddr) -> Self {
+        Self {
+            addr,
+            crc: CrcMode::default(),
+            definitions: PacketDefinitions::built_in(),
+            local_addr: None,
+            running: Arc::new(AtomicBool::new(false)),
+            handle: None,
+        }
+    }
+
+    /// Listens on all interfaces on `port`.
+    pub fn on_port(port: u16) -> Self {
+        Self::new(SocketAddr::from(([0, 0, 0, 0], port)))
+    }
+
+    pub fn crc(mut self, crc: CrcMode) -> Self {
+        self.crc = crc;
+        self
+    }
+
+    /// Decodes payloads with `definitions` instead of the built-in layouts.
+    pub fn definitions(mut self, definitions: Arc<PacketDefinitions>) -> Self {
+        self.definitions = definitions;
+        self
+    }
+
+    /// Address the socket is bound to once started; useful with port 0.
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.local_addr
+    }
+}
+
+impl TelemetrySource for UdpSource {
+    fn name(&self) -> String {
+        format!("UDP {}", self.local_addr.unwrap_or(self.addr))
+    }
+
+    fn start(&mut self, sink: Sender<SourceEvent>) -> io::Result<()> {
+        if self.is_running() {
+            return Err(io::Error::other("UDP source already running"));
+        }
+
+        let socket = UdpSocket::bind(self.addr)?;
+        socket.set_read_timeout(Some(POLL_INTERVAL))?;
+        self.local_addr = Some(socket.local_addr()?);
+
+        let crc = self.crc;
+        let definitions = Arc::clone(&self.definitions);
+        let running = Arc::clone(&self.running);
+        running.store(true, Ordering::SeqCst);
+        self.handle = Some(std::thread::spawn(move || {
+            let mut buf = [0u8; 65535];
+            let mut bytes_received = 0;
+            let status = |state, bytes_received| {
+                SourceEvent::Status(SourceStatus {
+                    state,
+                    bytes_received,
+                })
+            };
+            let _ = sink.send(status(ConnectionState::Listening, 0));
+            // The state only changes on stop, so the byte count is reported
+            // at most every `STATUS_INTERVAL` and once the datagrams pause.
+            let mut bytes_reported = 0;
+            let mut last_status = Instant::now();
+            let mut errors = 0;
+            while running.load(Ordering::SeqCst) {
+                let event = match socket.recv_from(&mut buf) {
+                    Ok((len, _)) => {
+                        errors = 0;
+                        bytes_received += len as u64;
+                        match Parser::parse_using(&buf[..len], crc, &definitions) {
+                            Ok(packet) => SourceEvent::Packet(packet),
+                            Err(e) if e.is_corruption() => {
+                                SourceEvent::Corrupted(format!("Corrupted datagram: {}", e))
+                            }
+                            Err(e) => SourceEvent::Error(format!("Error parsing datagram: {}", e)),
+                        }
+                    }
+                    Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                        if bytes_reported != bytes_received {
+                            last_status = Instant::now();
+                            bytes_reported = bytes_received;
+                            let event = status(ConnectionState::Listening, bytes_received);
+                            if sink.send(event).is_err() {
+                                break;
+                            }
+                        }
+                        continue;
+                    }
+                    Err(e) => {
+                        // A persistent error would otherwise return at once
+                        // and flood the sink
+                        errors += 1;
+                        let pause = POLL_INTERVAL
+                            .saturating_mul(2u32.saturating_pow(errors - 1))
+                            .min(MAX_ERROR_PAUSE);
+                        let event = SourceEvent::Error(format!(
+                            "UDP receive error, pausing {:.1}s: {}",
+                            pause.as_secs_f64(),
+                            e
+                        ));
+                        if sink.send(event).is_err() {
+                            break;
+                        }
+                        let mut paused = Duration::ZERO;
+                        while running.load(Ordering::SeqCst) && paused < pause {
+                            std::thread::sleep(POLL_INTERVAL);
+                            paused += POLL_INTERVAL;
+                        }
+                        continue;
+                    }
+                };
+                if sink.send(event).is_err() {
+                    break;
+                }
+                if bytes_reported != bytes_received && last_status.elapsed() >= STATUS_INTERVAL {
+                    last_status = Instant::now();
+                    bytes_reported = bytes_received;
+                    if sink
+                        .send(status(ConnectionState::Listening, bytes_received))
+                        .is_err()
+                    {
+                        break;
+                    }
+                }
+            }
+            running.store(false, Ordering::SeqCst);
+            let _ = sink.send(status(ConnectionState::Stopped, bytes_received));
+        }));
+        Ok(())
+    }
+
+    fn stop(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+
+    fn is_running(&self) -> bool {
+        self.running.load(Ordering::SeqCst)
+    }
+}
+
+impl Drop for UdpSource {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
@@ -0,0 +1,202 @@
+use crate::definition::PacketDefinitions;
+use crate::models::{Subsystem, TelemetryPacket};
+use crate::monitor::{Alert, AlertLevel};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+fn default_level() -> AlertLevel {
+    AlertLevel::Critical
+}
+
+/// Expected reporting behaviour of one subsystem.
+///
+/// The subsystem is stale once no packet has arrived for `timeout_seconds`
+/// of wall-clock time, and a data gap is reported when consecutive packet
+/// timestamps are more than `max_gap_seconds` apart.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReportingPeriod {
+    pub subsystem: Subsystem,
+    pub timeout_seconds: u64,
+    #[serde(default)]
+    pub max_gap_seconds: Option<u64>,
+    #[serde(default = "default_level")]
+    pub level: AlertLevel, // Level of the stale alert
+}
+
+impl ReportingPeriod {
+    pub fn new(subsystem: Subsystem, timeout_seconds: u64) -> Self {
+        Self {
+            subsystem,
+            timeout_seconds,
+            max_gap_seconds: None,
+            level: default_level(),
+        }
+    }
+
+    pub fn max_gap(mut self, seconds: u64) -> Self {
+        self.max_gap_seconds = Some(seconds);
+        self
+    }
+
+    pub fn level(mut self, level: AlertLevel) -> Self {
+        self.level = level;
+        self
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct WatchState {
+    last_timestamp: Option<u64>,
+    received: Option<Instant>,
+    stale: bool, // Stale alert already raised for the current silence
+}
+
+/// Tracks when each subsystem last reported.
+///
+/// A subsystem that has not reported since the watchdog was created or
+/// reset is timed from that moment, so one that never reports still goes
+/// stale. Alerts are keyed by the subsystem's packet definition name, see
+/// `PacketDefinitions::key`.
+#[derive(Debug, Clone)]
+pub struct Watchdog {
+    pub periods: Vec<ReportingPeriod>,
+    definitions: Arc<PacketDefinitions>, // Names the subsystems
+    state: HashMap<u8, WatchState>,      // By subsystem id
+    armed: Instant,                      // Creation or last reset
+    latest: Option<(u64, Instant)>,      // Newest packet timestamp from any subsystem
+}
+
+impl Default for Watchdog {
+    fn default() -> Self {
+        Self::new(Vec::new())
+    }
+}
+
+impl Watchdog {
+    pub fn new(periods: Vec<ReportingPeriod>) -> Self {
+        Self {
+            periods,
+            definitions: PacketDefinitions::built_in(),
+            state: HashMap::new(),
+            armed: Instant::now(),
+            latest: None,
+        }
+    }
+
+    /// Keys alerts by the names of the packets in `definitions`.
+    pub fn set_definitions(&mut self, definitions: Arc<PacketDefinitions>) {
+        self.definitions = definitions;
+    }
+
+    pub fn reset(&mut self) {
+        self.state.clear();
+        self.armed = Instant::now();
+        self.latest = None;
+    }
+
+    /// Records a packet received at `now` and returns alerts for a data gap
+    /// or a timestamp that jumped backwards since the previous packet from
+    /// the same subsystem.
+    pub fn observe(&mut self, packet: &TelemetryPacket, now: Instant) -> Vec<Alert> {
+        let period = self
+            .periods
+            .iter()
+            .find(|p| p.subsystem == packet.subsystem);
+        let key = self.definitions.key(&packet.subsystem);
+        let state = self.state.entry(packet.subsystem.id()).or_default();
+        let mut alerts = Vec::new();
+
+        if let Some(last) = state.last_timestamp {
+            if packet.timestamp < last {
+                alerts.push(Alert {
+                    rule: format!("{}.time_reversal", key),
+                    field: key.to_string(),
+                    level: AlertLevel::Warning,
+                    message: format!(
+                        "{:?} Timestamp Jumped Backwards: {} after {}",
+                        packet.subsystem, packet.timestamp, last
+                    ),
+                    timestamp: packet.timestamp,
+                });
+            } else if let Some(max_gap) = period.and_then(|p| p.max_gap_seconds) {
+                let gap = packet.timestamp - last;
+                if gap > max_gap {
+                    alerts.push(Alert {
+                        rule: format!("{}.gap", key),
+                        field: key.to_string(),
+                        level: AlertLevel::Warning,
+                        message: format!(
+                            "{:?} Data Gap: {} s between packets (Max: {} s)",
+                            packet.subsystem, gap, max_gap
+                        ),
+                        timestamp: packet.timestamp,
+                    });
+                }
+            }
+        }
+
+        state.last_timestamp = Some(packet.timestamp);
+        state.received = Some(now);
+        state.stale = false;
+        if self.latest.is_none_or(|(t, _)| packet.timestamp >= t) {
+            self.latest = Some((packet.timestamp, now));
+        }
+        alerts
+    }
+
+    /// Returns one alert per subsystem that has been silent for longer than
+    /// its timeout, counting from its last packet or, if it has not reported,
+    /// from creation or reset. Each silence is reported once; the next packet
+    /// from the subsystem re-arms the check.
+    pub fn check(&mut self, now: Instant) -> Vec<(Subsystem, Alert)> {
+        let mut alerts = Vec::new();
+        for period in &self.periods {
+            let key = self.definitions.key(&period.subsystem);
+            let state = self.state.entry(period.subsystem.id()).or_default();
+            let silence = now.saturating_duration_since(state.received.unwrap_or(self.armed));
+            if state.stale || silence <= Duration::from_secs(period.timeout_seconds) {
+                continue;
+            }
+
+            state.stale = true;
+            alerts.push((
+                period.subsystem.clone(),
+                Alert {
+                    rule: format!("{}.stale", key),
+                    field: key.to_string(),
+                    level: period.level,
+                    message: format!(
+                        "No {:?} Telemetry for {} s (Timeout: {} s)",
+                        period.subsystem,
+                        silence.as_secs(),
+                        period.timeout_seconds
+                    ),
+                    timestamp: estimate_timestamp(state.last_timestamp, silence, self.latest, now),
+                },
+            ));
+        }
+        alerts
+    }
+}
+
+/// Estimated spacecraft time of a check `silence` after the subsystem's last
+/// packet: from that packet's timestamp, else from the newest packet of any
+/// subsystem, else the wall clock.
+fn estimate_timestamp(
+    last: Option<u64>,
+    silence: Duration,
+    latest: Option<(u64, Instant)>,
+    now: Instant,
+) -> u64 {
+    match (last, latest) {
+        (Some(last), _) => last.saturating_add(silence.as_secs()),
+        (None, Some((timestamp, received))) => {
+            timestamp.saturating_add(now.saturating_duration_since(received).as_secs())
+        }
+        (None, None) => SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs()),
+    }
+}